@@ -3,13 +3,21 @@
 //! Equivalente Rust del script Python `busqueda_comunicaciones.py`
 
 use crate::config::Config;
+use crate::esperas;
+use crate::perfil_temporal::{crear_perfil, eliminar_perfil, PerfilEnUso, PREFIJO_PERFIL_BUSQUEDA};
+use crate::selectores::{self, localizar_por_texto, primer_visible, respaldo_por_indice};
+use crate::web_automation::{
+    autenticar_proxy, cerrar_navegador, esperar_descargas_completas, esperar_login,
+    pdfs_descargados, verificar_chrome, MonitorDescargas,
+};
 use anyhow::{Context, Result};
-use calamine::{open_workbook, Reader, Xlsx};
+use calamine::{open_workbook_auto, Data, Reader};
 use chromiumoxide::browser::{Browser, BrowserConfig};
 use futures::StreamExt;
 use regex::Regex;
-use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 /// Resultado de la búsqueda de comunicaciones
@@ -17,134 +25,237 @@ use tokio::time::sleep;
 pub struct ResultadoBusqueda {
     pub comunicaciones_descargadas: usize,
     pub total_comunicaciones: usize,
+    /// La búsqueda se detuvo antes de terminar por pedido del usuario
+    pub cancelada: bool,
 }
 
-/// Lee un archivo Excel y devuelve las comunicaciones (CCOO N°) que no tienen organismo asignado
-pub fn obtener_comunicaciones_sin_organismo(path: &Path) -> Result<Vec<String>> {
-    let mut workbook: Xlsx<_> = open_workbook(path)
-        .with_context(|| format!("No se pudo abrir el archivo Excel: {:?}", path))?;
+/// Comunicaciones leídas del Excel de referencia
+#[derive(Debug, Default)]
+pub struct ComunicacionesSinOrganismo {
+    /// Comunicaciones (CCOO N°) sin organismo asignado
+    pub comunicaciones: Vec<String>,
+    /// Filas con número de comunicación, tengan o no organismo
+    pub filas_con_ccoo: usize,
+}
+
+/// Qué hay para buscar en el Excel de referencia
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pendientes {
+    /// Hay comunicaciones sin organismo
+    Buscar,
+    /// El Excel no tiene comunicaciones: probablemente es el archivo o la hoja equivocados
+    ExcelSinComunicaciones,
+    /// Todas las comunicaciones (la cantidad indicada) ya tienen organismo
+    TodasConOrganismo(usize),
+}
+
+impl std::fmt::Display for Pendientes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pendientes::Buscar => f.write_str("Hay comunicaciones sin organismo"),
+            Pendientes::ExcelSinComunicaciones => f.write_str(
+                "El Excel no tiene comunicaciones en la columna CCOO N°; \
+                 revise que sea el archivo y la hoja correctos",
+            ),
+            Pendientes::TodasConOrganismo(cantidad) => write!(
+                f,
+                "No hay comunicaciones sin organismo en el Excel: las {} ya tienen organismo",
+                cantidad
+            ),
+        }
+    }
+}
+
+impl ComunicacionesSinOrganismo {
+    /// Distingue un Excel sin comunicaciones de uno en el que todas ya tienen organismo
+    pub fn pendientes(&self) -> Pendientes {
+        if !self.comunicaciones.is_empty() {
+            Pendientes::Buscar
+        } else if self.filas_con_ccoo == 0 {
+            Pendientes::ExcelSinComunicaciones
+        } else {
+            Pendientes::TodasConOrganismo(self.filas_con_ccoo)
+        }
+    }
+}
+
+/// Normaliza un número de comunicación al formato de SADE (`NO-2024-12345-GCABA-XXX`):
+/// quita espacios y caracteres no imprimibles, unifica los distintos tipos de guion
+/// y pasa a mayúsculas
+pub fn normalizar_ccoo(raw: &str) -> String {
+    let mut resultado = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        let c = match c {
+            '\u{2010}'..='\u{2015}' | '\u{2212}' | '_' => '-',
+            '\u{200B}'..='\u{200D}' | '\u{FEFF}' => continue,
+            c if c.is_whitespace() || c.is_control() => continue,
+            c => c,
+        };
+        // Evitar guiones repetidos
+        if c == '-' && resultado.ends_with('-') {
+            continue;
+        }
+        resultado.push(c);
+    }
+    resultado.trim_matches('-').to_uppercase()
+}
+
+/// Normaliza un encabezado para compararlo: sin espacios ni acentos, en mayúsculas
+/// y con los distintos signos de grado/ordinal (°, º, ˚) unificados
+fn normalizar_encabezado(encabezado: &str) -> String {
+    encabezado
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '\u{200B}'..='\u{200D}' | '\u{FEFF}'))
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' | 'Á' | 'À' | 'Ä' | 'Â' => 'A',
+            'é' | 'è' | 'ë' | 'ê' | 'É' | 'È' | 'Ë' | 'Ê' => 'E',
+            'í' | 'ì' | 'ï' | 'î' | 'Í' | 'Ì' | 'Ï' | 'Î' => 'I',
+            'ó' | 'ò' | 'ö' | 'ô' | 'Ó' | 'Ò' | 'Ö' | 'Ô' => 'O',
+            'ú' | 'ù' | 'ü' | 'û' | 'Ú' | 'Ù' | 'Ü' | 'Û' => 'U',
+            'º' | '˚' | 'ᵒ' => '°',
+            c => c,
+        })
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Indica si un encabezado del Excel corresponde a la columna buscada, tolerando
+/// mayúsculas, espacios, acentos y variantes del signo de número ("CCOO Nº", "CCOO N °")
+pub fn nombre_columna_coincide(header: &str, objetivo: &str) -> bool {
+    normalizar_encabezado(header) == normalizar_encabezado(objetivo)
+}
 
-    // Obtener la primera hoja
-    let sheet_name = workbook
-        .sheet_names()
-        .first()
+/// Busca la columna con el encabezado dado (ver `nombre_columna_coincide`).
+/// Si no está, el error lista los encabezados encontrados.
+fn buscar_columna(headers: &[String], nombre: &str) -> Result<usize> {
+    headers
+        .iter()
+        .position(|h| nombre_columna_coincide(h, nombre))
+        .with_context(|| {
+            let encontrados: Vec<&str> = headers
+                .iter()
+                .map(|h| h.as_str())
+                .filter(|h| !h.is_empty())
+                .collect();
+            format!(
+                "No se encontró la columna '{}'. Encabezados encontrados: {}",
+                nombre,
+                if encontrados.is_empty() {
+                    "(ninguno)".to_string()
+                } else {
+                    encontrados.join(", ")
+                }
+            )
+        })
+}
+
+/// Filas en que se busca el encabezado, por si la planilla tiene títulos arriba
+const FILAS_BUSQUEDA_ENCABEZADO: usize = 20;
+
+/// Encabezado de la columna de comunicaciones, que identifica la fila de encabezados
+const COLUMNA_CCOO: &str = "CCOO N°";
+
+/// Devuelve el índice y las celdas de la primera fila que tiene la columna
+/// `COLUMNA_CCOO`, buscando en las primeras `FILAS_BUSQUEDA_ENCABEZADO` filas
+fn buscar_fila_encabezado<'a>(
+    filas: impl Iterator<Item = &'a [Data]>,
+) -> Result<(usize, Vec<String>)> {
+    filas
+        .take(FILAS_BUSQUEDA_ENCABEZADO)
+        .map(|fila| {
+            fila.iter()
+                .map(|cell| cell.to_string().trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .enumerate()
+        .find(|(_, celdas)| celdas.iter().any(|c| nombre_columna_coincide(c, COLUMNA_CCOO)))
+        .with_context(|| {
+            format!(
+                "No se encontró la fila de encabezados con la columna '{}' en las primeras {} filas",
+                COLUMNA_CCOO, FILAS_BUSQUEDA_ENCABEZADO
+            )
+        })
+}
+
+/// Elige la hoja a leer: por nombre, por número (empezando en 1) o la primera
+/// si no se indica. Si no existe, el error lista las hojas disponibles.
+fn elegir_hoja(hojas: &[String], hoja: Option<&str>) -> Result<String> {
+    let Some(buscada) = hoja.map(str::trim).filter(|h| !h.is_empty()) else {
+        return hojas
+            .first()
+            .cloned()
+            .context("El archivo Excel no tiene hojas");
+    };
+
+    hojas
+        .iter()
+        .find(|h| h.trim().eq_ignore_ascii_case(buscada))
+        .or_else(|| {
+            buscada
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .and_then(|i| hojas.get(i))
+        })
         .cloned()
-        .context("El archivo Excel no tiene hojas")?;
+        .with_context(|| {
+            format!(
+                "No se encontró la hoja '{}'. Hojas disponibles: {}",
+                buscada,
+                hojas.join(", ")
+            )
+        })
+}
+
+/// Lee un archivo Excel y devuelve las comunicaciones (CCOO N°) que no tienen organismo
+/// asignado, junto con cuántas filas tenían comunicación.
+/// `hoja` puede ser el nombre o el número de la hoja; si es `None` se usa la primera.
+/// Acepta `.xlsx` y también los `.xls` (BIFF) de listados viejos.
+pub fn obtener_comunicaciones_sin_organismo(
+    path: &Path,
+    hoja: Option<&str>,
+) -> Result<ComunicacionesSinOrganismo> {
+    let mut workbook = open_workbook_auto(path)
+        .with_context(|| format!("No se pudo abrir el archivo Excel: {:?}", path))?;
+
+    let sheet_name = elegir_hoja(&workbook.sheet_names(), hoja)?;
 
     let range = workbook
         .worksheet_range(&sheet_name)
         .context("No se pudo leer la hoja de cálculo")?;
 
-    // Encontrar los índices de las columnas
-    let headers: Vec<String> = range
-        .rows()
-        .next()
-        .context("El archivo está vacío")?
-        .iter()
-        .map(|cell| cell.to_string().trim().to_string())
-        .collect();
-
-    let idx_ccoo = headers
-        .iter()
-        .position(|h| h == "CCOO N°")
-        .context("No se encontró la columna 'CCOO N°'")?;
+    if range.is_empty() {
+        anyhow::bail!("El archivo está vacío");
+    }
 
-    let idx_organismo = headers
-        .iter()
-        .position(|h| h == "ORGANISMO")
-        .context("No se encontró la columna 'ORGANISMO'")?;
+    // El encabezado puede no estar en la primera fila si hay un título arriba
+    let (fila_encabezado, headers) = buscar_fila_encabezado(range.rows())?;
+    let idx_ccoo = buscar_columna(&headers, COLUMNA_CCOO)?;
+    let idx_organismo = buscar_columna(&headers, "ORGANISMO")?;
 
     // Filtrar filas donde ORGANISMO está vacío
-    let mut comunicaciones: Vec<String> = Vec::new();
+    let mut lectura = ComunicacionesSinOrganismo::default();
 
-    for row in range.rows().skip(1) {
-        // Saltar encabezado
+    for row in range.rows().skip(fila_encabezado + 1) {
         let organismo = row
             .get(idx_organismo)
             .map(|c| c.to_string())
             .unwrap_or_default();
         let ccoo = row
             .get(idx_ccoo)
-            .map(|c| c.to_string().trim().to_string())
+            .map(|c| normalizar_ccoo(&c.to_string()))
             .unwrap_or_default();
 
-        // Si organismo está vacío y ccoo tiene valor
-        if organismo.trim().is_empty() && !ccoo.is_empty() {
-            comunicaciones.push(ccoo);
-        }
-    }
-
-    Ok(comunicaciones)
-}
-
-/// Configura un perfil temporal con preferencias para desactivar traducción y permitir descargas
-fn setup_custom_profile() -> Result<PathBuf> {
-    let mut temp_dir = std::env::temp_dir();
-    let unique_id = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)?
-        .as_nanos();
-    temp_dir.push(format!("busqueda_ccoo_profile_{}", unique_id));
-
-    let default_dir = temp_dir.join("Default");
-    std::fs::create_dir_all(&default_dir).context("No se pudo crear directorio del perfil")?;
-
-    let prefs_path = default_dir.join("Preferences");
-    let prefs_content = r#"{
-        "translate": { "enabled": false },
-        "profile": { 
-            "password_manager_enabled": false,
-            "default_content_setting_values": { "automatic_downloads": 1 }
-        },
-        "credentials_enable_service": false
-    }"#;
-
-    std::fs::write(prefs_path, prefs_content)
-        .context("No se pudo escribir archivo de preferencias")?;
-
-    Ok(temp_dir)
-}
-
-/// Espera hasta que no haya archivos .crdownload en la carpeta de descargas
-async fn esperar_descargas_completas(ruta_descargas: &PathBuf, timeout_secs: u64) -> bool {
-    let inicio = std::time::Instant::now();
-    let timeout = Duration::from_secs(timeout_secs);
-    let tiempo_estabilidad = Duration::from_secs(2);
-    let mut inicio_estabilidad: Option<std::time::Instant> = None;
-
-    loop {
-        let hay_pendientes = if let Ok(entries) = std::fs::read_dir(ruta_descargas) {
-            entries.filter_map(|e| e.ok()).any(|entry| {
-                entry
-                    .path()
-                    .extension()
-                    .map(|ext| ext == "crdownload")
-                    .unwrap_or(false)
-            })
-        } else {
-            false
-        };
-
-        if hay_pendientes {
-            inicio_estabilidad = None;
-        } else {
-            match inicio_estabilidad {
-                None => {
-                    inicio_estabilidad = Some(std::time::Instant::now());
-                }
-                Some(instante) => {
-                    if instante.elapsed() >= tiempo_estabilidad {
-                        return true;
-                    }
-                }
-            }
+        if ccoo.is_empty() {
+            continue;
         }
-
-        if inicio.elapsed() >= timeout {
-            return false;
+        lectura.filas_con_ccoo += 1;
+        if organismo.trim().is_empty() {
+            lectura.comunicaciones.push(ccoo);
         }
-
-        sleep(Duration::from_millis(500)).await;
     }
+
+    Ok(lectura)
 }
 
 /// Busca y descarga comunicaciones desde SADE
@@ -152,14 +263,13 @@ pub async fn buscar_comunicaciones(
     comunicaciones: &[String],
     usuario_id: &str,
     config: &Config,
+    cancelar: &AtomicBool,
     on_status: impl Fn(&str),
+    on_progreso: impl Fn(u32, u32),
 ) -> Result<ResultadoBusqueda> {
     let total = comunicaciones.len();
     if total == 0 {
-        return Ok(ResultadoBusqueda {
-            comunicaciones_descargadas: 0,
-            total_comunicaciones: 0,
-        });
+        return Ok(ResultadoBusqueda::default());
     }
 
     let credenciales = config
@@ -168,16 +278,35 @@ pub async fn buscar_comunicaciones(
 
     on_status("Iniciando navegador...");
 
-    // Obtener carpeta de descargas del usuario
-    let ruta_descargas = dirs::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    // Antes de crear el perfil, para no dejarlo huérfano si falta Chrome
+    let chrome = verificar_chrome()?;
+
+    // Carpeta de descargas propia del usuario
+    if let (alternativa, true) = config.ruta_descargas_efectiva() {
+        on_status(&format!(
+            "No se puede escribir en la carpeta de descargas {}, se usa {}",
+            config.ruta_descargas.display(),
+            alternativa.display()
+        ));
+    }
+    let ruta_descargas = config.ruta_descargas_usuario(usuario_id)?;
 
     // Configurar perfil personalizado para preferencias
-    let user_data_dir = setup_custom_profile()?;
+    let perfil = PerfilEnUso::marcar(crear_perfil(
+        PREFIJO_PERFIL_BUSQUEDA,
+        &ruta_descargas,
+        config,
+        usuario_id,
+    )?)?;
+    let user_data_dir = perfil.ruta().to_path_buf();
 
     // Configurar navegador con opciones para permitir descargas (igual que web_automation)
     let browser_config = BrowserConfig::builder()
+        .chrome_executable(chrome)
         .user_data_dir(&user_data_dir)
         .with_head() // Mostrar navegador (no headless)
+        .args(config.argumentos_ventana())
+        .args(config.argumentos_proxy())
         // Suprimir popups y diálogos
         .arg("--no-first-run")
         .arg("--no-default-browser-check")
@@ -212,24 +341,31 @@ pub async fn buscar_comunicaciones(
     // Manejar eventos del navegador en segundo plano
     let handle = tokio::spawn(async move { while let Some(_event) = handler.next().await {} });
 
+    // Autorizar descargas sin avisos; las bloqueadas quedan en el log
+    let _monitor = MonitorDescargas::iniciar(&browser, &ruta_descargas).await?;
+
     let page = browser
         .new_page("about:blank")
         .await
         .context("Error al crear página")?;
+    autenticar_proxy(&page, config).await?;
 
-    // Navegar a SADE
+    // Navegar a SADE, midiendo cuánto tarda para ajustar las esperas
     on_status("Navegando a SADE...");
+    esperas::reiniciar();
+    let inicio_carga = Instant::now();
     page.goto("http://euc.gcba.gob.ar/ccoo-web/")
         .await
         .context("Error al navegar a SADE")?;
+    esperas::calibrar(inicio_carga.elapsed(), esperas::REFERENCIA_CARGA);
 
-    sleep(Duration::from_secs(2)).await;
+    esperas::esperar(Duration::from_secs(2)).await;
 
     // Login
     on_status("Iniciando sesión...");
 
     let inputs = page
-        .find_elements(".form-control.z-textbox")
+        .find_elements(&selectores::actuales().campo_login)
         .await
         .context("No se encontraron campos de login")?;
 
@@ -246,11 +382,13 @@ pub async fn buscar_comunicaciones(
             .await?;
 
         // Click en botón de login
-        let login_btn = page.find_element(".btn.btn-default.z-button").await?;
+        let login_btn = page
+            .find_element(&selectores::actuales().boton_login)
+            .await?;
         login_btn.click().await?;
     }
 
-    sleep(Duration::from_secs(3)).await;
+    esperar_login(&page).await;
 
     let mut descargadas = 0;
 
@@ -259,9 +397,23 @@ pub async fn buscar_comunicaciones(
     let patron_ccoo =
         Regex::new(r"^NO-\d{4}-\d+-GCABA-[A-Za-z0-9]+$").expect("Regex de comunicación inválido");
 
+    let mut cancelada = false;
+    on_progreso(0, total as u32);
+
     for (idx, comunicacion) in comunicaciones.iter().enumerate() {
+        if cancelar.load(Ordering::Relaxed) {
+            on_status("Búsqueda cancelada, cerrando navegador...");
+            cancelada = true;
+            break;
+        }
+        if idx > 0 {
+            on_progreso(idx as u32, total as u32);
+        }
+
+        let comunicacion = normalizar_ccoo(comunicacion);
+
         // Saltear comunicaciones que no coincidan con el patrón esperado
-        if !patron_ccoo.is_match(comunicacion) {
+        if !patron_ccoo.is_match(&comunicacion) {
             on_status(&format!(
                 "Salteando comunicación {} de {} (formato inválido: {})",
                 idx + 1,
@@ -278,104 +430,204 @@ pub async fn buscar_comunicaciones(
         ));
 
         // Buscar campo de texto para número de comunicación
-        if let Ok(textboxes) = page.find_elements(".z-textbox").await {
+        if let Ok(textboxes) = page
+            .find_elements(&selectores::actuales().campo_busqueda)
+            .await
+        {
             if !textboxes.is_empty() {
                 // Limpiar y escribir número de comunicación
                 textboxes[0].click().await.ok();
 
-                // Seleccionar todo el texto y reemplazarlo usando JavaScript
+                // Seleccionar todo el texto y reemplazarlo usando JavaScript.
+                // El valor se serializa como literal JSON (válido en JS) para que
+                // comillas, barras o saltos de línea no rompan el script
+                let literal_js = serde_json::to_string(&comunicacion)?;
+                let selector_js = serde_json::to_string(&selectores::actuales().campo_busqueda)?;
                 let js_code = format!(
                     r#"
                     (function() {{
-                        var input = document.querySelectorAll('.z-textbox')[0];
+                        var input = document.querySelectorAll({})[0];
                         if (input) {{
-                            input.value = '{}';
+                            input.value = {};
                             input.dispatchEvent(new Event('input', {{ bubbles: true }}));
                             input.dispatchEvent(new Event('change', {{ bubbles: true }}));
                         }}
                     }})();
                     "#,
-                    comunicacion
+                    selector_js, literal_js
                 );
 
                 let _ = page.evaluate(js_code).await;
 
-                sleep(Duration::from_millis(500)).await;
-
-                // Click en botón de búsqueda (tercer z-button)
-                if let Ok(buttons) = page.find_elements(".z-button").await {
-                    if buttons.len() > 2 {
-                        buttons[2].click().await.ok();
-                        sleep(Duration::from_secs(2)).await;
-                    }
+                esperas::esperar(Duration::from_millis(500)).await;
+
+                // Click en botón de búsqueda (antes el tercer z-button)
+                if let Ok(Some(buscar)) = localizar_por_texto(
+                    &page,
+                    &selectores::actuales().boton_busqueda,
+                    &["Buscar"],
+                    2,
+                )
+                .await
+                {
+                    buscar.click().await.ok();
+                    esperas::esperar(Duration::from_secs(2)).await;
                 }
 
-                // Click en botón para ver detalles (índice 29 según Python)
-                if let Ok(detail_btns) = page.find_elements(".boton-sin-caja.z-button").await {
-                    if detail_btns.len() > 29 {
-                        detail_btns[29].click().await.ok();
-                        sleep(Duration::from_secs(2)).await;
-                    }
+                // Click en botón para ver detalles del resultado (antes el índice 29)
+                let detalle =
+                    match primer_visible(&page, &selectores::actuales().detalle_busqueda).await {
+                        Ok(Some(boton)) => Some(boton),
+                        _ => respaldo_por_indice(
+                            &page,
+                            &selectores::actuales().boton_sin_caja,
+                            29,
+                            "el botón de detalle",
+                        )
+                        .await
+                        .ok()
+                        .flatten(),
+                    };
+                if let Some(detalle) = detalle {
+                    detalle.click().await.ok();
+                    esperas::esperar(Duration::from_secs(2)).await;
                 }
 
                 // Click en botón de descarga
-                if let Ok(download_btns) = page.find_elements(".z-icon-download").await {
+                if let Ok(download_btns) = page
+                    .find_elements(&selectores::actuales().icono_descarga)
+                    .await
+                {
                     if !download_btns.is_empty() {
-                        download_btns[0].click().await.ok();
+                        let previos = pdfs_descargados(&ruta_descargas);
+                        let esperados = usize::from(download_btns[0].click().await.is_ok());
 
                         // Espera mínima para que el navegador procese
                         sleep(Duration::from_millis(300)).await;
 
                         // Espera inicial para asegurar que Chrome cree los archivos .crdownload
-                        sleep(Duration::from_secs(1)).await;
+                        esperas::esperar(Duration::from_secs(1)).await;
 
                         // Esperar a que las descargas terminen
-                        if esperar_descargas_completas(&ruta_descargas, 30).await {
+                        if esperados > 0
+                            && esperar_descargas_completas(&ruta_descargas, &previos, esperados, 30)
+                                .await
+                        {
                             descargadas += 1;
                         }
                     }
                 }
 
                 // Volver a la lista
-                if let Ok(back_btns) = page.find_elements(".btn.z-button").await {
-                    if !back_btns.is_empty() {
-                        back_btns[0].click().await.ok();
-                        sleep(Duration::from_secs(1)).await;
-                    }
+                if let Ok(Some(volver)) =
+                    localizar_por_texto(&page, &selectores::actuales().boton, &["Volver"], 0).await
+                {
+                    volver.click().await.ok();
+                    esperas::esperar(Duration::from_secs(1)).await;
                 }
             }
         }
     }
 
+    if !cancelada {
+        on_progreso(total as u32, total as u32);
+    }
+
     on_status("Cerrando navegador...");
 
     // Cerrar navegador
-    drop(browser);
-    handle.abort();
-
-    // Dar tiempo al SO para liberar los archivos
-    sleep(Duration::from_secs(2)).await;
-
-    // Limpiar perfil temporal con reintentos
-    let mut clean_retries = 5;
-    while clean_retries > 0 {
-        if let Err(e) = std::fs::remove_dir_all(&user_data_dir) {
-            if clean_retries == 1 {
-                eprintln!(
-                    "Advertencia: No se pudo limpiar el perfil temporal tras varios intentos: {}",
-                    e
-                );
-            } else {
-                sleep(Duration::from_secs(1)).await;
-            }
-        } else {
-            break;
-        }
-        clean_retries -= 1;
+    if cerrar_navegador(browser, handle, config.mantener_navegador_abierto) {
+        // Dar tiempo al SO para liberar los archivos
+        sleep(Duration::from_secs(2)).await;
+
+        // Limpiar perfil temporal con reintentos
+        eliminar_perfil(perfil, config).await;
+    } else {
+        perfil.conservar();
+        on_status("El navegador queda abierto para revisar SADE; ciérrelo a mano al terminar");
     }
 
     Ok(ResultadoBusqueda {
         comunicaciones_descargadas: descargadas,
         total_comunicaciones: total,
+        cancelada,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nombre_columna_coincide_tolera_variantes() {
+        for variante in [
+            "CCOO N°",
+            "CCOO Nº",
+            "CCOO N °",
+            " ccoo n° ",
+            "CCOO\u{a0}N˚",
+        ] {
+            assert!(
+                nombre_columna_coincide(variante, "CCOO N°"),
+                "{:?}",
+                variante
+            );
+        }
+        assert!(nombre_columna_coincide("Organismo", "ORGANISMO"));
+        assert!(nombre_columna_coincide("Orgánismo", "ORGANISMO"));
+        assert!(!nombre_columna_coincide("CCOO", "CCOO N°"));
+        assert!(!nombre_columna_coincide("ORGANISMOS", "ORGANISMO"));
+    }
+
+    #[test]
+    fn pendientes_distingue_excel_vacio_de_todas_con_organismo() {
+        let lectura = |comunicaciones: &[&str], filas_con_ccoo| ComunicacionesSinOrganismo {
+            comunicaciones: comunicaciones.iter().map(|c| c.to_string()).collect(),
+            filas_con_ccoo,
+        };
+        assert_eq!(lectura(&["NO-1"], 3).pendientes(), Pendientes::Buscar);
+        assert_eq!(
+            lectura(&[], 0).pendientes(),
+            Pendientes::ExcelSinComunicaciones
+        );
+        assert_eq!(
+            lectura(&[], 3).pendientes(),
+            Pendientes::TodasConOrganismo(3)
+        );
+    }
+
+    #[test]
+    fn normalizar_ccoo_unifica_guiones_espacios_y_mayusculas() {
+        for (entrada, esperado) in [
+            (" no-2024-123-gcaba-dgin ", "NO-2024-123-GCABA-DGIN"),
+            (
+                "NO\u{2013}2024\u{2014}123\u{2212}GCABA-DGIN",
+                "NO-2024-123-GCABA-DGIN",
+            ),
+            ("NO_2024_123_GCABA_DGIN", "NO-2024-123-GCABA-DGIN"),
+            ("NO - 2024 -\t123", "NO-2024-123"),
+            ("NO--2024-\u{200B}123\u{FEFF}", "NO-2024-123"),
+            ("-NO-2024-123-", "NO-2024-123"),
+            ("", ""),
+        ] {
+            assert_eq!(normalizar_ccoo(entrada), esperado, "{:?}", entrada);
+        }
+    }
+
+    #[test]
+    fn elegir_hoja_por_nombre_numero_o_la_primera() {
+        let hojas = vec!["Listado".to_string(), " Pendientes ".to_string()];
+        assert_eq!(elegir_hoja(&hojas, None).unwrap(), "Listado");
+        assert_eq!(elegir_hoja(&hojas, Some("  ")).unwrap(), "Listado");
+        assert_eq!(
+            elegir_hoja(&hojas, Some("pendientes")).unwrap(),
+            " Pendientes "
+        );
+        assert_eq!(elegir_hoja(&hojas, Some("2")).unwrap(), " Pendientes ");
+
+        let error = elegir_hoja(&hojas, Some("3")).unwrap_err().to_string();
+        assert!(error.contains("Listado,  Pendientes "), "{}", error);
+        assert!(elegir_hoja(&hojas, Some("0")).is_err());
+        assert!(elegir_hoja(&[], None).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+//! Modo de línea de comandos para correr las etapas sin levantar la GUI
+//!
+//! Pensado para tareas programadas: los mensajes de estado van a stdout (y al
+//! log del día) y el código de salida indica si la corrida terminó bien.
+
+use anyhow::{Context, Result};
+use auto_sade::config::Config;
+use auto_sade::excel_handler::{ModoGuardado, OrdenFilas, OrdenarPor};
+use auto_sade::file_processor::{self, ModoColision};
+use auto_sade::pdf_extractor::FuenteFecha;
+use auto_sade::web_automation::{self, ResultadoDescarga};
+use auto_sade::{excel_handler, pdf_extractor, perfil_temporal, registro};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Argumentos de línea de comandos. Sin subcomando se abre la GUI.
+#[derive(Debug, Parser)]
+#[command(
+    name = "auto-sade",
+    version,
+    about = "Manejo de comunicaciones de inventario de SADE"
+)]
+pub struct Cli {
+    /// Directorio de trabajo (por defecto el de la configuración)
+    #[arg(long, global = true)]
+    directorio: Option<PathBuf>,
+
+    /// Número de instancia para correr varias a la vez (por defecto `SADE_INSTANCIA`)
+    #[arg(long, global = true)]
+    instancia: Option<u32>,
+
+    #[command(subcommand)]
+    comando: Option<Comando>,
+}
+
+impl Cli {
+    /// Sin subcomando se abre la interfaz gráfica
+    pub fn usa_gui(&self) -> bool {
+        self.comando.is_none()
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Comando {
+    /// Descarga los adjuntos de un rango o una lista de comunicaciones
+    Descargar(ArgsDescarga),
+    /// Mueve los PDFs descargados a la carpeta de trabajo
+    Mover {
+        /// Identificador del usuario en el .env (1 = Erica, 2 = Cecilia)
+        #[arg(long)]
+        usuario: String,
+        #[command(flatten)]
+        opciones: ArgsMover,
+    },
+    /// Procesa los PDFs de la carpeta de trabajo y genera el Excel
+    Procesar(ArgsProcesar),
+    /// Descarga, mueve, procesa y genera el Excel
+    Todo {
+        #[command(flatten)]
+        descarga: ArgsDescarga,
+        #[command(flatten)]
+        mover: ArgsMover,
+        #[command(flatten)]
+        procesar: ArgsProcesar,
+    },
+}
+
+#[derive(Debug, Args)]
+struct ArgsDescarga {
+    /// Identificador del usuario en el .env (1 = Erica, 2 = Cecilia)
+    #[arg(long)]
+    usuario: String,
+    /// Número de la primera comunicación
+    #[arg(long, required_unless_present = "lista", requires = "final_")]
+    inicio: Option<u32>,
+    /// Número de la última comunicación
+    #[arg(long = "final", requires = "inicio")]
+    final_: Option<u32>,
+    /// Comunicaciones sueltas en lugar de un rango, por ejemplo "12,15,20-25"
+    #[arg(long, conflicts_with_all = ["inicio", "final_"])]
+    lista: Option<String>,
+    /// Dejar Chrome abierto al terminar para revisar SADE a mano
+    #[arg(long)]
+    mantener_navegador: bool,
+    /// Minutos máximos para toda la descarga; al excederlos se corta y se cierra el navegador
+    #[arg(long, value_name = "MINUTOS")]
+    tiempo_maximo: Option<u64>,
+}
+
+impl ArgsDescarga {
+    /// Comunicaciones a descargar: la lista o el rango inicio-final
+    fn numeros(&self) -> Result<Vec<u32>> {
+        match (&self.lista, self.inicio, self.final_) {
+            (Some(lista), _, _) => web_automation::parsear_lista_comunicaciones(lista),
+            (None, Some(inicio), Some(final_)) => {
+                web_automation::rango_comunicaciones(inicio, final_)
+            }
+            _ => anyhow::bail!("Indique --inicio y --final, o --lista"),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct ArgsMover {
+    /// Guardar los archivos eliminados en Descartados en lugar de borrarlos
+    #[arg(long)]
+    descartar: bool,
+    /// Qué hacer si el archivo ya existe en el destino
+    #[arg(long, value_enum, default_value_t = Colision::Renombrar)]
+    colision: Colision,
+}
+
+#[derive(Debug, Args)]
+struct ArgsProcesar {
+    /// Usar OCR en PDFs escaneados
+    #[arg(long)]
+    ocr: bool,
+    /// Incluir subcarpetas al procesar
+    #[arg(long)]
+    subcarpetas: bool,
+    /// Guardar los PDFs sin novedad en Procesados/<organismo>
+    #[arg(long)]
+    por_organismo: bool,
+    /// Solo extraer los datos, sin mover los PDFs a Procesados/Revisar
+    #[arg(long)]
+    sin_mover: bool,
+    /// Registrar en el log qué patrón clasificó cada PDF como sin novedad
+    #[arg(long)]
+    diagnostico: bool,
+    /// Procesar solo estos archivos, separados por coma (con o sin .pdf)
+    #[arg(long, value_delimiter = ',')]
+    archivos: Option<Vec<String>>,
+    /// Columna por la que se ordenan las filas del Excel
+    #[arg(long, value_enum, default_value_t = Orden::Procesamiento)]
+    ordenar: Orden,
+    /// Metadato del PDF del que se toma la fecha (si falta se usa el otro)
+    #[arg(long, value_enum, default_value_t = Fecha::Modificacion)]
+    fecha: Fecha,
+    /// Agrupar al final las filas "Con novedades"
+    #[arg(long)]
+    novedades_al_final: bool,
+    /// Exportar además, en un Excel aparte, solo las filas que no son "Sin novedad"
+    #[arg(long)]
+    solo_novedades: bool,
+    /// Guardar también los datos en JSON (`<Excel> datos.json`) para otros sistemas
+    #[arg(long)]
+    json: bool,
+    /// Qué hacer si el Excel de salida ya existe
+    #[arg(long, value_enum, default_value_t = Guardado::Nuevo)]
+    guardado: Guardado,
+    /// Al sobrescribir, no guardar antes una copia del Excel anterior
+    #[arg(long)]
+    sin_respaldo: bool,
+}
+
+/// Orden de las filas del Excel elegible desde la línea de comandos
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Orden {
+    Procesamiento,
+    Ccoo,
+    Fecha,
+    Organismo,
+}
+
+impl From<Orden> for OrdenarPor {
+    fn from(orden: Orden) -> Self {
+        match orden {
+            Orden::Procesamiento => OrdenarPor::Procesamiento,
+            Orden::Ccoo => OrdenarPor::Ccoo,
+            Orden::Fecha => OrdenarPor::Fecha,
+            Orden::Organismo => OrdenarPor::Organismo,
+        }
+    }
+}
+
+/// Metadato de fecha elegible desde la línea de comandos
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Fecha {
+    Modificacion,
+    Creacion,
+}
+
+impl From<Fecha> for FuenteFecha {
+    fn from(fecha: Fecha) -> Self {
+        match fecha {
+            Fecha::Modificacion => FuenteFecha::Modificacion,
+            Fecha::Creacion => FuenteFecha::Creacion,
+        }
+    }
+}
+
+/// Modo de guardado del Excel elegible desde la línea de comandos
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Guardado {
+    Sobrescribir,
+    Anexar,
+    Nuevo,
+}
+
+impl Guardado {
+    fn modo(self, respaldo: bool) -> ModoGuardado {
+        match self {
+            Guardado::Sobrescribir => ModoGuardado::Sobrescribir { respaldo },
+            Guardado::Anexar => ModoGuardado::Anexar,
+            Guardado::Nuevo => ModoGuardado::NuevoConTimestamp,
+        }
+    }
+}
+
+/// Modo de colisión elegible desde la línea de comandos
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Colision {
+    Sobrescribir,
+    Renombrar,
+    Saltar,
+}
+
+impl From<Colision> for ModoColision {
+    fn from(colision: Colision) -> Self {
+        match colision {
+            Colision::Sobrescribir => ModoColision::Sobrescribir,
+            Colision::Renombrar => ModoColision::Renombrar,
+            Colision::Saltar => ModoColision::Saltar,
+        }
+    }
+}
+
+/// Muestra un mensaje de estado en stdout y lo agrega al log del día
+fn informar(mensaje: &str) {
+    println!("{}", mensaje);
+    registro::escribir(mensaje);
+}
+
+/// Ejecuta el subcomando y devuelve el código de salida del proceso
+pub fn ejecutar(cli: Cli) -> ExitCode {
+    let Some(comando) = cli.comando else {
+        return ExitCode::SUCCESS;
+    };
+    match correr(cli.directorio, cli.instancia, comando) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let mensaje = format!("Error: {:#}", e);
+            eprintln!("{}", mensaje);
+            registro::escribir(&mensaje);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn correr(directorio: Option<PathBuf>, instancia: Option<u32>, comando: Comando) -> Result<()> {
+    let mut config = Config::from_env().context("Error de configuración")?;
+    if let Some(directorio) = directorio {
+        config.establecer_directorio_trabajo(directorio);
+    }
+    if instancia.is_some() {
+        config.instancia_id = instancia;
+    }
+    registro::inicializar(&config.ruta_archivos);
+    perfil_temporal::limpiar_perfiles_huerfanos();
+
+    match comando {
+        Comando::Descargar(args) => verificar_descarga(&descargar(&config, &args)?),
+        Comando::Mover { usuario, opciones } => mover(&config, &usuario, &opciones),
+        Comando::Procesar(args) => procesar(&config, &args, None),
+        Comando::Todo {
+            descarga,
+            mover: opciones_mover,
+            procesar: opciones_procesar,
+        } => {
+            // Las comunicaciones fallidas no detienen el resto, pero sí marcan la salida
+            let resultado = descargar(&config, &descarga)?;
+            if resultado.cancelada || resultado.sin_espacio {
+                return verificar_descarga(&resultado);
+            }
+            mover(&config, &descarga.usuario, &opciones_mover)?;
+            let operador = config
+                .get_credenciales(&descarga.usuario)
+                .map(|c| c.nombre.as_str());
+            procesar(&config, &opciones_procesar, operador)?;
+            verificar_descarga(&resultado)
+        }
+    }
+}
+
+fn descargar(config: &Config, args: &ArgsDescarga) -> Result<ResultadoDescarga> {
+    let rt = tokio::runtime::Runtime::new().context("No se pudo iniciar el runtime de tokio")?;
+
+    // Ctrl+C pide cancelar: se termina la comunicación en curso y se corta
+    let cancelar = Arc::new(AtomicBool::new(false));
+    let flag = Arc::clone(&cancelar);
+    rt.spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            informar("Cancelando, se termina la comunicación en curso...");
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let mut config = config.clone();
+    config.mantener_navegador_abierto |= args.mantener_navegador;
+
+    let numeros = args.numeros()?;
+    let resultado = rt
+        .block_on(web_automation::descargar_lista(
+            &numeros,
+            &args.usuario,
+            &config,
+            &cancelar,
+            &AtomicBool::new(false),
+            args.tiempo_maximo.map(|m| Duration::from_secs(m * 60)),
+            informar,
+            |_, _| {},
+        ))
+        .context("Falló la descarga")?;
+
+    informar(&format!(
+        "{} de {} comunicaciones descargadas",
+        resultado.comunicaciones_procesadas, resultado.total_comunicaciones
+    ));
+    if !resultado.descargas_bloqueadas.is_empty() {
+        informar(&format!(
+            "{} descargas bloqueadas por Chrome: {}",
+            resultado.descargas_bloqueadas.len(),
+            resultado.descargas_bloqueadas.join(", ")
+        ));
+    }
+    Ok(resultado)
+}
+
+/// Convierte una descarga cancelada o con comunicaciones fallidas en error
+fn verificar_descarga(resultado: &ResultadoDescarga) -> Result<()> {
+    if resultado.cancelada {
+        anyhow::bail!("Descarga cancelada");
+    }
+    if resultado.sin_espacio {
+        anyhow::bail!("Descarga detenida por falta de espacio en disco");
+    }
+    if !resultado.fallidas.is_empty() {
+        let fallidas: Vec<String> = resultado.fallidas.iter().map(|n| n.to_string()).collect();
+        anyhow::bail!(
+            "{} comunicaciones sin archivos descargados: {}",
+            fallidas.len(),
+            fallidas.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn mover(config: &Config, usuario: &str, args: &ArgsMover) -> Result<()> {
+    informar("Moviendo archivos...");
+    let opciones = file_processor::OpcionesMover {
+        descartar: args.descartar,
+        colision: args.colision.into(),
+    };
+    let ruta_descarga = config.ruta_descargas_usuario(usuario)?;
+    let resultado =
+        file_processor::mover_archivos(&ruta_descarga, &config.ruta_archivos, &opciones)
+            .context("Falló la etapa de mover archivos")?;
+
+    let neto = resultado
+        .archivos_movidos
+        .saturating_sub(resultado.archivos_eliminados);
+    informar(&format!(
+        "{} movidos, {} eliminados, {} colisiones, {} duplicados",
+        neto, resultado.archivos_eliminados, resultado.colisiones, resultado.duplicados
+    ));
+    Ok(())
+}
+
+/// Procesa los PDFs y guarda el Excel con sus metadatos; `usuario` es el operador
+/// que hizo la descarga, si se conoce
+fn procesar(config: &Config, args: &ArgsProcesar, usuario: Option<&str>) -> Result<()> {
+    let columnas = excel_handler::ColumnasConfig::desde_config(config)?;
+    informar("Procesando PDFs...");
+    let opciones = pdf_extractor::OpcionesProcesamiento {
+        ocr: args.ocr,
+        password: config.password_pdf.clone(),
+        recursivo: args.subcarpetas,
+        archivos: args.archivos.clone(),
+        por_organismo: args.por_organismo,
+        mover: !args.sin_mover,
+        diagnostico: args.diagnostico,
+        fuente_fecha: args.fecha.into(),
+    };
+    let resultado = pdf_extractor::procesar_pdfs(
+        &config.ruta_archivos,
+        &opciones,
+        |actual, total, archivo| {
+            println!("Procesando {} de {}: {}", actual, total, archivo);
+        },
+    )
+    .context("Falló la etapa de procesamiento")?;
+
+    let orden = OrdenFilas {
+        por: args.ordenar.into(),
+        novedades_al_final: args.novedades_al_final,
+    };
+    let excel = excel_handler::guardar_excel(
+        &resultado.datos,
+        &config.ruta_excel,
+        &columnas,
+        orden,
+        args.guardado.modo(!args.sin_respaldo),
+    )
+    .context("Error al guardar Excel")?;
+    let metadatos = excel_handler::MetadatosCorrida::nuevos(&resultado.datos, usuario);
+    excel_handler::guardar_metadatos(&excel, &metadatos)?;
+    if args.solo_novedades {
+        let filtrado = excel_handler::guardar_excel_filtrado(
+            &resultado.datos,
+            &excel_handler::ruta_con_sufijo(
+                &config.ruta_excel,
+                excel_handler::SUFIJO_CON_NOVEDADES,
+            ),
+            &columnas,
+            orden,
+        )
+        .context("Error al guardar el Excel con novedades")?;
+        excel_handler::guardar_metadatos(&filtrado, &metadatos)?;
+        informar(&format!("Filas con novedades en {}", filtrado.display()));
+    }
+    if args.json {
+        // El JSON acompaña al Excel completo y tiene sus mismas filas
+        let ruta = excel_handler::ruta_json(&excel);
+        excel_handler::guardar_json(&resultado.datos, &ruta)?;
+        informar(&format!("Datos en JSON en {}", ruta.display()));
+    }
+
+    let resumen = resultado.resumen();
+    informar(&format!(
+        "{} archivos procesados ({} sin novedad, {} con novedades, {} fallidos), Excel en {}",
+        resultado.datos.len(),
+        resumen.sin_novedad,
+        resumen.con_novedades,
+        resumen.fallidos,
+        excel.display()
+    ));
+    if !resultado.archivos_bloqueados.is_empty() {
+        informar(&format!(
+            "{} sin mover por estar abiertos: {}",
+            resultado.archivos_bloqueados.len(),
+            resultado.archivos_bloqueados.join(", ")
+        ));
+    }
+    if args.sin_mover {
+        if !resultado.archivos_protegidos.is_empty() {
+            informar(&format!(
+                "{} protegidos con contraseña sin procesar",
+                resultado.archivos_protegidos.len()
+            ));
+        }
+        return Ok(());
+    }
+    if !resultado.archivos_protegidos.is_empty() {
+        informar(&format!(
+            "{} protegidos con contraseña movidos a {}",
+            resultado.archivos_protegidos.len(),
+            pdf_extractor::CARPETA_PROTEGIDOS
+        ));
+    }
+    let ilegibles = resultado
+        .datos
+        .iter()
+        .filter(|d| d.resultado == pdf_extractor::Resultado::RevisionManual)
+        .count();
+    if ilegibles > 0 {
+        informar(&format!(
+            "{} sin texto legible movidos a {}",
+            ilegibles,
+            pdf_extractor::CARPETA_ILEGIBLES
+        ));
+    }
+    Ok(())
+}
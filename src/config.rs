@@ -1,68 +1,287 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::env;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Carpeta base de descargas: `SADE_RUTA_DESCARGAS` o `<Descargas>/SADE`. Si el
+/// sistema no informa la carpeta de descargas se usa la alternativa del ejecutable.
+fn obtener_ruta_descargas() -> PathBuf {
+    env::var("SADE_RUTA_DESCARGAS")
+        .ok()
+        .filter(|r| !r.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            dirs::download_dir()
+                .map(|d| d.join("SADE"))
+                .unwrap_or_else(ruta_descargas_alternativa)
+        })
+}
+
+/// Carpeta `Descargas` junto al ejecutable, para cuando la configurada no sirve
+pub fn ruta_descargas_alternativa() -> PathBuf {
+    obtener_directorio_exe().join("Descargas")
+}
+
+/// Indica si se puede crear la carpeta y escribir en ella
+fn es_escribible(carpeta: &Path) -> bool {
+    let prueba = carpeta.join(".sade_prueba_escritura");
+    let escribible = fs::create_dir_all(carpeta).is_ok() && fs::write(&prueba, b"").is_ok();
+    let _ = fs::remove_file(&prueba);
+    escribible
+}
 
 /// Credenciales de usuario para SADE
 #[derive(Debug, Clone)]
 pub struct Credenciales {
+    /// Nombre legible del operador, para mostrar en la GUI
+    pub nombre: String,
     pub usuario: String,
     pub password: String,
 }
 
+/// Operador de SADE que puede cargar credenciales en el `.env`
+#[derive(Debug, Clone, Copy)]
+pub struct Operador {
+    /// Identificador con que se lo elige en la GUI y la línea de comandos
+    pub id: &'static str,
+    pub nombre: &'static str,
+    /// Sufijo de sus variables: `SADE_USER_<SUFIJO>` y `SADE_PASSWORD_<SUFIJO>`
+    pub sufijo: &'static str,
+}
+
+impl Operador {
+    /// Nombres de las variables de usuario y contraseña
+    pub fn variables(&self) -> (String, String) {
+        (
+            format!("SADE_USER_{}", self.sufijo),
+            format!("SADE_PASSWORD_{}", self.sufijo),
+        )
+    }
+}
+
+/// Operadores conocidos, en el orden en que se muestran
+pub const OPERADORES: [Operador; 2] = [
+    Operador {
+        id: "1",
+        nombre: "Erica",
+        sufijo: "ERICA",
+    },
+    Operador {
+        id: "2",
+        nombre: "Cecilia",
+        sufijo: "CECILIA",
+    },
+];
+
+/// Escribe un valor entre comillas dobles con los escapes que entiende dotenvy
+fn valor_env(valor: &str) -> String {
+    let mut escapado = String::with_capacity(valor.len() + 2);
+    escapado.push('"');
+    for c in valor.chars() {
+        if matches!(c, '\\' | '"' | '$') {
+            escapado.push('\\');
+        }
+        escapado.push(c);
+    }
+    escapado.push('"');
+    escapado
+}
+
+/// Actualiza variables en el `.env` de `ruta`, conservando el resto de las líneas
+/// y los comentarios, y las aplica al proceso para que `Config::from_env` las vea.
+/// Un valor vacío borra la variable.
+pub fn guardar_variables_env(ruta: &Path, valores: &[(String, String)]) -> Result<()> {
+    let contenido = match fs::read_to_string(ruta) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("No se pudo leer {:?}", ruta)),
+    };
+
+    let clave_de = |linea: &str| {
+        let linea = linea.trim_start();
+        let linea = linea.strip_prefix("export ").unwrap_or(linea);
+        linea.split_once('=').map(|(k, _)| k.trim().to_string())
+    };
+    let mut pendientes: Vec<&(String, String)> = valores.iter().collect();
+    let mut lineas = Vec::new();
+    for linea in contenido.lines() {
+        let Some(posicion) =
+            clave_de(linea).and_then(|clave| pendientes.iter().position(|(k, _)| *k == clave))
+        else {
+            lineas.push(linea.to_string());
+            continue;
+        };
+        let (clave, valor) = pendientes.remove(posicion);
+        if !valor.is_empty() {
+            lineas.push(format!("{}={}", clave, valor_env(valor)));
+        }
+    }
+    for (clave, valor) in pendientes.into_iter().filter(|(_, v)| !v.is_empty()) {
+        lineas.push(format!("{}={}", clave, valor_env(valor)));
+    }
+
+    let mut texto = lineas.join("\n");
+    texto.push('\n');
+    fs::write(ruta, texto).with_context(|| format!("No se pudo guardar {:?}", ruta))?;
+
+    for (clave, valor) in valores {
+        if valor.is_empty() {
+            env::remove_var(clave);
+        } else {
+            env::set_var(clave, valor);
+        }
+    }
+    Ok(())
+}
+
+/// Proxy por el que sale el navegador
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    /// `host:puerto`, opcionalmente con esquema (`http://host:puerto`)
+    pub servidor: String,
+    /// Usuario y contraseña, si el proxy pide autenticación
+    pub credenciales: Option<(String, String)>,
+}
+
+impl Proxy {
+    /// Interpreta `host:puerto` o `usuario:contraseña@host:puerto`, con o sin esquema
+    pub fn desde_texto(texto: &str) -> Result<Self> {
+        let texto = texto.trim();
+        let (esquema, resto) = match texto.split_once("://") {
+            Some((esquema, resto)) => (format!("{}://", esquema), resto),
+            None => (String::new(), texto),
+        };
+        let (credenciales, host) = match resto.rsplit_once('@') {
+            Some((credenciales, host)) => {
+                let (usuario, password) = credenciales.split_once(':').context(
+                    "Las credenciales del proxy deben tener el formato usuario:contraseña",
+                )?;
+                (Some((usuario.to_string(), password.to_string())), host)
+            }
+            None => (None, resto),
+        };
+        if host.is_empty() {
+            anyhow::bail!("Falta el servidor del proxy");
+        }
+        Ok(Proxy {
+            servidor: format!("{}{}", esquema, host),
+            credenciales,
+        })
+    }
+}
+
+/// Bytes en un megabyte, para las variables expresadas en MB
+pub const BYTES_POR_MB: u64 = 1024 * 1024;
+
+/// Nombre del archivo Excel de salida dentro del directorio de trabajo
+pub const NOMBRE_EXCEL: &str = "Listado RDP a copiar.xlsx";
+
 /// Configuración de la aplicación
 #[derive(Debug, Clone)]
 pub struct Config {
+    pub directorio_trabajo: PathBuf,
     pub ruta_archivos: PathBuf,
     pub ruta_excel: PathBuf,
     pub usuarios: HashMap<String, Credenciales>,
+    /// Contraseña para abrir PDFs protegidos (`SADE_PDF_PASSWORD`)
+    pub password_pdf: Option<String>,
+    /// Columnas del Excel de salida tal como vienen en `SADE_COLUMNAS_EXCEL`
+    /// (`campo=Encabezado;...`); las interpreta `ColumnasConfig::desde_config`
+    pub columnas_excel: Option<String>,
+    /// Pestañas del navegador usadas en paralelo al descargar (`SADE_PESTANAS_DESCARGA`)
+    pub pestanas_descarga: usize,
+    /// Carpeta base de descargas, con una subcarpeta por usuario (`SADE_RUTA_DESCARGAS`)
+    pub ruta_descargas: PathBuf,
+    /// Hoja del Excel de referencia para la búsqueda, por nombre o número (`SADE_HOJA_BUSQUEDA`)
+    pub hoja_busqueda: Option<String>,
+    /// Posición `x,y` de la ventana del navegador (`SADE_VENTANA_POSICION`)
+    pub ventana_posicion: Option<(i32, i32)>,
+    /// Tamaño `ancho,alto` de la ventana del navegador (`SADE_VENTANA_TAMANO`)
+    pub ventana_tamano: Option<(u32, u32)>,
+    /// Intentos para borrar el perfil temporal de Chrome al cerrar (`SADE_REINTENTOS_LIMPIEZA`)
+    pub reintentos_limpieza_perfil: u32,
+    /// Espera entre intentos de borrar el perfil (`SADE_ESPERA_LIMPIEZA_MS`, en milisegundos)
+    pub espera_limpieza_perfil: Duration,
+    /// Número de instancia para correr varias a la vez en la misma PC (`SADE_INSTANCIA`).
+    /// Cada instancia descarga en su propia subcarpeta y usa su propio perfil de Chrome.
+    pub instancia_id: Option<u32>,
+    /// Espacio libre mínimo en la carpeta de descargas para empezar o seguir
+    /// descargando (`SADE_ESPACIO_MINIMO_MB`); 0 desactiva el control
+    pub espacio_minimo_descarga: u64,
+    /// Dejar Chrome abierto al terminar para inspeccionar SADE a mano
+    /// (`SADE_MANTENER_NAVEGADOR=1`); el perfil temporal queda sin borrar
+    pub mantener_navegador_abierto: bool,
+    /// Proxy del navegador (`SADE_PROXY`, con `SADE_PROXY_USUARIO` y
+    /// `SADE_PROXY_PASSWORD` si pide autenticación)
+    pub proxy: Option<Proxy>,
+}
+
+/// Lee un par de números `a,b` (o `axb`) de la variable de entorno `variable`
+fn leer_par<T: FromStr>(variable: &str) -> Result<Option<(T, T)>> {
+    let texto = match env::var(variable) {
+        Ok(texto) if !texto.trim().is_empty() => texto,
+        _ => return Ok(None),
+    };
+    let par = texto
+        .split_once([',', 'x'])
+        .and_then(|(a, b)| Some((a.trim().parse().ok()?, b.trim().parse().ok()?)));
+    par.map(Some)
+        .with_context(|| format!("{} debe tener el formato a,b", variable))
 }
 
-/// Obtiene el directorio donde está el ejecutable
-fn obtener_directorio_exe() -> PathBuf {
+/// Lee un número de la variable de entorno `variable`, o `defecto` si no está definida
+fn leer_numero<T: FromStr>(variable: &str, defecto: T) -> Result<T> {
+    match env::var(variable) {
+        Ok(texto) if !texto.trim().is_empty() => texto
+            .trim()
+            .parse()
+            .ok()
+            .with_context(|| format!("{} debe ser un número", variable)),
+        _ => Ok(defecto),
+    }
+}
+
+/// Obtiene el directorio donde está el ejecutable (donde se busca el `.env`)
+pub fn obtener_directorio_exe() -> PathBuf {
     env::current_exe()
         .ok()
         .and_then(|p| p.parent().map(|p| p.to_path_buf()))
         .unwrap_or_else(|| PathBuf::from("."))
 }
 
+/// Obtiene el directorio de trabajo: `SADE_DIRECTORIO_TRABAJO` si está definido,
+/// o el directorio del ejecutable en caso contrario
+fn obtener_directorio_trabajo() -> PathBuf {
+    env::var("SADE_DIRECTORIO_TRABAJO")
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+        .map(|d| PathBuf::from(d.trim()))
+        .unwrap_or_else(obtener_directorio_exe)
+}
+
 impl Config {
-    /// Carga la configuración - las rutas se basan en el directorio del ejecutable
+    /// Carga la configuración - las rutas se basan en el directorio de trabajo
     pub fn from_env() -> Result<Self> {
-        let dir_exe = obtener_directorio_exe();
-
-        // RUTA_ARCHIVOS = directorio del exe (donde están los PDFs)
-        let ruta_archivos = dir_exe.clone();
-
-        // RUTA_EXCEL = archivo "Listado RDP a copiar.xlsx" en el directorio del exe
-        let ruta_excel = dir_exe.join("Listado RDP a copiar.xlsx");
+        let directorio_trabajo = obtener_directorio_trabajo();
 
         let mut usuarios = HashMap::new();
 
-        // Cargar credenciales de ERICA
-        if let (Ok(user), Ok(pass)) = (env::var("SADE_USER_ERICA"), env::var("SADE_PASSWORD_ERICA"))
-        {
-            usuarios.insert(
-                "1".to_string(),
-                Credenciales {
-                    usuario: user,
-                    password: pass,
-                },
-            );
-        }
-
-        // Cargar credenciales de CECILIA
-        if let (Ok(user), Ok(pass)) = (
-            env::var("SADE_USER_CECILIA"),
-            env::var("SADE_PASSWORD_CECILIA"),
-        ) {
-            usuarios.insert(
-                "2".to_string(),
-                Credenciales {
-                    usuario: user,
-                    password: pass,
-                },
-            );
+        // Cargar las credenciales de cada operador
+        for operador in OPERADORES {
+            let (var_usuario, var_password) = operador.variables();
+            if let (Ok(user), Ok(pass)) = (env::var(var_usuario), env::var(var_password)) {
+                usuarios.insert(
+                    operador.id.to_string(),
+                    Credenciales {
+                        nombre: operador.nombre.to_string(),
+                        usuario: user,
+                        password: pass,
+                    },
+                );
+            }
         }
 
         // Verificar que hay al menos un usuario configurado
@@ -70,15 +289,157 @@ impl Config {
             anyhow::bail!("No se encontraron credenciales de usuario en el archivo .env");
         }
 
-        // Crear subcarpetas necesarias si no existen
-        let _ = std::fs::create_dir_all(ruta_archivos.join("Procesados"));
-        let _ = std::fs::create_dir_all(ruta_archivos.join("Revisar"));
+        let password_pdf = env::var("SADE_PDF_PASSWORD").ok().filter(|p| !p.is_empty());
+
+        // Columnas del Excel: formato "campo=Encabezado;campo=Encabezado"
+        let columnas_excel = env::var("SADE_COLUMNAS_EXCEL")
+            .ok()
+            .filter(|texto| !texto.trim().is_empty());
+
+        // Pestañas en paralelo para descargar; 1 = modo secuencial
+        let pestanas_descarga = match env::var("SADE_PESTANAS_DESCARGA") {
+            Ok(texto) if !texto.trim().is_empty() => texto
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n >= 1)
+                .context("SADE_PESTANAS_DESCARGA debe ser un número mayor o igual a 1")?,
+            _ => 1,
+        };
+
+        let hoja_busqueda = env::var("SADE_HOJA_BUSQUEDA")
+            .ok()
+            .filter(|h| !h.trim().is_empty());
+
+        // Ubicación de la ventana del navegador, por ejemplo en otro monitor
+        let ventana_posicion = leer_par("SADE_VENTANA_POSICION")?;
+        let ventana_tamano = leer_par("SADE_VENTANA_TAMANO")?;
+
+        // Borrado del perfil temporal: en máquinas lentas Chrome tarda en soltar los archivos
+        let reintentos_limpieza_perfil = leer_numero("SADE_REINTENTOS_LIMPIEZA", 5)?;
+        let espera_limpieza_perfil =
+            Duration::from_millis(leer_numero("SADE_ESPERA_LIMPIEZA_MS", 1000)?);
+
+        // Instancias concurrentes: sin definir se usa la carpeta del usuario tal cual
+        let instancia_id = match env::var("SADE_INSTANCIA") {
+            Ok(texto) if !texto.trim().is_empty() => Some(leer_numero("SADE_INSTANCIA", 0)?),
+            _ => None,
+        };
 
-        Ok(Config {
-            ruta_archivos,
-            ruta_excel,
+        let espacio_minimo_descarga =
+            leer_numero::<u64>("SADE_ESPACIO_MINIMO_MB", 500)?.saturating_mul(BYTES_POR_MB);
+
+        let mantener_navegador_abierto = env::var("SADE_MANTENER_NAVEGADOR")
+            .is_ok_and(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "si" | "sí"));
+
+        // Proxy corporativo: las variables de usuario y contraseña pisan las del texto
+        let proxy = match env::var("SADE_PROXY") {
+            Ok(texto) if !texto.trim().is_empty() => {
+                let mut proxy =
+                    Proxy::desde_texto(&texto).context("Valor inválido en SADE_PROXY")?;
+                if let Ok(usuario) = env::var("SADE_PROXY_USUARIO") {
+                    let password = env::var("SADE_PROXY_PASSWORD").unwrap_or_default();
+                    proxy.credenciales = Some((usuario, password));
+                }
+                Some(proxy)
+            }
+            _ => None,
+        };
+
+        let mut config = Config {
             usuarios,
-        })
+            password_pdf,
+            columnas_excel,
+            pestanas_descarga,
+            hoja_busqueda,
+            ventana_posicion,
+            ventana_tamano,
+            reintentos_limpieza_perfil,
+            espera_limpieza_perfil,
+            instancia_id,
+            espacio_minimo_descarga,
+            mantener_navegador_abierto,
+            proxy,
+            ..Default::default()
+        };
+        config.establecer_directorio_trabajo(directorio_trabajo);
+
+        Ok(config)
+    }
+
+    /// Cambia el directorio de trabajo y recalcula las rutas que dependen de él
+    /// (PDFs y el Excel de salida)
+    pub fn establecer_directorio_trabajo(&mut self, directorio: PathBuf) {
+        // RUTA_ARCHIVOS = directorio de trabajo (donde están los PDFs)
+        self.ruta_archivos = directorio.clone();
+
+        // RUTA_EXCEL = archivo "Listado RDP a copiar.xlsx" en el directorio de trabajo
+        self.ruta_excel = directorio.join(NOMBRE_EXCEL);
+        self.directorio_trabajo = directorio;
+    }
+
+    /// Cambia solo la carpeta de PDFs, sin tocar el directorio de trabajo ni el Excel
+    pub fn establecer_ruta_archivos(&mut self, ruta: PathBuf) {
+        self.ruta_archivos = ruta;
+    }
+
+    /// Devuelve los usuarios configurados ordenados por identificador
+    pub fn usuarios_ordenados(&self) -> Vec<(&String, &Credenciales)> {
+        let mut usuarios: Vec<_> = self.usuarios.iter().collect();
+        usuarios.sort_by(|a, b| a.0.cmp(b.0));
+        usuarios
+    }
+
+    /// Nombre legible del usuario, o su identificador si no está configurado
+    pub fn nombre_usuario<'a>(&'a self, usuario_id: &'a str) -> &'a str {
+        self.get_credenciales(usuario_id)
+            .map(|c| c.nombre.as_str())
+            .unwrap_or(usuario_id)
+    }
+
+    /// Carpeta base de descargas a usar: la configurada si existe (o se puede
+    /// crear) y es escribible, o si no `ruta_descargas_alternativa`. El `bool`
+    /// indica si se tuvo que usar la alternativa.
+    pub fn ruta_descargas_efectiva(&self) -> (PathBuf, bool) {
+        if es_escribible(&self.ruta_descargas) {
+            (self.ruta_descargas.clone(), false)
+        } else {
+            (ruta_descargas_alternativa(), true)
+        }
+    }
+
+    /// Carpeta de descargas del usuario (`<ruta_descargas>/<nombre>`, más
+    /// `instancia_<n>` si hay instancia), para que los operadores y las instancias
+    /// que comparten la PC no mezclen sus archivos. La crea si no existe.
+    pub fn ruta_descargas_usuario(&self, usuario_id: &str) -> Result<PathBuf> {
+        let (base, _) = self.ruta_descargas_efectiva();
+        let mut ruta = base.join(self.nombre_usuario(usuario_id));
+        if let Some(instancia) = self.instancia_id {
+            ruta.push(format!("instancia_{}", instancia));
+        }
+        std::fs::create_dir_all(&ruta)
+            .with_context(|| format!("No se pudo crear la carpeta de descargas {:?}", ruta))?;
+        Ok(ruta)
+    }
+
+    /// Argumentos de Chrome para salir por el proxy configurado
+    pub fn argumentos_proxy(&self) -> Vec<String> {
+        self.proxy
+            .iter()
+            .map(|p| format!("--proxy-server={}", p.servidor))
+            .collect()
+    }
+
+    /// Argumentos de Chrome para ubicar y dimensionar la ventana del navegador
+    pub fn argumentos_ventana(&self) -> Vec<String> {
+        let mut argumentos = Vec::new();
+        if let Some((x, y)) = self.ventana_posicion {
+            argumentos.push(format!("--window-position={},{}", x, y));
+        }
+        if let Some((ancho, alto)) = self.ventana_tamano {
+            argumentos.push(format!("--window-size={},{}", ancho, alto));
+        }
+        argumentos
     }
 
     /// Obtiene las credenciales para un usuario específico
@@ -88,13 +449,65 @@ impl Config {
 }
 
 impl Default for Config {
-    /// Crea una configuración por defecto basada en el directorio del ejecutable
+    /// Crea una configuración por defecto basada en el directorio de trabajo
     fn default() -> Self {
-        let dir_exe = obtener_directorio_exe();
+        let directorio_trabajo = obtener_directorio_trabajo();
         Config {
-            ruta_archivos: dir_exe.clone(),
-            ruta_excel: dir_exe.join("Listado RDP a copiar.xlsx"),
+            ruta_archivos: directorio_trabajo.clone(),
+            ruta_excel: directorio_trabajo.join(NOMBRE_EXCEL),
+            directorio_trabajo,
             usuarios: HashMap::new(),
+            password_pdf: None,
+            columnas_excel: None,
+            pestanas_descarga: 1,
+            ruta_descargas: obtener_ruta_descargas(),
+            hoja_busqueda: None,
+            ventana_posicion: None,
+            ventana_tamano: None,
+            reintentos_limpieza_perfil: 5,
+            espera_limpieza_perfil: Duration::from_secs(1),
+            instancia_id: None,
+            espacio_minimo_descarga: 500 * BYTES_POR_MB,
+            mantener_navegador_abierto: false,
+            proxy: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_desde_texto_con_y_sin_credenciales() {
+        assert_eq!(
+            Proxy::desde_texto(" proxy.gcba:8080 ").unwrap(),
+            Proxy {
+                servidor: "proxy.gcba:8080".to_string(),
+                credenciales: None,
+            }
+        );
+        assert_eq!(
+            Proxy::desde_texto("http://u:p@proxy.gcba:8080").unwrap(),
+            Proxy {
+                servidor: "http://proxy.gcba:8080".to_string(),
+                credenciales: Some(("u".to_string(), "p".to_string())),
+            }
+        );
+        // La contraseña puede tener `@`: el servidor es lo que sigue a la última
+        assert_eq!(
+            Proxy::desde_texto("u:p@ss@proxy.gcba:8080").unwrap(),
+            Proxy {
+                servidor: "proxy.gcba:8080".to_string(),
+                credenciales: Some(("u".to_string(), "p@ss".to_string())),
+            }
+        );
+    }
+
+    #[test]
+    fn proxy_desde_texto_rechaza_formatos_invalidos() {
+        assert!(Proxy::desde_texto("usuario@proxy.gcba:8080").is_err());
+        assert!(Proxy::desde_texto("u:p@").is_err());
+        assert!(Proxy::desde_texto("").is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Esperas de la automatización escaladas según la latencia medida de SADE
+//!
+//! Los tiempos fijos están pensados para una conexión promedio. Al empezar cada
+//! corrida se mide cuánto tardan la carga de SADE y el login, y las esperas
+//! siguientes se multiplican por un factor para que el mismo binario funcione
+//! en redes rápidas y lentas.
+
+use chromiumoxide::Page;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Factor actual en centésimos (100 = tiempos originales)
+static FACTOR: AtomicU32 = AtomicU32::new(FACTOR_NEUTRO);
+
+const FACTOR_NEUTRO: u32 = 100;
+
+/// Límites del factor: nunca menos de la mitad ni más del triple de lo original
+const FACTOR_MINIMO: u32 = 50;
+const FACTOR_MAXIMO: u32 = 300;
+
+/// Lo que tarda la carga inicial de SADE en una conexión promedio
+pub(crate) const REFERENCIA_CARGA: Duration = Duration::from_millis(1500);
+
+/// Lo que tarda en aparecer la aplicación tras el login en una conexión promedio
+pub(crate) const REFERENCIA_LOGIN: Duration = Duration::from_secs(2);
+
+/// Cada cuánto se consulta la página en las esperas activas
+const INTERVALO_SONDEO: Duration = Duration::from_millis(250);
+
+/// Factor (en centésimos) que corresponde a una medición respecto de su referencia
+fn factor_desde_medicion(medido: Duration, referencia: Duration) -> u32 {
+    let referencia = referencia.as_millis().max(1);
+    let factor = medido.as_millis() * u128::from(FACTOR_NEUTRO) / referencia;
+    (factor.min(u128::from(FACTOR_MAXIMO)) as u32).max(FACTOR_MINIMO)
+}
+
+/// Vuelve a los tiempos originales; se llama al empezar cada corrida
+pub(crate) fn reiniciar() {
+    FACTOR.store(FACTOR_NEUTRO, Ordering::Relaxed);
+}
+
+/// Incorpora una medición, promediándola con el factor que ya había para que
+/// una sola operación lenta o rápida no lo mueva demasiado
+pub(crate) fn calibrar(medido: Duration, referencia: Duration) {
+    let nuevo = factor_desde_medicion(medido, referencia);
+    let _ = FACTOR.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |actual| {
+        Some((actual + nuevo) / 2)
+    });
+}
+
+/// Duración `base` escalada por el factor actual
+pub(crate) fn escalar(base: Duration) -> Duration {
+    base * FACTOR.load(Ordering::Relaxed) / FACTOR_NEUTRO
+}
+
+/// Espera `base` escalada por el factor actual
+pub(crate) async fn esperar(base: Duration) {
+    sleep(escalar(base)).await;
+}
+
+/// Espera activamente a que aparezca algún elemento con `selector`, como mucho
+/// `tope`. Devuelve cuánto tardó, o `None` si no apareció.
+pub(crate) async fn esperar_elemento(
+    page: &Page,
+    selector: &str,
+    tope: Duration,
+) -> Option<Duration> {
+    let inicio = Instant::now();
+    loop {
+        let hay = page
+            .find_elements(selector)
+            .await
+            .is_ok_and(|e| !e.is_empty());
+        if hay {
+            return Some(inicio.elapsed());
+        }
+        if inicio.elapsed() >= tope {
+            return None;
+        }
+        sleep(INTERVALO_SONDEO).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factor_proporcional_y_acotado() {
+        let referencia = Duration::from_secs(2);
+        assert_eq!(
+            factor_desde_medicion(Duration::from_secs(2), referencia),
+            100
+        );
+        assert_eq!(
+            factor_desde_medicion(Duration::from_secs(3), referencia),
+            150
+        );
+        assert_eq!(
+            factor_desde_medicion(Duration::from_millis(100), referencia),
+            FACTOR_MINIMO
+        );
+        assert_eq!(
+            factor_desde_medicion(Duration::from_secs(60), referencia),
+            FACTOR_MAXIMO
+        );
+        assert_eq!(
+            factor_desde_medicion(Duration::ZERO, Duration::ZERO),
+            FACTOR_MINIMO
+        );
+    }
+}
@@ -1,41 +1,242 @@
-use crate::pdf_extractor::DatosPdf;
+use crate::config::Config;
+use crate::file_processor::ruta_libre;
+use crate::pdf_extractor::{DatosPdf, Resultado};
+use crate::registro::registrar;
 use anyhow::{Context, Result};
-use calamine::{open_workbook, Reader, Xlsx};
+use calamine::{open_workbook, open_workbook_auto, Data, Reader, Xlsx};
 use chrono::NaiveDate;
-use rust_xlsxwriter::{Format, Workbook};
-use std::path::Path;
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Nombre de la hoja principal generada por `guardar_excel`
+const HOJA_DATOS: &str = "CCOO revisar";
+
+/// Nombre de la hoja de resumen generada por `guardar_excel`
+const HOJA_RESUMEN: &str = "Resumen";
+
+/// Cómo se muestran los PDFs sin organismo en el resumen y en la exportación por organismo
+pub(crate) const SIN_ORGANISMO: &str = "Sin organismo";
+
+/// Largo máximo que Excel admite para el nombre de una hoja
+const LARGO_MAXIMO_HOJA: usize = 31;
+
+/// Campos de `DatosPdf` que se pueden volcar a una columna del Excel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CampoDatos {
+    Ccoo,
+    Organismo,
+    Patrimonial,
+    Fecha,
+    Resultado,
+    OrganismosExtra,
+    Carpeta,
+    Archivo,
+}
+
+impl CampoDatos {
+    /// Obtiene el campo a partir de su nombre (sin distinguir mayúsculas)
+    fn desde_nombre(nombre: &str) -> Option<Self> {
+        match nombre.trim().to_lowercase().as_str() {
+            "ccoo" => Some(CampoDatos::Ccoo),
+            "organismo" => Some(CampoDatos::Organismo),
+            "patrimonial" => Some(CampoDatos::Patrimonial),
+            "fecha" => Some(CampoDatos::Fecha),
+            "resultado" => Some(CampoDatos::Resultado),
+            "organismos_extra" => Some(CampoDatos::OrganismosExtra),
+            "carpeta" => Some(CampoDatos::Carpeta),
+            "archivo" => Some(CampoDatos::Archivo),
+            _ => None,
+        }
+    }
+
+    /// Valor del campo como texto (la fecha se escribe aparte con formato)
+    fn valor_texto(self, dato: &DatosPdf) -> String {
+        match self {
+            CampoDatos::Ccoo => dato.ccoo.clone(),
+            CampoDatos::Organismo => dato.organismo.clone(),
+            CampoDatos::Patrimonial => dato.patrimonial.clone(),
+            CampoDatos::Fecha => dato
+                .fecha
+                .map(|f| f.format("%d/%m/%Y").to_string())
+                .unwrap_or_default(),
+            CampoDatos::Resultado => dato.resultado.texto().to_string(),
+            CampoDatos::OrganismosExtra => dato.organismos_extra.join("; "),
+            CampoDatos::Carpeta => dato.carpeta.clone(),
+            CampoDatos::Archivo => dato.archivo.clone(),
+        }
+    }
+}
+
+/// Columna del Excel de salida: qué campo contiene y con qué encabezado
+#[derive(Debug, Clone)]
+pub struct Columna {
+    pub campo: CampoDatos,
+    pub encabezado: String,
+}
+
+/// Mapeo de columnas del Excel de salida, en orden
+#[derive(Debug, Clone)]
+pub struct ColumnasConfig {
+    pub columnas: Vec<Columna>,
+}
+
+impl Default for ColumnasConfig {
+    /// Columnas históricas de la planilla "Listado RDP a copiar"
+    fn default() -> Self {
+        let columnas = [
+            (CampoDatos::Ccoo, "CCOO N°"),
+            (CampoDatos::Organismo, "ORGANISMO"),
+            (CampoDatos::Patrimonial, "Institucional Patrimonial"),
+            (CampoDatos::Fecha, "Fecha"),
+            (CampoDatos::Resultado, "RESULTADO INVENTARIO FISICO"),
+            (CampoDatos::OrganismosExtra, "OTROS ORGANISMOS"),
+            (CampoDatos::Carpeta, "CARPETA"),
+            (CampoDatos::Archivo, "ARCHIVO"),
+        ];
+        ColumnasConfig {
+            columnas: columnas
+                .into_iter()
+                .map(|(campo, encabezado)| Columna {
+                    campo,
+                    encabezado: encabezado.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ColumnasConfig {
+    /// Interpreta un mapeo con el formato `campo=Encabezado;campo=Encabezado`; cada
+    /// campo puede aparecer una sola vez y con encabezado. Campos válidos: ccoo, organismo, patrimonial, fecha, resultado, organismos_extra,
+    /// carpeta, archivo
+    pub fn desde_texto(texto: &str) -> Result<Self> {
+        let mut columnas = Vec::new();
+
+        for par in texto.split(';').filter(|p| !p.trim().is_empty()) {
+            let (nombre, encabezado) = par
+                .split_once('=')
+                .with_context(|| format!("Columna sin '=': '{}'", par.trim()))?;
+            let campo = CampoDatos::desde_nombre(nombre)
+                .with_context(|| format!("Campo de columna desconocido: '{}'", nombre.trim()))?;
+            if columnas.iter().any(|c: &Columna| c.campo == campo) {
+                anyhow::bail!("El campo '{}' está repetido en el mapeo", nombre.trim());
+            }
+            let encabezado = encabezado.trim();
+            if encabezado.is_empty() {
+                anyhow::bail!("La columna '{}' no tiene encabezado", nombre.trim());
+            }
+            columnas.push(Columna {
+                campo,
+                encabezado: encabezado.to_string(),
+            });
+        }
+
+        if columnas.is_empty() {
+            anyhow::bail!("El mapeo de columnas está vacío");
+        }
+
+        Ok(ColumnasConfig { columnas })
+    }
+
+    /// Columnas configuradas en `SADE_COLUMNAS_EXCEL`, o las de siempre si no hay
+    pub fn desde_config(config: &Config) -> Result<Self> {
+        match config.columnas_excel.as_deref() {
+            Some(texto) => {
+                Self::desde_texto(texto).context("Valor inválido en SADE_COLUMNAS_EXCEL")
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Índice de la columna que contiene el campo dado
+    fn posicion(&self, campo: CampoDatos) -> Option<usize> {
+        self.columnas.iter().position(|c| c.campo == campo)
+    }
+}
+
+/// Encabezado de la columna que indica de qué archivo proviene cada fila consolidada
+const ENCABEZADO_ORIGEN: &str = "ORIGEN";
 
 /// Convierte una fecha NaiveDate al número serial de Excel
 /// Excel usa el sistema de fechas 1900, donde el 1 de enero de 1900 = 1
 /// Nota: Excel tiene un bug histórico donde considera 1900 como año bisiesto
-fn fecha_a_excel_serial(fecha: &NaiveDate) -> f64 {
-    // Fecha base de Excel: 30 de diciembre de 1899
-    // (Excel cuenta desde 1, y tiene el bug del año bisiesto 1900)
-    let fecha_base = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap();
+/// (existe el serial 60 = 29/02/1900), así que a partir del 1 de marzo de 1900
+/// los seriales quedan corridos un día respecto del calendario real.
+/// Las fechas anteriores a 1900 no son representables y devuelven error.
+fn fecha_a_excel_serial(fecha: &NaiveDate) -> Result<f64> {
+    let inicio_1900 = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap();
+    let marzo_1900 = NaiveDate::from_ymd_opt(1900, 3, 1).unwrap();
+
+    if *fecha < inicio_1900 {
+        anyhow::bail!(
+            "La fecha {} es anterior a 1900 y no se puede representar en Excel",
+            fecha.format("%d/%m/%Y")
+        );
+    }
+
+    // Antes del 29/02/1900 ficticio: 1 de enero de 1900 = 1
+    // Desde el 1 de marzo: base 30/12/1899, que absorbe el día inexistente
+    let fecha_base = if *fecha < marzo_1900 {
+        NaiveDate::from_ymd_opt(1899, 12, 31).unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(1899, 12, 30).unwrap()
+    };
     let dias = fecha.signed_duration_since(fecha_base).num_days();
-    dias as f64
+    Ok(dias as f64)
 }
 
-/// Guarda los datos extraídos en un archivo Excel
-/// Equivalente a `guardar_dataframe` en Python
-pub fn guardar_excel(datos: &[DatosPdf], ruta_salida: &Path) -> Result<()> {
-    let mut workbook = Workbook::new();
+/// Convierte un número serial de Excel a fecha (inversa de `fecha_a_excel_serial`).
+/// Devuelve `None` para seriales menores a 1 y para el 29/02/1900 ficticio (60).
+fn excel_serial_a_fecha(serial: f64) -> Option<NaiveDate> {
+    let dias = serial.trunc() as i64;
+    let fecha_base = match dias {
+        i64::MIN..=0 | 60 => return None,
+        1..=59 => NaiveDate::from_ymd_opt(1899, 12, 31)?,
+        _ => NaiveDate::from_ymd_opt(1899, 12, 30)?,
+    };
+    fecha_base.checked_add_signed(chrono::Duration::days(dias))
+}
 
-    // Crear nueva hoja
-    let worksheet = workbook.add_worksheet();
-    worksheet.set_name("CCOO revisar")?;
+/// Formatos de fecha aceptados en celdas de texto. Los de año con dos dígitos van
+/// primero porque chrono acepta "07/05/24" con `%d/%m/%Y` como el año 24.
+const FORMATOS_FECHA_TEXTO: [&str; 5] =
+    ["%d/%m/%y", "%d/%m/%Y", "%d-%m-%y", "%d-%m-%Y", "%Y-%m-%d"];
 
-    // Escribir encabezados
-    let headers = [
-        "CCOO N°",
-        "ORGANISMO",
-        "Institucional Patrimonial",
-        "Fecha",
-        "RESULTADO INVENTARIO FISICO",
-    ];
+/// Lee la fecha de una celda de un Excel existente, la haya guardado la aplicación
+/// (serial con formato `dd/mm/yyyy`) o escrito alguien a mano: acepta fechas de
+/// Excel, seriales numéricos, fechas ISO y texto `dd/mm/yyyy`. La hora se descarta.
+pub fn leer_fecha_celda(celda: &Data) -> Option<NaiveDate> {
+    match celda {
+        Data::DateTime(dt) => excel_serial_a_fecha(dt.as_f64()),
+        Data::Float(f) => excel_serial_a_fecha(*f),
+        Data::Int(i) => excel_serial_a_fecha(*i as f64),
+        Data::DateTimeIso(texto) => texto
+            .get(..10)
+            .and_then(|fecha| NaiveDate::parse_from_str(fecha, "%Y-%m-%d").ok()),
+        Data::String(texto) => {
+            // Descartar la hora si viene como "dd/mm/yyyy hh:mm"
+            let fecha = texto.split_whitespace().next()?;
+            // Las fechas anteriores a 1900 se guardan como texto y se leen de aquí
+            FORMATOS_FECHA_TEXTO
+                .iter()
+                .find_map(|formato| NaiveDate::parse_from_str(fecha, formato).ok())
+        }
+        _ => None,
+    }
+}
 
-    for (col, header) in headers.iter().enumerate() {
-        worksheet.write_string(0, col as u16, *header)?;
+/// Escribe encabezados y filas de datos en la hoja dada
+fn escribir_datos(
+    worksheet: &mut Worksheet,
+    datos: &[DatosPdf],
+    columnas: &ColumnasConfig,
+) -> Result<()> {
+    // Escribir encabezados
+    for (col, columna) in columnas.columnas.iter().enumerate() {
+        worksheet.write_string(0, col as u16, &columna.encabezado)?;
     }
 
     // Crear formato de fecha para Excel (dd/mm/yyyy)
@@ -44,61 +245,736 @@ pub fn guardar_excel(datos: &[DatosPdf], ruta_salida: &Path) -> Result<()> {
     // Escribir datos
     for (row, dato) in datos.iter().enumerate() {
         let row_num = (row + 1) as u32;
-        worksheet.write_string(row_num, 0, &dato.ccoo)?;
-        worksheet.write_string(row_num, 1, &dato.organismo)?;
-        worksheet.write_string(row_num, 2, &dato.patrimonial)?;
-
-        // Escribir fecha como número con formato de fecha
-        // Excel la reconocerá automáticamente como fecha
-        if let Some(ref fecha) = dato.fecha {
-            let serial = fecha_a_excel_serial(fecha);
-            worksheet.write_number_with_format(row_num, 3, serial, &formato_fecha)?;
-        } else {
-            worksheet.write_string(row_num, 3, "")?;
+
+        for (col, columna) in columnas.columnas.iter().enumerate() {
+            let col = col as u16;
+            match (columna.campo, dato.fecha) {
+                // Escribir fecha como número con formato de fecha
+                // Excel la reconocerá automáticamente como fecha
+                (CampoDatos::Fecha, Some(ref fecha)) => match fecha_a_excel_serial(fecha) {
+                    Ok(serial) => {
+                        worksheet.write_number_with_format(row_num, col, serial, &formato_fecha)?;
+                    }
+                    Err(e) => {
+                        // Una fecha fuera de rango no debe hacer perder el resto de las filas
+                        registrar!("{}: {:#}, se escribe como texto", dato.ccoo, e);
+                        worksheet.write_string(row_num, col, columna.campo.valor_texto(dato))?;
+                    }
+                },
+                (campo, _) => {
+                    worksheet.write_string(row_num, col, campo.valor_texto(dato))?;
+                }
+            }
         }
+    }
+
+    Ok(())
+}
 
-        worksheet.write_string(row_num, 4, &dato.resultado)?;
+/// Organismo del PDF, o `SIN_ORGANISMO` si no tiene
+fn nombre_organismo(dato: &DatosPdf) -> &str {
+    let organismo = dato.organismo.trim();
+    if organismo.is_empty() {
+        SIN_ORGANISMO
+    } else {
+        organismo
     }
+}
+
+/// Convierte un texto en un nombre de hoja válido para Excel: sin `[ ] : * ? / \`,
+/// sin apóstrofos en los extremos, de hasta 31 caracteres y distinto (sin
+/// distinguir mayúsculas) de los ya `usados`
+fn nombre_hoja(texto: &str, usados: &[String]) -> String {
+    let limpio: String = texto
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .collect();
+    let limpio = limpio.trim().trim_matches('\'').trim();
+    let base: String = if limpio.is_empty() {
+        SIN_ORGANISMO.to_string()
+    } else {
+        limpio.chars().take(LARGO_MAXIMO_HOJA).collect()
+    };
 
-    // Intentar agregar a archivo existente o crear nuevo
-    if ruta_salida.exists() {
-        // Si el archivo existe, intentamos agregar una nueva hoja
-        // Nota: rust_xlsxwriter no soporta edición de archivos existentes directamente
-        // Así que creamos un nuevo archivo con sufijo
-        let stem = ruta_salida
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("output");
-        let extension = ruta_salida
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("xlsx");
-        let parent = ruta_salida.parent().unwrap_or(Path::new("."));
+    // Excel compara los nombres sin distinguir mayúsculas, también las acentuadas
+    let repetido = |nombre: &str| {
+        let buscado = nombre.to_lowercase();
+        usados.iter().any(|u| u.to_lowercase() == buscado)
+    };
+    if !repetido(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| {
+            let sufijo = format!(" ({})", n);
+            let recorte: String = base
+                .chars()
+                .take(LARGO_MAXIMO_HOJA - sufijo.chars().count())
+                .collect();
+            format!("{}{}", recorte.trim_end(), sufijo)
+        })
+        .find(|nombre| !repetido(nombre))
+        .unwrap_or(base)
+}
 
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let nuevo_nombre = format!("{}_{}.{}", stem, timestamp, extension);
-        let nueva_ruta = parent.join(nuevo_nombre);
+/// Escribe la hoja de resumen: totales por resultado y desglose por organismo
+fn escribir_resumen(worksheet: &mut Worksheet, datos: &[DatosPdf]) -> Result<u32> {
+    let negrita = Format::new().set_bold();
+    let indice = |resultado: Resultado| {
+        Resultado::TODOS
+            .iter()
+            .position(|r| *r == resultado)
+            .unwrap_or_default()
+    };
 
-        workbook
-            .save(&nueva_ruta)
-            .with_context(|| format!("Error al guardar Excel en {:?}", nueva_ruta))?;
+    let mut totales = [0usize; Resultado::TODOS.len()];
+    for dato in datos {
+        totales[indice(dato.resultado)] += 1;
+    }
 
-        println!("Archivo guardado en {:?}", nueva_ruta);
-    } else {
-        workbook
-            .save(ruta_salida)
-            .with_context(|| format!("Error al guardar Excel en {:?}", ruta_salida))?;
+    worksheet.write_string_with_format(0, 0, "Total de PDFs", &negrita)?;
+    worksheet.write_number(0, 1, datos.len() as f64)?;
+    for (i, resultado) in Resultado::TODOS.iter().enumerate() {
+        let fila = 1 + i as u32;
+        worksheet.write_string_with_format(fila, 0, resultado.texto(), &negrita)?;
+        worksheet.write_number(fila, 1, totales[i] as f64)?;
     }
 
+    // Desglose por organismo, ordenado alfabéticamente
+    let mut por_organismo: BTreeMap<&str, [usize; Resultado::TODOS.len()]> = BTreeMap::new();
+    for dato in datos {
+        por_organismo.entry(nombre_organismo(dato)).or_default()[indice(dato.resultado)] += 1;
+    }
+
+    let fila_inicio = Resultado::TODOS.len() as u32 + 2;
+    let encabezados = ["Organismo", "Total"]
+        .into_iter()
+        .chain(Resultado::TODOS.iter().map(|r| r.texto()));
+    for (col, encabezado) in encabezados.enumerate() {
+        worksheet.write_string_with_format(fila_inicio, col as u16, encabezado, &negrita)?;
+    }
+
+    for (i, (organismo, cantidades)) in por_organismo.iter().enumerate() {
+        let fila = fila_inicio + 1 + i as u32;
+        worksheet.write_string(fila, 0, *organismo)?;
+        worksheet.write_number(fila, 1, cantidades.iter().sum::<usize>() as f64)?;
+        for (col, cantidad) in cantidades.iter().enumerate() {
+            worksheet.write_number(fila, 2 + col as u16, *cantidad as f64)?;
+        }
+    }
+
+    worksheet.set_column_width(0, 40)?;
+
+    // Primera fila libre debajo del desglose
+    Ok(fila_inicio + por_organismo.len() as u32 + 2)
+}
+
+/// Mensaje para cuando el Excel está abierto en otro programa
+const MENSAJE_EXCEL_ABIERTO: &str = "Cierre el archivo Excel antes de procesar";
+
+/// Indica si el error de E/S es el de un archivo bloqueado por otro programa:
+/// permiso denegado, o las violaciones de uso compartido y de bloqueo de Windows
+fn es_archivo_bloqueado(error: &std::io::Error) -> bool {
+    error.kind() == std::io::ErrorKind::PermissionDenied
+        || matches!(error.raw_os_error(), Some(32 | 33))
+}
+
+/// Falla con un mensaje claro si el Excel está abierto en otro programa. Solo se
+/// confía en el bloqueo de Windows: el `~$<nombre>` que deja Excel puede quedar
+/// huérfano tras un cierre inesperado y no indica que el archivo siga abierto.
+pub fn verificar_excel_cerrado(ruta: &Path) -> Result<()> {
+    let bloqueado = ruta.exists()
+        && std::fs::OpenOptions::new()
+            .append(true)
+            .open(ruta)
+            .is_err_and(|e| es_archivo_bloqueado(&e));
+    if bloqueado {
+        anyhow::bail!("{}: {}", MENSAJE_EXCEL_ABIERTO, ruta.display());
+    }
     Ok(())
 }
 
-/// Lee un archivo Excel existente (para referencia futura)
-#[allow(dead_code)]
-pub fn leer_excel(ruta: &Path) -> Result<Vec<Vec<String>>> {
+/// Guarda el libro en `ruta`, traduciendo el error de archivo bloqueado
+fn guardar_en(workbook: &mut Workbook, ruta: &Path) -> Result<()> {
+    match workbook.save(ruta) {
+        Err(XlsxError::IoError(e)) if es_archivo_bloqueado(&e) => {
+            anyhow::bail!("{}: {}", MENSAJE_EXCEL_ABIERTO, ruta.display())
+        }
+        resultado => resultado.with_context(|| format!("Error al guardar Excel en {:?}", ruta)),
+    }
+}
+
+/// Qué hacer cuando el Excel de salida ya existe
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModoGuardado {
+    /// Guardar encima; con `respaldo` antes se copia el anterior a `<nombre> respaldo.xlsx`
+    /// (o `<nombre> respaldo (n).xlsx` si ya hay respaldos)
+    Sobrescribir { respaldo: bool },
+    /// Sumar las filas al Excel existente; las de un CCOO repetido se reemplazan
+    Anexar,
+    /// Crear otro archivo con sufijo de fecha y hora
+    #[default]
+    NuevoConTimestamp,
+}
+
+impl ModoGuardado {
+    pub const TODOS: [ModoGuardado; 3] = [
+        ModoGuardado::NuevoConTimestamp,
+        ModoGuardado::Sobrescribir { respaldo: true },
+        ModoGuardado::Anexar,
+    ];
+
+    pub fn descripcion(&self) -> &'static str {
+        match self {
+            ModoGuardado::Sobrescribir { .. } => "Sobrescribir",
+            ModoGuardado::Anexar => "Anexar",
+            ModoGuardado::NuevoConTimestamp => "Nuevo con fecha y hora",
+        }
+    }
+}
+
+/// Sufijo de la copia del Excel que se hace antes de sobrescribirlo
+pub const SUFIJO_RESPALDO: &str = "respaldo";
+
+/// Guarda el libro en la ruta indicada; si el archivo ya existe actúa según `modo`
+/// (con `Anexar` se guarda encima: las filas ya se combinaron antes).
+/// Devuelve la ruta efectivamente usada.
+/// Si el archivo que se va a escribir está abierto en otro programa falla pidiendo
+/// que se cierre; con `NuevoConTimestamp` el existente puede seguir abierto.
+fn guardar_libro(
+    workbook: &mut Workbook,
+    ruta_salida: &Path,
+    modo: ModoGuardado,
+) -> Result<PathBuf> {
+    if !ruta_salida.exists() {
+        guardar_en(workbook, ruta_salida)?;
+        return Ok(ruta_salida.to_path_buf());
+    }
+
+    match modo {
+        ModoGuardado::NuevoConTimestamp => {
+            // rust_xlsxwriter no edita archivos existentes, así que se crea
+            // uno nuevo con sufijo
+            let stem = ruta_salida
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("output");
+            let extension = ruta_salida
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or("xlsx");
+            let parent = ruta_salida.parent().unwrap_or(Path::new("."));
+
+            let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+            let nuevo_nombre = format!("{}_{}.{}", stem, timestamp, extension);
+            let nueva_ruta = parent.join(nuevo_nombre);
+
+            guardar_en(workbook, &nueva_ruta)?;
+
+            registrar!(
+                "{} ya existía, el Excel se guardó en {}",
+                ruta_salida.display(),
+                nueva_ruta.display()
+            );
+            Ok(nueva_ruta)
+        }
+        ModoGuardado::Sobrescribir { respaldo } => {
+            verificar_excel_cerrado(ruta_salida)?;
+            if respaldo {
+                // Un respaldo anterior no se pisa: se agrega ` (1)`, ` (2)`...
+                let copia = ruta_libre(&ruta_con_sufijo(ruta_salida, SUFIJO_RESPALDO));
+                std::fs::copy(ruta_salida, &copia).with_context(|| {
+                    format!(
+                        "No se pudo respaldar {} en {:?}",
+                        ruta_salida.display(),
+                        copia
+                    )
+                })?;
+                registrar!("Excel anterior respaldado en {}", copia.display());
+            }
+            guardar_en(workbook, ruta_salida)?;
+            Ok(ruta_salida.to_path_buf())
+        }
+        ModoGuardado::Anexar => {
+            verificar_excel_cerrado(ruta_salida)?;
+            guardar_en(workbook, ruta_salida)?;
+            Ok(ruta_salida.to_path_buf())
+        }
+    }
+}
+
+/// Filas `existentes` seguidas de las `nuevas`; una nueva con el mismo CCOO que
+/// una existente la reemplaza en su lugar
+fn anexar_datos(existentes: Vec<DatosPdf>, nuevas: &[DatosPdf]) -> Vec<DatosPdf> {
+    let mut filas = existentes;
+    let mut indices: HashMap<String, usize> = filas
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (d.ccoo.clone(), i))
+        .collect();
+    for dato in nuevas {
+        match indices.get(&dato.ccoo) {
+            Some(&i) => filas[i] = dato.clone(),
+            None => {
+                indices.insert(dato.ccoo.clone(), filas.len());
+                filas.push(dato.clone());
+            }
+        }
+    }
+    filas
+}
+
+/// Guarda los datos extraídos en un archivo Excel y devuelve la ruta en que quedó.
+/// Si el archivo ya existe se lo sobrescribe, se le anexan las filas o se crea
+/// uno nuevo con fecha y hora según `modo`.
+/// Equivalente a `guardar_dataframe` en Python
+pub fn guardar_excel(
+    datos: &[DatosPdf],
+    ruta_salida: &Path,
+    columnas: &ColumnasConfig,
+    orden: OrdenFilas,
+    modo: ModoGuardado,
+) -> Result<PathBuf> {
+    let anexados;
+    let datos = if modo == ModoGuardado::Anexar && ruta_salida.exists() {
+        let existentes = leer_datos_excel(ruta_salida, columnas).with_context(|| {
+            format!(
+                "No se pudieron leer las filas de {} para anexar",
+                ruta_salida.display()
+            )
+        })?;
+        anexados = anexar_datos(existentes, datos);
+        &anexados[..]
+    } else {
+        datos
+    };
+
+    let mut workbook = Workbook::new();
+
+    // Crear nueva hoja
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(HOJA_DATOS)?;
+    escribir_datos(worksheet, &ordenar_datos(datos, orden), columnas)?;
+
+    // Hoja de resumen para una vista rápida del supervisor
+    let resumen = workbook.add_worksheet();
+    resumen.set_name(HOJA_RESUMEN)?;
+    escribir_resumen(resumen, datos)?;
+
+    guardar_libro(&mut workbook, ruta_salida, modo)
+}
+
+/// Ruta hermana de `ruta` con un sufijo en el nombre, por ejemplo
+/// `Listado.xlsx` -> `Listado por organismo.xlsx`
+pub fn ruta_con_sufijo(ruta: &Path, sufijo: &str) -> PathBuf {
+    let stem = ruta
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    ruta.with_file_name(format!("{} {}.xlsx", stem, sufijo))
+}
+
+/// Sufijo del Excel que solo tiene las filas con novedades o para revisar
+pub const SUFIJO_CON_NOVEDADES: &str = "con novedades";
+
+/// Filas que no son "Sin novedad": con novedades y para revisar a mano
+fn filas_con_novedades(datos: &[DatosPdf]) -> Vec<DatosPdf> {
+    datos
+        .iter()
+        .filter(|d| d.resultado != Resultado::SinNovedad)
+        .cloned()
+        .collect()
+}
+
+/// Guarda solo las filas que no son "Sin novedad" (con novedades y para revisar a
+/// mano), en el orden pedido. El resumen cuenta todos los PDFs procesados e indica
+/// cuántas filas se exportaron de cuántas.
+pub fn guardar_excel_filtrado(
+    datos: &[DatosPdf],
+    ruta_salida: &Path,
+    columnas: &ColumnasConfig,
+    orden: OrdenFilas,
+) -> Result<PathBuf> {
+    let filtrados = filas_con_novedades(datos);
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(HOJA_DATOS)?;
+    escribir_datos(worksheet, &ordenar_datos(&filtrados, orden), columnas)?;
+
+    let resumen = workbook.add_worksheet();
+    resumen.set_name(HOJA_RESUMEN)?;
+    let fila = escribir_resumen(resumen, datos)?;
+    resumen.write_string_with_format(
+        fila,
+        0,
+        "Filas exportadas (sin \"Sin novedad\")",
+        &Format::new().set_bold(),
+    )?;
+    resumen.write_string(fila, 1, format!("{} de {}", filtrados.len(), datos.len()))?;
+
+    guardar_libro(&mut workbook, ruta_salida, ModoGuardado::NuevoConTimestamp)
+}
+
+/// Columna por la que se ordenan las filas del Excel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrdenarPor {
+    /// En el orden en que se procesaron los PDFs
+    #[default]
+    Procesamiento,
+    Ccoo,
+    Fecha,
+    Organismo,
+}
+
+impl OrdenarPor {
+    pub const TODOS: [OrdenarPor; 4] = [
+        OrdenarPor::Procesamiento,
+        OrdenarPor::Ccoo,
+        OrdenarPor::Fecha,
+        OrdenarPor::Organismo,
+    ];
+
+    pub fn descripcion(&self) -> &'static str {
+        match self {
+            OrdenarPor::Procesamiento => "Procesamiento",
+            OrdenarPor::Ccoo => "CCOO",
+            OrdenarPor::Fecha => "Fecha",
+            OrdenarPor::Organismo => "Organismo",
+        }
+    }
+}
+
+/// Orden de las filas al guardar el Excel
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrdenFilas {
+    pub por: OrdenarPor,
+    /// Agrupar al final las filas "Con novedades"
+    pub novedades_al_final: bool,
+}
+
+/// Parte de un texto para el orden natural: los números se comparan por valor
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Trozo {
+    Numero(u64),
+    Texto(String),
+}
+
+/// Clave de orden natural, para que `NO-2024-9-...` quede antes que `NO-2024-10-...`
+fn clave_natural(texto: &str) -> Vec<Trozo> {
+    let mut trozos = Vec::new();
+    let mut actual = String::new();
+    let mut en_numero = false;
+    let cerrar = |actual: &mut String, en_numero: bool, trozos: &mut Vec<Trozo>| {
+        if actual.is_empty() {
+            return;
+        }
+        trozos.push(if en_numero {
+            Trozo::Numero(actual.parse().unwrap_or(u64::MAX))
+        } else {
+            Trozo::Texto(actual.to_lowercase())
+        });
+        actual.clear();
+    };
+    for c in texto.chars() {
+        if c.is_ascii_digit() != en_numero {
+            cerrar(&mut actual, en_numero, &mut trozos);
+            en_numero = c.is_ascii_digit();
+        }
+        actual.push(c);
+    }
+    cerrar(&mut actual, en_numero, &mut trozos);
+    trozos
+}
+
+/// Compara dos filas según la columna elegida; los vacíos van al final
+fn comparar_por(a: &DatosPdf, b: &DatosPdf, por: OrdenarPor) -> Ordering {
+    match por {
+        OrdenarPor::Procesamiento => Ordering::Equal,
+        OrdenarPor::Ccoo => clave_natural(&a.ccoo).cmp(&clave_natural(&b.ccoo)),
+        OrdenarPor::Fecha => a
+            .fecha
+            .is_none()
+            .cmp(&b.fecha.is_none())
+            .then(a.fecha.cmp(&b.fecha)),
+        OrdenarPor::Organismo => {
+            let (oa, ob) = (a.organismo.trim(), b.organismo.trim());
+            oa.is_empty()
+                .cmp(&ob.is_empty())
+                .then_with(|| oa.to_lowercase().cmp(&ob.to_lowercase()))
+        }
+    }
+}
+
+/// Copia de los datos en el orden pedido. El orden es estable: las filas que
+/// empatan conservan el orden de procesamiento.
+fn ordenar_datos(datos: &[DatosPdf], orden: OrdenFilas) -> Vec<DatosPdf> {
+    let mut ordenados = datos.to_vec();
+    ordenados.sort_by(|a, b| {
+        let novedades =
+            |d: &DatosPdf| orden.novedades_al_final && d.resultado == Resultado::ConNovedades;
+        novedades(a)
+            .cmp(&novedades(b))
+            .then_with(|| comparar_por(a, b, orden.por))
+    });
+    ordenados
+}
+
+/// Guarda los datos en un Excel con una hoja por organismo, ordenadas alfabéticamente,
+/// y devuelve la ruta en que quedó. Los PDFs sin organismo van a la hoja "Sin organismo".
+pub fn guardar_excel_por_organismo(
+    datos: &[DatosPdf],
+    ruta_salida: &Path,
+    columnas: &ColumnasConfig,
+    orden: OrdenFilas,
+) -> Result<PathBuf> {
+    let ordenados = ordenar_datos(datos, orden);
+    let mut por_organismo: BTreeMap<&str, Vec<DatosPdf>> = BTreeMap::new();
+    for dato in &ordenados {
+        por_organismo
+            .entry(nombre_organismo(dato))
+            .or_default()
+            .push(dato.clone());
+    }
+
+    let mut workbook = Workbook::new();
+    let mut usados: Vec<String> = Vec::new();
+    for (organismo, datos_organismo) in &por_organismo {
+        let nombre = nombre_hoja(organismo, &usados);
+        let worksheet = workbook.add_worksheet();
+        worksheet
+            .set_name(&nombre)
+            .with_context(|| format!("Nombre de hoja inválido: '{}'", nombre))?;
+        escribir_datos(worksheet, datos_organismo, columnas)?;
+        usados.push(nombre);
+    }
+
+    // Un libro sin hojas no se puede guardar
+    if usados.is_empty() {
+        workbook.add_worksheet().set_name(HOJA_DATOS)?;
+    }
+
+    guardar_libro(&mut workbook, ruta_salida, ModoGuardado::NuevoConTimestamp)
+}
+
+/// Ruta del JSON con los datos junto al Excel (`<nombre> datos.json`); distinta
+/// del JSON de metadatos, que usa el mismo nombre que el Excel
+pub fn ruta_json(ruta_excel: &Path) -> PathBuf {
+    let stem = ruta_excel
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    ruta_excel.with_file_name(format!("{} datos.json", stem))
+}
+
+/// Guarda los datos como un array JSON, para que otros sistemas los consuman
+/// sin leer el Excel. Las fechas van en formato ISO y las ausentes como `null`.
+pub fn guardar_json(datos: &[DatosPdf], ruta: &Path) -> Result<()> {
+    let contenido = serde_json::to_string_pretty(datos)?;
+    std::fs::write(ruta, contenido)
+        .with_context(|| format!("No se pudo guardar el JSON en {:?}", ruta))
+}
+
+/// Procedencia de un Excel generado, para auditoría. Se guarda en un JSON con el
+/// mismo nombre que el Excel.
+#[derive(Debug, Serialize)]
+pub struct MetadatosCorrida {
+    /// Fecha y hora local de la corrida, en formato RFC 3339
+    pub fecha_corrida: String,
+    /// Operador de SADE, o el usuario del sistema si no se conoce
+    pub usuario: String,
+    pub cantidad_procesados: usize,
+    pub cantidad_sin_novedad: usize,
+    pub cantidad_con_novedades: usize,
+    pub cantidad_revision_manual: usize,
+    pub version_app: String,
+}
+
+impl MetadatosCorrida {
+    /// Metadatos de una corrida que generó `datos`, hecha por `usuario`
+    pub fn nuevos(datos: &[DatosPdf], usuario: Option<&str>) -> Self {
+        let contar =
+            |resultado: Resultado| datos.iter().filter(|d| d.resultado == resultado).count();
+        let usuario = usuario
+            .map(str::to_string)
+            .or_else(|| std::env::var("USERNAME").ok())
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_default();
+        MetadatosCorrida {
+            fecha_corrida: chrono::Local::now().to_rfc3339(),
+            usuario,
+            cantidad_procesados: datos.len(),
+            cantidad_sin_novedad: contar(Resultado::SinNovedad),
+            cantidad_con_novedades: contar(Resultado::ConNovedades),
+            cantidad_revision_manual: contar(Resultado::RevisionManual),
+            version_app: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// Guarda los metadatos junto al Excel (`<nombre del Excel>.json`) y devuelve la ruta
+pub fn guardar_metadatos(ruta_excel: &Path, metadatos: &MetadatosCorrida) -> Result<PathBuf> {
+    let ruta = ruta_excel.with_extension("json");
+    let contenido = serde_json::to_string_pretty(metadatos)?;
+    std::fs::write(&ruta, contenido)
+        .with_context(|| format!("Error al guardar los metadatos en {:?}", ruta))?;
+    Ok(ruta)
+}
+
+/// Resultado de consolidar varios Excels generados por la aplicación
+#[derive(Debug)]
+pub struct ResultadoConsolidacion {
+    pub filas: usize,
+    pub archivos_leidos: usize,
+    pub ruta_salida: PathBuf,
+}
+
+/// Lee las filas de datos de un Excel generado por `guardar_excel`
+fn leer_datos_excel(ruta: &Path, columnas: &ColumnasConfig) -> Result<Vec<DatosPdf>> {
     let mut workbook: Xlsx<_> =
         open_workbook(ruta).with_context(|| format!("Error al abrir Excel: {:?}", ruta))?;
 
+    // Usar la hoja de datos si existe, si no la primera
+    let sheet_names = workbook.sheet_names();
+    let hoja = sheet_names
+        .iter()
+        .find(|n| n.as_str() == HOJA_DATOS)
+        .or_else(|| sheet_names.first())
+        .cloned()
+        .with_context(|| format!("El archivo {:?} no tiene hojas", ruta))?;
+
+    let range = workbook
+        .worksheet_range(&hoja)
+        .with_context(|| format!("No se pudo leer la hoja '{}' de {:?}", hoja, ruta))?;
+
+    let mut filas = range.rows();
+    let headers: Vec<String> = filas
+        .next()
+        .with_context(|| format!("El archivo {:?} está vacío", ruta))?
+        .iter()
+        .map(|cell| cell.to_string().trim().to_string())
+        .collect();
+
+    // Ubicar cada campo según el encabezado configurado
+    let columna = |campo: CampoDatos| {
+        columnas.posicion(campo).and_then(|i| {
+            headers
+                .iter()
+                .position(|h| *h == columnas.columnas[i].encabezado)
+        })
+    };
+    let idx_ccoo = columna(CampoDatos::Ccoo)
+        .with_context(|| format!("No se encontró la columna de CCOO en {:?}", ruta))?;
+    let idx_organismo = columna(CampoDatos::Organismo);
+    let idx_patrimonial = columna(CampoDatos::Patrimonial);
+    let idx_fecha = columna(CampoDatos::Fecha);
+    let idx_resultado = columna(CampoDatos::Resultado);
+    let idx_extra = columna(CampoDatos::OrganismosExtra);
+    let idx_carpeta = columna(CampoDatos::Carpeta);
+    let idx_archivo = columna(CampoDatos::Archivo);
+
+    let texto = |row: &[Data], idx: Option<usize>| {
+        idx.and_then(|i| row.get(i))
+            .map(|c| c.to_string().trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut datos = Vec::new();
+    for row in filas {
+        let ccoo = texto(row, Some(idx_ccoo));
+        if ccoo.is_empty() {
+            continue;
+        }
+
+        let fecha = idx_fecha
+            .and_then(|i| row.get(i))
+            .and_then(leer_fecha_celda);
+
+        let organismos_extra = texto(row, idx_extra)
+            .split(';')
+            .map(|o| o.trim().to_string())
+            .filter(|o| !o.is_empty())
+            .collect();
+
+        datos.push(DatosPdf {
+            ccoo,
+            organismo: texto(row, idx_organismo),
+            organismos_extra,
+            patrimonial: texto(row, idx_patrimonial),
+            fecha,
+            resultado: Resultado::desde_texto(&texto(row, idx_resultado)),
+            carpeta: texto(row, idx_carpeta),
+            archivo: texto(row, idx_archivo),
+            origen: None,
+        });
+    }
+
+    Ok(datos)
+}
+
+/// Consolida varios Excels generados por la aplicación en uno solo,
+/// deduplicando por CCOO. Los archivos se leen del más antiguo al más nuevo
+/// (por fecha de modificación), así que ante duplicados gana la corrida más reciente.
+/// Una columna adicional indica de qué archivo proviene cada fila.
+pub fn consolidar_excels(
+    rutas: &[PathBuf],
+    ruta_salida: &Path,
+    columnas: &ColumnasConfig,
+) -> Result<ResultadoConsolidacion> {
+    let mut ordenadas: Vec<&PathBuf> = rutas.iter().collect();
+    ordenadas.sort_by_key(|r| std::fs::metadata(r).and_then(|m| m.modified()).ok());
+
+    // Filas en orden de primera aparición, con el índice de cada CCOO
+    let mut filas: Vec<(DatosPdf, String)> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+
+    for ruta in &ordenadas {
+        let origen = ruta
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        for dato in leer_datos_excel(ruta, columnas)? {
+            match indices.get(&dato.ccoo) {
+                Some(&i) => filas[i] = (dato, origen.clone()),
+                None => {
+                    indices.insert(dato.ccoo.clone(), filas.len());
+                    filas.push((dato, origen.clone()));
+                }
+            }
+        }
+    }
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet.set_name(HOJA_DATOS)?;
+
+    let datos: Vec<DatosPdf> = filas.iter().map(|(d, _)| d.clone()).collect();
+    escribir_datos(worksheet, &datos, columnas)?;
+
+    let col_origen = columnas.columnas.len() as u16;
+    worksheet.write_string(0, col_origen, ENCABEZADO_ORIGEN)?;
+    for (row, (_, origen)) in filas.iter().enumerate() {
+        worksheet.write_string((row + 1) as u32, col_origen, origen)?;
+    }
+
+    let ruta_salida = guardar_libro(&mut workbook, ruta_salida, ModoGuardado::NuevoConTimestamp)?;
+
+    Ok(ResultadoConsolidacion {
+        filas: filas.len(),
+        archivos_leidos: ordenadas.len(),
+        ruta_salida,
+    })
+}
+
+/// Lee un archivo Excel existente (`.xlsx` o `.xls`, para referencia futura)
+pub fn leer_excel(ruta: &Path) -> Result<Vec<Vec<String>>> {
+    let mut workbook =
+        open_workbook_auto(ruta).with_context(|| format!("Error al abrir Excel: {:?}", ruta))?;
+
     let mut datos = Vec::new();
 
     if let Some(Ok(range)) = workbook.worksheet_range_at(0) {
@@ -110,3 +986,391 @@ pub fn leer_excel(ruta: &Path) -> Result<Vec<Vec<String>>> {
 
     Ok(datos)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_processor::CarpetaDePrueba;
+    use crate::pdf_extractor::dato;
+
+    #[test]
+    fn nombre_hoja_quita_caracteres_prohibidos_y_recorta() {
+        assert_eq!(nombre_hoja("DG/ABC: Compras?", &[]), "DG_ABC_ Compras_");
+        assert_eq!(nombre_hoja("'Organismo'", &[]), "Organismo");
+        assert_eq!(nombre_hoja("   ", &[]), SIN_ORGANISMO);
+        assert_eq!(
+            nombre_hoja(&"A".repeat(40), &[]).chars().count(),
+            LARGO_MAXIMO_HOJA
+        );
+    }
+
+    #[test]
+    fn leer_fecha_celda_es_inversa_de_fecha_a_excel_serial() {
+        use calamine::{ExcelDateTime, ExcelDateTimeType};
+
+        for (anio, mes, dia) in [(1900, 1, 1), (1900, 2, 28), (1900, 3, 1), (2024, 5, 17)] {
+            let fecha = NaiveDate::from_ymd_opt(anio, mes, dia).unwrap();
+            let serial = fecha_a_excel_serial(&fecha).unwrap();
+            assert_eq!(leer_fecha_celda(&Data::Float(serial)), Some(fecha));
+            assert_eq!(leer_fecha_celda(&Data::Int(serial as i64)), Some(fecha));
+            let celda = Data::DateTime(ExcelDateTime::new(
+                serial,
+                ExcelDateTimeType::DateTime,
+                false,
+            ));
+            assert_eq!(leer_fecha_celda(&celda), Some(fecha));
+        }
+    }
+
+    #[test]
+    fn fecha_a_excel_serial_en_los_limites_de_1900() {
+        let fecha = |anio, mes, dia| NaiveDate::from_ymd_opt(anio, mes, dia).unwrap();
+        assert!(fecha_a_excel_serial(&fecha(1899, 12, 31)).is_err());
+        assert_eq!(fecha_a_excel_serial(&fecha(1900, 1, 1)).unwrap(), 1.0);
+        assert_eq!(fecha_a_excel_serial(&fecha(1900, 2, 28)).unwrap(), 59.0);
+        assert_eq!(fecha_a_excel_serial(&fecha(1900, 3, 1)).unwrap(), 61.0);
+    }
+
+    #[test]
+    fn una_fecha_fuera_de_rango_no_impide_guardar_el_excel() {
+        let base = CarpetaDePrueba::nueva("fecha_rango");
+        let ruta = base.join("Listado.xlsx");
+        let mut antigua = dato("NO-1", "DGIN", Resultado::SinNovedad);
+        antigua.fecha = NaiveDate::from_ymd_opt(1899, 12, 31);
+        let mut valida = dato("NO-2", "DGIN", Resultado::SinNovedad);
+        valida.fecha = NaiveDate::from_ymd_opt(1900, 3, 1);
+
+        let columnas = ColumnasConfig::default();
+        let guardado = guardar_excel(
+            &[antigua, valida],
+            &ruta,
+            &columnas,
+            OrdenFilas::default(),
+            ModoGuardado::default(),
+        )
+        .unwrap();
+        let fechas: Vec<Option<NaiveDate>> = leer_datos_excel(&guardado, &columnas)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.fecha)
+            .collect();
+        assert_eq!(
+            fechas,
+            [
+                NaiveDate::from_ymd_opt(1899, 12, 31),
+                NaiveDate::from_ymd_opt(1900, 3, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn leer_fecha_celda_acepta_texto() {
+        let fecha = NaiveDate::from_ymd_opt(2024, 5, 7);
+        for texto in [
+            "07/05/2024",
+            "7/5/2024",
+            "07/05/24",
+            "07-05-24",
+            "07-05-2024",
+            "2024-05-07",
+            "07/05/2024 10:30",
+        ] {
+            assert_eq!(
+                leer_fecha_celda(&Data::String(texto.into())),
+                fecha,
+                "{}",
+                texto
+            );
+        }
+        assert_eq!(
+            leer_fecha_celda(&Data::DateTimeIso("2024-05-07T10:30:00".into())),
+            fecha
+        );
+        assert_eq!(
+            leer_fecha_celda(&Data::String("31/12/1899".into())),
+            NaiveDate::from_ymd_opt(1899, 12, 31)
+        );
+        assert_eq!(leer_fecha_celda(&Data::String("sin fecha".into())), None);
+        assert_eq!(leer_fecha_celda(&Data::Empty), None);
+    }
+
+    #[test]
+    fn columnas_desde_texto_valida_el_mapeo() {
+        let columnas = ColumnasConfig::desde_texto(" ccoo = CCOO ; Fecha=Día;").unwrap();
+        let mapeo: Vec<(CampoDatos, &str)> = columnas
+            .columnas
+            .iter()
+            .map(|c| (c.campo, c.encabezado.as_str()))
+            .collect();
+        assert_eq!(
+            mapeo,
+            [(CampoDatos::Ccoo, "CCOO"), (CampoDatos::Fecha, "Día")]
+        );
+
+        assert!(ColumnasConfig::desde_texto("ccoo=CCOO;expediente=Exp").is_err());
+        assert!(ColumnasConfig::desde_texto("ccoo=CCOO;CCOO=Otra").is_err());
+        assert!(ColumnasConfig::desde_texto("ccoo=CCOO;fecha= ").is_err());
+        assert!(ColumnasConfig::desde_texto("ccoo").is_err());
+        assert!(ColumnasConfig::desde_texto(" ; ").is_err());
+    }
+
+    #[test]
+    fn ordenar_datos_por_ccoo_en_orden_natural() {
+        let datos = vec![
+            dato("NO-2024-10-GCABA-A", "", Resultado::SinNovedad),
+            dato("NO-2024-9-GCABA-B", "", Resultado::ConNovedades),
+            dato("NO-2023-50-GCABA-C", "", Resultado::SinNovedad),
+        ];
+        let ccoo = |orden| -> Vec<String> {
+            ordenar_datos(&datos, orden)
+                .into_iter()
+                .map(|d| d.ccoo)
+                .collect()
+        };
+        assert_eq!(
+            ccoo(OrdenFilas {
+                por: OrdenarPor::Ccoo,
+                novedades_al_final: false
+            }),
+            [
+                "NO-2023-50-GCABA-C",
+                "NO-2024-9-GCABA-B",
+                "NO-2024-10-GCABA-A"
+            ]
+        );
+        assert_eq!(
+            ccoo(OrdenFilas {
+                por: OrdenarPor::Ccoo,
+                novedades_al_final: true
+            }),
+            [
+                "NO-2023-50-GCABA-C",
+                "NO-2024-10-GCABA-A",
+                "NO-2024-9-GCABA-B"
+            ]
+        );
+        assert_eq!(
+            ccoo(OrdenFilas::default()),
+            [
+                "NO-2024-10-GCABA-A",
+                "NO-2024-9-GCABA-B",
+                "NO-2023-50-GCABA-C"
+            ]
+        );
+    }
+
+    #[test]
+    fn ordenar_datos_deja_vacios_al_final_y_es_estable() {
+        let mut con_fecha = dato("NO-3", "dgin", Resultado::SinNovedad);
+        con_fecha.fecha = NaiveDate::from_ymd_opt(2024, 1, 2);
+        let datos = vec![
+            dato("NO-1", "", Resultado::SinNovedad),
+            dato("NO-2", "DGCG", Resultado::SinNovedad),
+            con_fecha,
+            dato("NO-4", "DGCG", Resultado::SinNovedad),
+        ];
+        let ccoo = |por| -> Vec<String> {
+            let orden = OrdenFilas {
+                por,
+                novedades_al_final: false,
+            };
+            ordenar_datos(&datos, orden)
+                .into_iter()
+                .map(|d| d.ccoo)
+                .collect()
+        };
+        assert_eq!(
+            ccoo(OrdenarPor::Organismo),
+            ["NO-2", "NO-4", "NO-3", "NO-1"]
+        );
+        assert_eq!(ccoo(OrdenarPor::Fecha), ["NO-3", "NO-1", "NO-2", "NO-4"]);
+    }
+
+    #[test]
+    fn nombre_hoja_evita_repetidos() {
+        let usados = vec!["DGIN".to_string(), "DGIN (2)".to_string()];
+        assert_eq!(nombre_hoja("dgin", &usados), "dgin (3)");
+        let usados = vec!["DIRECCIÓN".to_string()];
+        assert_eq!(nombre_hoja("Dirección", &usados), "Dirección (2)");
+
+        let largo = "B".repeat(40);
+        let usados = vec!["B".repeat(LARGO_MAXIMO_HOJA)];
+        let nombre = nombre_hoja(&largo, &usados);
+        assert_eq!(nombre, format!("{} (2)", "B".repeat(LARGO_MAXIMO_HOJA - 4)));
+    }
+
+    #[test]
+    fn verificar_excel_cerrado_ignora_el_archivo_de_bloqueo_huerfano() {
+        let base = CarpetaDePrueba::nueva("excel");
+        let ruta = base.join("Listado.xlsx");
+        std::fs::write(&ruta, b"").unwrap();
+        assert!(verificar_excel_cerrado(&ruta).is_ok());
+
+        // Excel deja `~$<nombre>` si se cierra de golpe: no debe bloquear el guardado
+        std::fs::write(base.join("~$Listado.xlsx"), b"").unwrap();
+        assert!(verificar_excel_cerrado(&ruta).is_ok());
+    }
+
+    #[test]
+    fn el_nombre_del_archivo_se_guarda_y_se_vuelve_a_leer() {
+        let base = CarpetaDePrueba::nueva("archivo");
+        let ruta = base.join("Listado.xlsx");
+        let mut con_mayusculas = dato("NO-2", "DGIN", Resultado::ConNovedades);
+        con_mayusculas.archivo = "NO-2.PDF".to_string();
+        let datos = [dato("NO-1", "DGIN", Resultado::SinNovedad), con_mayusculas];
+
+        let columnas = ColumnasConfig::default();
+        let guardado = guardar_excel(
+            &datos,
+            &ruta,
+            &columnas,
+            OrdenFilas::default(),
+            ModoGuardado::default(),
+        )
+        .unwrap();
+        let leidos = leer_datos_excel(&guardado, &columnas).unwrap();
+        let archivos: Vec<&str> = leidos.iter().map(|d| d.archivo.as_str()).collect();
+        assert_eq!(archivos, ["NO-1.pdf", "NO-2.PDF"]);
+    }
+
+    #[test]
+    fn guardar_excel_existente_segun_el_modo() {
+        let base = CarpetaDePrueba::nueva("modo_guardado");
+        let ruta = base.join("Listado.xlsx");
+        let columnas = ColumnasConfig::default();
+        let guardar = |datos: &[DatosPdf], modo| {
+            guardar_excel(datos, &ruta, &columnas, OrdenFilas::default(), modo).unwrap()
+        };
+        let ccoos = |ruta: &Path| -> Vec<String> {
+            leer_datos_excel(ruta, &columnas)
+                .unwrap()
+                .into_iter()
+                .map(|d| format!("{}:{}", d.ccoo, d.resultado))
+                .collect()
+        };
+
+        guardar(
+            &[
+                dato("NO-1", "DGIN", Resultado::SinNovedad),
+                dato("NO-2", "DGIN", Resultado::ConNovedades),
+            ],
+            ModoGuardado::default(),
+        );
+
+        // Anexar reemplaza el CCOO repetido y suma el nuevo
+        let anexado = guardar(
+            &[
+                dato("NO-2", "DGIN", Resultado::SinNovedad),
+                dato("NO-3", "DGIN", Resultado::ConNovedades),
+            ],
+            ModoGuardado::Anexar,
+        );
+        assert_eq!(anexado, ruta);
+        assert_eq!(
+            ccoos(&ruta),
+            [
+                "NO-1:Sin novedad",
+                "NO-2:Sin novedad",
+                "NO-3:Con novedades (ver)"
+            ]
+        );
+
+        // Sobrescribir deja el anterior como respaldo
+        let sobrescrito = guardar(
+            &[dato("NO-9", "DGIN", Resultado::SinNovedad)],
+            ModoGuardado::Sobrescribir { respaldo: true },
+        );
+        assert_eq!(sobrescrito, ruta);
+        assert_eq!(ccoos(&ruta), ["NO-9:Sin novedad"]);
+        assert_eq!(ccoos(&ruta_con_sufijo(&ruta, SUFIJO_RESPALDO)).len(), 3);
+
+        // Un segundo sobrescrito no pisa el respaldo anterior
+        guardar(&[], ModoGuardado::Sobrescribir { respaldo: true });
+        assert_eq!(ccoos(&ruta_con_sufijo(&ruta, SUFIJO_RESPALDO)).len(), 3);
+        assert_eq!(
+            ccoos(&ruta_con_sufijo(&ruta, "respaldo (1)")),
+            ["NO-9:Sin novedad"]
+        );
+
+        // El modo histórico crea otro archivo
+        let nuevo = guardar(&[], ModoGuardado::NuevoConTimestamp);
+        assert_ne!(nuevo, ruta);
+    }
+
+    #[test]
+    fn consolidar_excels_gana_la_corrida_mas_reciente() {
+        let base = CarpetaDePrueba::nueva("consolidar");
+        let columnas = ColumnasConfig::default();
+        let guardar = |nombre: &str, datos: &[DatosPdf]| {
+            guardar_excel(
+                datos,
+                &base.join(nombre),
+                &columnas,
+                OrdenFilas::default(),
+                ModoGuardado::default(),
+            )
+            .unwrap()
+        };
+        let viejo = guardar(
+            "Viejo.xlsx",
+            &[
+                dato("NO-1", "DGIN", Resultado::SinNovedad),
+                dato("NO-2", "DGIN", Resultado::SinNovedad),
+            ],
+        );
+        let nuevo = guardar(
+            "Nuevo.xlsx",
+            &[
+                dato("NO-2", "DGIN", Resultado::ConNovedades),
+                dato("NO-3", "DGIN", Resultado::SinNovedad),
+            ],
+        );
+        // El orden lo da la fecha de modificación, no el de la lista
+        let hace_un_rato = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        std::fs::File::options()
+            .write(true)
+            .open(&viejo)
+            .unwrap()
+            .set_modified(hace_un_rato)
+            .unwrap();
+
+        let resultado =
+            consolidar_excels(&[nuevo, viejo], &base.join("Consolidado.xlsx"), &columnas).unwrap();
+        assert_eq!(resultado.filas, 3);
+        assert_eq!(resultado.archivos_leidos, 2);
+
+        let filas: Vec<String> = leer_excel(&resultado.ruta_salida)
+            .unwrap()
+            .into_iter()
+            .skip(1)
+            .map(|fila| {
+                let resultado = &fila[columnas.posicion(CampoDatos::Resultado).unwrap()];
+                format!("{}:{}:{}", fila[0], resultado, fila[fila.len() - 1])
+            })
+            .collect();
+        assert_eq!(
+            filas,
+            [
+                "NO-1:Sin novedad:Viejo.xlsx",
+                "NO-2:Con novedades (ver):Nuevo.xlsx",
+                "NO-3:Sin novedad:Nuevo.xlsx"
+            ]
+        );
+    }
+
+    #[test]
+    fn json_con_fecha_iso_o_null() {
+        let mut con_fecha = dato("NO-1", "DGIN", Resultado::SinNovedad);
+        con_fecha.fecha = NaiveDate::from_ymd_opt(2024, 3, 5);
+        let sin_fecha = dato("NO-2", "DGIN", Resultado::ConNovedades);
+
+        let json = serde_json::to_value([con_fecha, sin_fecha]).unwrap();
+        assert_eq!(json[0]["fecha"], "2024-03-05");
+        assert_eq!(json[0]["resultado"], "SinNovedad");
+        assert!(json[1]["fecha"].is_null());
+        assert_eq!(
+            ruta_json(Path::new("dir/Listado.xlsx")),
+            Path::new("dir/Listado datos.json")
+        );
+    }
+}
@@ -1,19 +1,254 @@
+use crate::registro::registrar;
 use anyhow::{Context, Result};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Subcarpeta del destino donde se guardan los archivos descartados
+pub const CARPETA_DESCARTADOS: &str = "Descartados";
+
+/// Qué hacer cuando el destino ya tiene un archivo con el mismo nombre
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModoColision {
+    /// Reemplazar el archivo existente
+    Sobrescribir,
+    /// Agregar un sufijo incremental, salvo que ambos archivos sean idénticos
+    #[default]
+    Renombrar,
+    /// Dejar el archivo en la carpeta de origen
+    Saltar,
+}
+
+impl ModoColision {
+    pub const TODOS: [ModoColision; 3] = [
+        ModoColision::Sobrescribir,
+        ModoColision::Renombrar,
+        ModoColision::Saltar,
+    ];
+
+    pub fn descripcion(&self) -> &'static str {
+        match self {
+            ModoColision::Sobrescribir => "Sobrescribir",
+            ModoColision::Renombrar => "Renombrar",
+            ModoColision::Saltar => "Saltar",
+        }
+    }
+}
+
+/// Organismos cuyas comunicaciones se eliminan (o se descartan) al mover
+pub const ORGANISMOS_ELIMINAR: &[&str] = &[
+    "DGSOCAI", "DGCG", "MGEYA", "UAIMHF", "DGADCYP", "EAIT", "DGTES", "OGEPU", "PG", "DGAIGA",
+];
+
+/// Opciones de la operación de mover archivos
+#[derive(Debug, Clone, Default)]
+pub struct OpcionesMover {
+    /// Mover a `Descartados` los archivos a eliminar en vez de borrarlos
+    pub descartar: bool,
+    /// Comportamiento ante nombres repetidos en el destino
+    pub colision: ModoColision,
+}
 
 /// Resultado de la operación de mover archivos
 #[derive(Debug, Default)]
 pub struct ResultadoMover {
     pub archivos_movidos: usize,
     pub archivos_eliminados: usize,
+    pub archivos_movidos_nombres: Vec<String>,
+    pub archivos_eliminados_nombres: Vec<String>,
+    pub colisiones: usize,
+    /// Archivos descartados por tener el mismo contenido que uno ya presente en el
+    /// destino, aunque el nombre sea distinto (p. ej. `... (1).pdf` de Chrome)
+    pub duplicados: usize,
+}
+
+/// Destino de un archivo luego de resolver una posible colisión
+enum DestinoArchivo {
+    Mover(PathBuf),
+    Duplicado,
+    Saltar,
+}
+
+/// Indica si dos archivos tienen exactamente el mismo contenido
+fn archivos_identicos(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) if ma.len() == mb.len() => {
+            matches!((fs::read(a), fs::read(b)), (Ok(ca), Ok(cb)) if ca == cb)
+        }
+        _ => false,
+    }
+}
+
+/// Hash SHA-256 del contenido de un archivo
+fn hash_archivo(ruta: &Path) -> Result<[u8; 32]> {
+    let contenido = fs::read(ruta).with_context(|| format!("No se pudo leer {:?}", ruta))?;
+    Ok(Sha256::digest(&contenido).into())
+}
+
+/// PDFs que ya están en una carpeta (sin subcarpetas), agrupados por tamaño. El
+/// hash solo se calcula cuando un archivo a mover tiene el mismo tamaño que alguno
+/// y se guarda para no volver a leerlo: en carpetas de red leer todo es muy lento.
+#[derive(Debug, Default)]
+struct ContenidoDestino {
+    por_tamanio: HashMap<u64, Vec<PathBuf>>,
+    hashes: HashMap<PathBuf, [u8; 32]>,
+}
+
+impl ContenidoDestino {
+    fn leer(carpeta: &Path) -> Result<Self> {
+        let mut contenido = ContenidoDestino::default();
+        for entry in fs::read_dir(carpeta)?.flatten() {
+            let path = entry.path();
+            let es_pdf = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+            if !es_pdf {
+                continue;
+            }
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    contenido.agregar(path, metadata.len());
+                }
+            }
+        }
+        Ok(contenido)
+    }
+
+    /// Registra `ruta`, reemplazando lo que se sabía de ella si se sobrescribió
+    fn agregar(&mut self, ruta: PathBuf, tamanio: u64) {
+        for rutas in self.por_tamanio.values_mut() {
+            rutas.retain(|r| *r != ruta);
+        }
+        self.hashes.remove(&ruta);
+        self.por_tamanio.entry(tamanio).or_default().push(ruta);
+    }
+
+    /// Indica si algún PDF de la carpeta tiene el mismo contenido que `origen`
+    fn contiene(&mut self, origen: &Path, tamanio: u64) -> bool {
+        let Some(candidatos) = self.por_tamanio.get(&tamanio) else {
+            return false;
+        };
+        let hash_origen = match hash_archivo(origen) {
+            Ok(hash) => hash,
+            Err(e) => {
+                registrar!("{:#}", e);
+                return false;
+            }
+        };
+        for candidato in candidatos {
+            let hash = match self.hashes.get(candidato) {
+                Some(hash) => *hash,
+                None => match hash_archivo(candidato) {
+                    Ok(hash) => {
+                        self.hashes.insert(candidato.clone(), hash);
+                        hash
+                    }
+                    Err(e) => {
+                        registrar!("{:#}", e);
+                        continue;
+                    }
+                },
+            };
+            if hash == hash_origen {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Devuelve `ruta` si está libre o busca un nombre libre agregando
+/// ` (1)`, ` (2)`, ... antes de la extensión
+pub(crate) fn ruta_libre(ruta: &Path) -> PathBuf {
+    if !ruta.exists() {
+        return ruta.to_path_buf();
+    }
+    let base = ruta
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let extension = ruta.extension().and_then(|s| s.to_str());
+    (1..)
+        .map(|n| {
+            let nombre = match extension {
+                Some(ext) => format!("{} ({}).{}", base, n, ext),
+                None => format!("{} ({})", base, n),
+            };
+            ruta.with_file_name(nombre)
+        })
+        .find(|r| !r.exists())
+        .unwrap_or_else(|| ruta.to_path_buf())
+}
+
+/// Quita el sufijo ` (n)` que Chrome agrega antes de la extensión cuando el
+/// archivo ya existe en Descargas. Devuelve `None` si el nombre no lo tiene.
+fn quitar_sufijo_copia(nombre: &str) -> Option<String> {
+    let (base, extension) = nombre.rsplit_once('.')?;
+    let (base, sufijo) = base.rsplit_once(" (")?;
+    let numero = sufijo.strip_suffix(')')?;
+    if numero.is_empty() || !numero.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}.{}", base, extension))
+}
+
+/// Convierte un texto en un nombre de carpeta válido en Windows: reemplaza
+/// `< > : " / \ | ? *` y los caracteres de control, quita puntos y espacios finales
+/// y evita los nombres reservados (`CON`, `NUL`, `COM1`...). Devuelve `None` si
+/// no queda nada.
+pub fn nombre_carpeta_valido(texto: &str) -> Option<String> {
+    let limpio: String = texto
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let limpio = limpio.trim().trim_end_matches('.').trim_end();
+    if limpio.is_empty() {
+        return None;
+    }
+
+    let base = limpio.split('.').next().unwrap_or("").to_ascii_uppercase();
+    let reservado = matches!(base.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((base.starts_with("COM") || base.starts_with("LPT"))
+            && base.len() == 4
+            && base[3..].chars().all(|c| c.is_ascii_digit()));
+    if reservado {
+        Some(format!("_{}", limpio))
+    } else {
+        Some(limpio.to_string())
+    }
+}
+
+/// Decide adónde mover `origen` según el modo de colisión
+fn resolver_destino(origen: &Path, destino: PathBuf, modo: ModoColision) -> DestinoArchivo {
+    if !destino.exists() {
+        return DestinoArchivo::Mover(destino);
+    }
+    match modo {
+        ModoColision::Sobrescribir => DestinoArchivo::Mover(destino),
+        ModoColision::Saltar => DestinoArchivo::Saltar,
+        ModoColision::Renombrar if archivos_identicos(origen, &destino) => {
+            DestinoArchivo::Duplicado
+        }
+        ModoColision::Renombrar => DestinoArchivo::Mover(ruta_libre(&destino)),
+    }
 }
 
 /// Mueve archivos PDF desde la carpeta de descargas al destino
 /// y elimina archivos de organismos específicos
 /// Equivalente a `mover_archivos` en Python
-pub fn mover_archivos(ruta_descarga: &Path, ruta_destino: &Path) -> Result<ResultadoMover> {
+pub fn mover_archivos(
+    ruta_descarga: &Path,
+    ruta_destino: &Path,
+    opciones: &OpcionesMover,
+) -> Result<ResultadoMover> {
     let mut resultado = ResultadoMover::default();
     
     // Patrón para archivos a mover: NO-YYYY-NNNN-GCABA-XXX.pdf
@@ -21,12 +256,16 @@ pub fn mover_archivos(ruta_descarga: &Path, ruta_destino: &Path) -> Result<Resul
         .context("Error al compilar regex de mover")?;
     
     // Patrón para archivos a eliminar (organismos específicos)
-    let patron_eliminar = Regex::new(
-        r"^NO-\d{4}-\d+-GCABA-(DGSOCAI|DGCG|MGEYA|UAIMHF|DGADCYP|EAIT|DGTES|OGEPU|PG|DGAIGA)\.pdf$"
-    ).context("Error al compilar regex de eliminar")?;
+    let patron_eliminar = Regex::new(&format!(
+        r"^NO-\d{{4}}-\d+-GCABA-({})\.pdf$",
+        ORGANISMOS_ELIMINAR.join("|")
+    )).context("Error al compilar regex de eliminar")?;
     
     // Asegurar que el directorio destino existe
     fs::create_dir_all(ruta_destino)?;
+
+    // Contenido ya presente en el destino, para no mover copias con otro nombre
+    let mut contenido_destino = ContenidoDestino::leer(ruta_destino)?;
     
     // Mover archivos que coinciden con el patrón
     let entries = fs::read_dir(ruta_descarga)
@@ -43,16 +282,73 @@ pub fn mover_archivos(ruta_descarga: &Path, ruta_destino: &Path) -> Result<Resul
             None => continue,
         };
         
-        if patron_mover.is_match(nombre) {
-            let ruta_final = ruta_destino.join(nombre);
+        // Rescatar las copias que Chrome renombró como `... (1).pdf`
+        let nombre_limpio = if patron_mover.is_match(nombre) {
+            Some(nombre.to_string())
+        } else {
+            quitar_sufijo_copia(nombre).filter(|n| patron_mover.is_match(n))
+        };
+
+        if let Some(nombre_limpio) = nombre_limpio {
+            let tamanio = fs::metadata(&path).map(|m| m.len()).unwrap_or_default();
+            if contenido_destino.contiene(&path, tamanio) {
+                if opciones.colision == ModoColision::Saltar {
+                    // Saltar promete dejar el archivo de origen donde está
+                    registrar!("{} ya está en el destino con otro nombre, se omite", nombre);
+                    continue;
+                }
+                registrar!(
+                    "{} ya está en el destino con otro nombre, se descarta",
+                    nombre
+                );
+                if let Err(e) = fs::remove_file(&path) {
+                    registrar!("Error eliminando duplicado {}: {}", nombre, e);
+                }
+                resultado.duplicados += 1;
+                continue;
+            }
+
+            let ruta_final = ruta_destino.join(&nombre_limpio);
+            if ruta_final.exists() {
+                resultado.colisiones += 1;
+            }
+            let ruta_final = match resolver_destino(&path, ruta_final, opciones.colision) {
+                DestinoArchivo::Mover(r) => r,
+                DestinoArchivo::Saltar => {
+                    registrar!("{} ya existe en el destino, se omite", nombre);
+                    continue;
+                }
+                DestinoArchivo::Duplicado => {
+                    // El destino ya tiene el mismo archivo: se descarta la copia
+                    if let Err(e) = fs::remove_file(&path) {
+                        registrar!("Error eliminando duplicado {}: {}", nombre, e);
+                    }
+                    resultado.duplicados += 1;
+                    continue;
+                }
+            };
             match fs::rename(&path, &ruta_final) {
-                Ok(_) => resultado.archivos_movidos += 1,
-                Err(e) => eprintln!("Error moviendo {}: {}", nombre, e),
+                Ok(_) => {
+                    contenido_destino.agregar(ruta_final.clone(), tamanio);
+                    resultado.archivos_movidos += 1;
+                    let nombre_final = ruta_final
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .map(str::to_string)
+                        .unwrap_or(nombre_limpio);
+                    resultado.archivos_movidos_nombres.push(nombre_final);
+                }
+                Err(e) => registrar!("Error moviendo {}: {}", nombre, e),
             }
         }
     }
     
-    // Eliminar archivos específicos del destino
+    // Eliminar (o descartar) archivos específicos del destino
+    let ruta_descartados = ruta_destino.join(CARPETA_DESCARTADOS);
+    if opciones.descartar {
+        fs::create_dir_all(&ruta_descartados)?;
+    }
+    
     let entries = fs::read_dir(ruta_destino)
         .with_context(|| format!("Error al leer directorio destino: {:?}", ruta_destino))?;
     
@@ -67,10 +363,23 @@ pub fn mover_archivos(ruta_descarga: &Path, ruta_destino: &Path) -> Result<Resul
             None => continue,
         };
         
-        if patron_eliminar.is_match(nombre) {
-            match fs::remove_file(&path) {
-                Ok(_) => resultado.archivos_eliminados += 1,
-                Err(e) => eprintln!("Error eliminando {}: {}", nombre, e),
+        // El organismo se toma del nombre sin el sufijo ` (n)` que agrega
+        // `ModoColision::Renombrar`
+        let nombre_base = quitar_sufijo_copia(nombre).unwrap_or_else(|| nombre.to_string());
+        if patron_eliminar.is_match(&nombre_base) {
+            let eliminado = if opciones.descartar {
+                fs::rename(&path, ruta_libre(&ruta_descartados.join(nombre)))
+            } else {
+                fs::remove_file(&path)
+            };
+            match eliminado {
+                Ok(_) => {
+                    resultado.archivos_eliminados += 1;
+                    resultado
+                        .archivos_eliminados_nombres
+                        .push(nombre.to_string());
+                }
+                Err(e) => registrar!("Error eliminando {}: {}", nombre, e),
             }
         }
     }
@@ -82,3 +391,189 @@ pub fn mover_archivos(ruta_descarga: &Path, ruta_destino: &Path) -> Result<Resul
 pub fn obtener_ruta_descargas() -> Option<std::path::PathBuf> {
     dirs::download_dir()
 }
+
+/// Carpeta temporal para tests; se borra al salir de alcance aunque falle un assert
+#[cfg(test)]
+pub(crate) struct CarpetaDePrueba(PathBuf);
+
+#[cfg(test)]
+impl CarpetaDePrueba {
+    pub(crate) fn nueva(nombre: &str) -> Self {
+        let ruta =
+            std::env::temp_dir().join(format!("auto_sade_{}_{}", nombre, std::process::id()));
+        let _ = fs::remove_dir_all(&ruta);
+        fs::create_dir_all(&ruta).unwrap();
+        Self(ruta)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for CarpetaDePrueba {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for CarpetaDePrueba {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quitar_sufijo_copia_de_chrome() {
+        assert_eq!(
+            quitar_sufijo_copia("NO-2024-123-GCABA-DGIN (1).pdf").as_deref(),
+            Some("NO-2024-123-GCABA-DGIN.pdf")
+        );
+        assert_eq!(
+            quitar_sufijo_copia("NO-2024-123-GCABA-DGIN (12).pdf").as_deref(),
+            Some("NO-2024-123-GCABA-DGIN.pdf")
+        );
+        assert_eq!(quitar_sufijo_copia("NO-2024-123-GCABA-DGIN.pdf"), None);
+        assert_eq!(quitar_sufijo_copia("informe (final).pdf"), None);
+        assert_eq!(quitar_sufijo_copia("informe ().pdf"), None);
+    }
+
+    #[test]
+    fn ruta_libre_agrega_sufijo_incremental() {
+        let base = CarpetaDePrueba::nueva("mover_ruta_libre");
+        let ruta = base.join("NO-2024-1-GCABA-DGIN.pdf");
+        assert_eq!(ruta_libre(&ruta), ruta);
+
+        fs::write(&ruta, b"a").unwrap();
+        assert_eq!(ruta_libre(&ruta), base.join("NO-2024-1-GCABA-DGIN (1).pdf"));
+
+        fs::write(base.join("NO-2024-1-GCABA-DGIN (1).pdf"), b"b").unwrap();
+        assert_eq!(ruta_libre(&ruta), base.join("NO-2024-1-GCABA-DGIN (2).pdf"));
+
+        let sin_extension = base.join("LEEME");
+        fs::write(&sin_extension, b"c").unwrap();
+        assert_eq!(ruta_libre(&sin_extension), base.join("LEEME (1)"));
+    }
+
+    #[test]
+    fn resolver_destino_segun_el_modo() {
+        let base = CarpetaDePrueba::nueva("mover_resolver_destino");
+        let origen = base.join("origen.pdf");
+        let igual = base.join("igual.pdf");
+        let distinto = base.join("distinto.pdf");
+        let libre = base.join("libre.pdf");
+        fs::write(&origen, b"contenido").unwrap();
+        fs::write(&igual, b"contenido").unwrap();
+        fs::write(&distinto, b"otro contenido").unwrap();
+
+        let destino = |ruta: &Path, modo| match resolver_destino(&origen, ruta.to_path_buf(), modo)
+        {
+            DestinoArchivo::Mover(r) => Some(r),
+            DestinoArchivo::Duplicado => Some(PathBuf::from("duplicado")),
+            DestinoArchivo::Saltar => None,
+        };
+
+        for modo in ModoColision::TODOS {
+            assert_eq!(destino(&libre, modo), Some(libre.clone()));
+        }
+        assert_eq!(
+            destino(&distinto, ModoColision::Sobrescribir),
+            Some(distinto.clone())
+        );
+        assert_eq!(destino(&distinto, ModoColision::Saltar), None);
+        assert_eq!(
+            destino(&distinto, ModoColision::Renombrar),
+            Some(base.join("distinto (1).pdf"))
+        );
+        assert_eq!(
+            destino(&igual, ModoColision::Renombrar),
+            Some(PathBuf::from("duplicado"))
+        );
+    }
+
+    #[test]
+    fn la_copia_renombrada_de_un_organismo_a_eliminar_se_elimina() {
+        let base = CarpetaDePrueba::nueva("mover_renombrada");
+        let descargas = base.join("descargas");
+        let destino = base.join("destino");
+        fs::create_dir_all(&descargas).unwrap();
+        fs::create_dir_all(&destino).unwrap();
+        fs::write(destino.join("NO-2024-1-GCABA-DGCG.pdf"), b"anterior").unwrap();
+        fs::write(destino.join("NO-2024-2-GCABA-DGIN.pdf"), b"anterior").unwrap();
+        fs::write(descargas.join("NO-2024-1-GCABA-DGCG.pdf"), b"nuevo 1").unwrap();
+        fs::write(descargas.join("NO-2024-2-GCABA-DGIN.pdf"), b"nuevo 2").unwrap();
+
+        let opciones = OpcionesMover {
+            descartar: false,
+            colision: ModoColision::Renombrar,
+        };
+        let resultado = mover_archivos(&descargas, &destino, &opciones).unwrap();
+
+        let mut movidos = resultado.archivos_movidos_nombres.clone();
+        movidos.sort();
+        assert_eq!(
+            movidos,
+            [
+                "NO-2024-1-GCABA-DGCG (1).pdf",
+                "NO-2024-2-GCABA-DGIN (1).pdf"
+            ]
+        );
+        assert_eq!(resultado.archivos_eliminados, 2);
+        assert!(!destino.join("NO-2024-1-GCABA-DGCG.pdf").exists());
+        assert!(!destino.join("NO-2024-1-GCABA-DGCG (1).pdf").exists());
+        assert!(destino.join("NO-2024-2-GCABA-DGIN (1).pdf").exists());
+    }
+
+    #[test]
+    fn duplicado_por_contenido_segun_el_modo() {
+        let base = CarpetaDePrueba::nueva("mover_duplicado");
+        let descargas = base.join("descargas");
+        let destino = base.join("destino");
+        fs::create_dir_all(&descargas).unwrap();
+        fs::create_dir_all(&destino).unwrap();
+        // El destino tiene el mismo contenido con otro nombre y extensión en mayúsculas
+        fs::write(destino.join("NO-2024-7-GCABA-DGIN.PDF"), b"igual").unwrap();
+        let copia = descargas.join("NO-2024-7-GCABA-DGIN (1).pdf");
+
+        let mover = |colision| {
+            fs::write(&copia, b"igual").unwrap();
+            let opciones = OpcionesMover {
+                descartar: false,
+                colision,
+            };
+            mover_archivos(&descargas, &destino, &opciones).unwrap()
+        };
+
+        let saltado = mover(ModoColision::Saltar);
+        assert_eq!(saltado.duplicados, 0);
+        assert!(copia.exists());
+
+        let renombrado = mover(ModoColision::Renombrar);
+        assert_eq!(renombrado.duplicados, 1);
+        assert_eq!(renombrado.archivos_movidos, 0);
+        assert!(!copia.exists());
+    }
+
+    #[test]
+    fn nombre_carpeta_valido_sanea_caracteres_y_reservados() {
+        assert_eq!(
+            nombre_carpeta_valido("DG/Compras: \"Sede\"?").as_deref(),
+            Some("DG_Compras_ _Sede__")
+        );
+        assert_eq!(
+            nombre_carpeta_valido("Organismo. ").as_deref(),
+            Some("Organismo")
+        );
+        assert_eq!(nombre_carpeta_valido("con").as_deref(), Some("_con"));
+        assert_eq!(
+            nombre_carpeta_valido("COM1.txt").as_deref(),
+            Some("_COM1.txt")
+        );
+        assert_eq!(nombre_carpeta_valido("COMPRAS").as_deref(), Some("COMPRAS"));
+        assert_eq!(nombre_carpeta_valido(" . "), None);
+    }
+}
@@ -1,11 +1,78 @@
-use crate::busqueda_comunicaciones;
-use crate::config::Config;
-use crate::excel_handler;
-use crate::file_processor;
-use crate::pdf_extractor;
-use crate::web_automation;
+use crate::i18n::{self, t, Idioma};
+use anyhow::Context;
+use auto_sade::busqueda_comunicaciones::{self, Pendientes};
+use auto_sade::config::{self, obtener_directorio_exe, Config, Operador, NOMBRE_EXCEL, OPERADORES};
+use auto_sade::excel_handler::{self, ModoGuardado, OrdenFilas, OrdenarPor};
+use auto_sade::file_processor::{self, ModoColision};
+use auto_sade::pdf_extractor;
+use auto_sade::perfil_temporal;
+use auto_sade::registro;
+use auto_sade::web_automation;
 use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Clave de almacenamiento del directorio de trabajo elegido por el usuario
+const CLAVE_DIRECTORIO_TRABAJO: &str = "directorio_trabajo";
+
+/// Claves de almacenamiento de la carpeta de PDFs y del Excel elegidos en la GUI
+const CLAVE_RUTA_ARCHIVOS: &str = "ruta_archivos";
+const CLAVE_RUTA_EXCEL: &str = "ruta_excel";
+
+/// Clave de almacenamiento de las últimas entradas del formulario
+const CLAVE_FORMULARIO: &str = "formulario";
+
+/// Clave de almacenamiento de los perfiles de trabajo guardados
+const CLAVE_PERFILES: &str = "perfiles_trabajo";
+
+/// Clave de almacenamiento del nombre del perfil activo
+const CLAVE_PERFIL_ACTIVO: &str = "perfil_activo";
+
+/// Clave de almacenamiento del idioma de la interfaz
+const CLAVE_IDIOMA: &str = "idioma";
+
+/// Clave de almacenamiento del tema (oscuro o claro)
+const CLAVE_TEMA_OSCURO: &str = "tema_oscuro";
+
+/// Últimas entradas del formulario, restauradas al abrir la aplicación
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct EntradasFormulario {
+    usuario: String,
+    comunicacion_inicio: String,
+    comunicacion_final: String,
+    usar_ocr: bool,
+    #[serde(default)]
+    descartar_eliminados: bool,
+    #[serde(default)]
+    modo_colision: ModoColision,
+    #[serde(default)]
+    incluir_subcarpetas: bool,
+    #[serde(default)]
+    agrupar_por_organismo: bool,
+    #[serde(default)]
+    ordenar_por: OrdenarPor,
+    #[serde(default)]
+    novedades_al_final: bool,
+    #[serde(default)]
+    modo_guardado: ModoGuardado,
+    #[serde(default)]
+    tiempo_maximo: String,
+}
+
+/// Perfil de trabajo con nombre: agrupa usuario, rutas y opciones habituales
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PerfilTrabajo {
+    nombre: String,
+    usuario: String,
+    directorio_trabajo: Option<PathBuf>,
+    comunicacion_inicio: String,
+    comunicacion_final: String,
+    usar_ocr: bool,
+}
 
 /// Estado de la aplicación
 #[derive(Debug, Clone, PartialEq)]
@@ -16,59 +83,1695 @@ pub enum EstadoApp {
     Error(String),
 }
 
+/// Avance numérico de la operación en curso, para estimar el tiempo restante
+#[derive(Debug, Clone, Copy)]
+struct Progreso {
+    actual: u32,
+    total: u32,
+    inicio: Instant,
+    /// La operación atiende el pedido de cancelación
+    cancelable: bool,
+}
+
+impl Progreso {
+    /// Estima el tiempo restante según el ritmo promedio hasta el momento
+    fn tiempo_restante(&self) -> Option<Duration> {
+        if self.actual == 0 || self.actual > self.total {
+            return None;
+        }
+        let por_unidad = self.inicio.elapsed() / self.actual;
+        Some(por_unidad * (self.total - self.actual))
+    }
+}
+
+/// Formatea una duración estimada para mostrar al usuario
+fn formatear_eta(restante: Duration) -> String {
+    let segundos = restante.as_secs();
+    if segundos < 60 {
+        format!("~{} s restantes", segundos)
+    } else {
+        format!("~{} min restantes", segundos.div_ceil(60))
+    }
+}
+
+/// Muestra el mensaje del estado con el color que le corresponde
+fn mostrar_estado(ui: &mut egui::Ui, estado: &EstadoApp) {
+    let (texto, color) = match estado {
+        EstadoApp::Listo => (t("listo"), egui::Color32::GRAY),
+        EstadoApp::Procesando(msg) => (msg.as_str(), ui.visuals().warn_fg_color),
+        EstadoApp::Finalizado(msg) => (msg.as_str(), egui::Color32::GREEN),
+        EstadoApp::Error(msg) => (msg.as_str(), ui.visuals().error_fg_color),
+    };
+    ui.label(egui::RichText::new(texto).color(color));
+}
+
+/// Barra de avance con la cantidad procesada y el tiempo restante estimado
+fn mostrar_progreso(ui: &mut egui::Ui, p: &Progreso) {
+    let eta = p
+        .tiempo_restante()
+        .map(formatear_eta)
+        .unwrap_or_else(|| t("calculando_eta").to_string());
+    let fraccion = if p.total > 0 {
+        p.actual as f32 / p.total as f32
+    } else {
+        0.0
+    };
+    ui.add(egui::ProgressBar::new(fraccion).desired_width(260.0));
+    ui.label(
+        egui::RichText::new(format!("{}/{} · {}", p.actual, p.total, eta))
+            .small()
+            .color(egui::Color32::GRAY),
+    );
+}
+
+/// Líneas que guarda el historial de la sesión; las más viejas se descartan
+/// (el log persistente en disco las conserva todas)
+const MAXIMO_LINEAS_REGISTRO: usize = 500;
+
+/// Cambia el estado de la aplicación y registra el mensaje en el historial
+fn establecer_estado(estado: &Mutex<EstadoApp>, log: &Mutex<VecDeque<String>>, nuevo: EstadoApp) {
+    match &nuevo {
+        EstadoApp::Procesando(msg) | EstadoApp::Finalizado(msg) | EstadoApp::Error(msg) => {
+            registro::escribir(msg);
+            if let Ok(mut l) = log.lock() {
+                if l.len() >= MAXIMO_LINEAS_REGISTRO {
+                    l.pop_front();
+                }
+                l.push_back(format!(
+                    "[{}] {}",
+                    chrono::Local::now().format("%H:%M:%S"),
+                    msg
+                ));
+            }
+        }
+        EstadoApp::Listo => {}
+    }
+
+    if let Ok(mut e) = estado.lock() {
+        *e = nuevo;
+    }
+}
+
+/// Registra el avance de una operación, conservando el instante de inicio
+fn actualizar_progreso(
+    progreso: &Mutex<Option<Progreso>>,
+    actual: u32,
+    total: u32,
+    cancelable: bool,
+) {
+    if let Ok(mut p) = progreso.lock() {
+        let inicio = match *p {
+            Some(prev) if actual > 0 => prev.inicio,
+            _ => Instant::now(),
+        };
+        *p = Some(Progreso {
+            actual,
+            total,
+            inicio,
+            cancelable,
+        });
+    }
+}
+
+/// Carpeta y Excel generados por el último procesamiento, para abrirlos al terminar
+#[derive(Debug, Clone)]
+struct ResultadoGenerado {
+    carpeta: PathBuf,
+    excel: PathBuf,
+}
+
+/// Lo que una operación en segundo plano ocupa mientras corre
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recurso {
+    /// El navegador automatizado contra SADE
+    Navegador,
+    /// La carpeta de descargas del usuario
+    Descargas,
+    /// La carpeta de PDFs a procesar, el Excel y la revisión pendiente
+    CarpetaTrabajo,
+}
+
+/// Operaciones que la ventana lanza en un thread aparte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operacion {
+    Descarga,
+    Busqueda,
+    Mover,
+    Procesamiento,
+    Todo,
+}
+
+impl Operacion {
+    fn recursos(self) -> &'static [Recurso] {
+        match self {
+            Operacion::Descarga | Operacion::Busqueda => &[Recurso::Navegador, Recurso::Descargas],
+            Operacion::Mover => &[Recurso::Descargas, Recurso::CarpetaTrabajo],
+            Operacion::Procesamiento => &[Recurso::CarpetaTrabajo],
+            Operacion::Todo => &[
+                Recurso::Navegador,
+                Recurso::Descargas,
+                Recurso::CarpetaTrabajo,
+            ],
+        }
+    }
+
+    fn usa(self, recurso: Recurso) -> bool {
+        self.recursos().contains(&recurso)
+    }
+}
+
+/// Operaciones corriendo en este momento. Dos operaciones pueden convivir
+/// mientras no compartan recursos: por ejemplo procesar PDFs durante una descarga.
+#[derive(Debug, Default)]
+struct OperacionesEnCurso {
+    activas: Vec<Operacion>,
+}
+
+impl OperacionesEnCurso {
+    fn ocupado(&self, recurso: Recurso) -> bool {
+        self.activas.iter().any(|op| op.usa(recurso))
+    }
+
+    fn permite(&self, operacion: Operacion) -> bool {
+        !operacion.recursos().iter().any(|r| self.ocupado(*r))
+    }
+
+    fn vacias(&self) -> bool {
+        self.activas.is_empty()
+    }
+}
+
+/// Marca como terminada una operación lanzada en un thread aparte
+fn terminar_operacion(operaciones: &Mutex<OperacionesEnCurso>, operacion: Operacion) {
+    if let Ok(mut ops) = operaciones.lock() {
+        if let Some(i) = ops.activas.iter().position(|op| *op == operacion) {
+            ops.activas.remove(i);
+        }
+    }
+}
+
+/// Cada cuánto se vuelve a contar la carpeta de descargas durante una descarga
+const INTERVALO_CONTEO_DESCARGAS: Duration = Duration::from_secs(1);
+
+/// Conteo en vivo de los PDFs terminados en la carpeta de descargas
+struct ConteoDescargas {
+    carpeta: PathBuf,
+    /// PDFs que ya estaban antes de empezar
+    previos: usize,
+    /// Máximo de PDFs nuevos visto: al mover los archivos la carpeta se vacía
+    descargados: usize,
+    ultima_lectura: Instant,
+}
+
+impl ConteoDescargas {
+    fn nuevo(carpeta: PathBuf) -> Self {
+        Self {
+            previos: web_automation::pdfs_descargados(&carpeta).len(),
+            carpeta,
+            descargados: 0,
+            ultima_lectura: Instant::now(),
+        }
+    }
+
+    /// Vuelve a contar si pasó `INTERVALO_CONTEO_DESCARGAS` desde la última vez
+    fn actualizar(&mut self) {
+        if self.ultima_lectura.elapsed() < INTERVALO_CONTEO_DESCARGAS {
+            return;
+        }
+        let actuales = web_automation::pdfs_descargados(&self.carpeta).len();
+        self.descargados = self.descargados.max(actuales.saturating_sub(self.previos));
+        self.ultima_lectura = Instant::now();
+    }
+}
+
+/// Usuario y contraseña de un operador en el editor de credenciales
+struct FilaCredencial {
+    operador: Operador,
+    usuario: String,
+    password: String,
+}
+
+/// Credenciales en edición, para no tener que escribir el `.env` a mano
+struct EditorCredenciales {
+    filas: Vec<FilaCredencial>,
+    mostrar_passwords: bool,
+}
+
+impl EditorCredenciales {
+    /// Editor con los valores actuales de las variables de entorno
+    fn desde_entorno() -> Self {
+        let filas = OPERADORES
+            .iter()
+            .map(|operador| {
+                let (var_usuario, var_password) = operador.variables();
+                FilaCredencial {
+                    operador: *operador,
+                    usuario: std::env::var(var_usuario).unwrap_or_default(),
+                    password: std::env::var(var_password).unwrap_or_default(),
+                }
+            })
+            .collect();
+        Self {
+            filas,
+            mostrar_passwords: false,
+        }
+    }
+
+    /// Variables a guardar en el `.env`; un operador con solo uno de los dos
+    /// datos es un error, sin ninguno se borran sus variables
+    fn variables(&self) -> Result<Vec<(String, String)>, String> {
+        let mut variables = Vec::new();
+        for fila in &self.filas {
+            let usuario = fila.usuario.trim();
+            if usuario.is_empty() != fila.password.is_empty() {
+                return Err(format!(
+                    "Complete usuario y contraseña de {} (o deje ambos vacíos)",
+                    fila.operador.nombre
+                ));
+            }
+            let (var_usuario, var_password) = fila.operador.variables();
+            variables.push((var_usuario, usuario.to_string()));
+            variables.push((var_password, fila.password.clone()));
+        }
+        Ok(variables)
+    }
+}
+
+/// Qué Excel se genera al confirmar la previsualización
+#[derive(Debug, Clone, Copy)]
+enum Exportacion {
+    /// Todas las filas en la hoja de datos
+    Completa,
+    /// Un libro aparte con una hoja por organismo
+    PorOrganismo,
+    /// Un libro aparte solo con las filas que no son "Sin novedad"
+    ConNovedades,
+}
+
+/// Datos extraídos que esperan la revisión del operador antes de guardar el Excel
+struct Previsualizacion {
+    datos: Vec<pdf_extractor::DatosPdf>,
+    carpeta: PathBuf,
+    ruta_excel: PathBuf,
+    columnas: excel_handler::ColumnasConfig,
+    /// Operador que hizo la corrida, para los metadatos del Excel
+    usuario: Option<String>,
+}
+
+/// Procesa los PDFs de una carpeta y devuelve el mensaje de resumen junto con los
+/// datos extraídos, para revisarlos o guardarlos en el Excel.
+/// El avance por archivo se muestra en el estado sin agregarlo al historial.
+fn procesar_y_resumir(
+    ruta_archivos: &Path,
+    opciones: &pdf_extractor::OpcionesProcesamiento,
+    estado: &Mutex<EstadoApp>,
+    progreso: &Mutex<Option<Progreso>>,
+) -> anyhow::Result<(String, Vec<pdf_extractor::DatosPdf>)> {
+    let resultado =
+        pdf_extractor::procesar_pdfs(ruta_archivos, opciones, |actual, total, archivo| {
+            if let Ok(mut e) = estado.lock() {
+                *e = EstadoApp::Procesando(format!(
+                    "Procesando {} de {}: {}",
+                    actual, total, archivo
+                ));
+            }
+            actualizar_progreso(
+                progreso,
+                (actual as u32).saturating_sub(1),
+                total as u32,
+                false,
+            );
+        });
+    if let Ok(mut p) = progreso.lock() {
+        *p = None;
+    }
+    let resultado = resultado?;
+
+    let resumen = resultado.resumen();
+    let mut mensaje = format!(
+        "{} archivos procesados ({} sin novedad, {} con novedades, {} fallidos)",
+        resultado.datos.len(),
+        resumen.sin_novedad,
+        resumen.con_novedades,
+        resumen.fallidos
+    );
+    if !resultado.archivos_bloqueados.is_empty() {
+        mensaje.push_str(&format!(
+            "\n{} sin mover por estar abiertos: {}",
+            resultado.archivos_bloqueados.len(),
+            resultado.archivos_bloqueados.join(", ")
+        ));
+    }
+    if !resultado.archivos_protegidos.is_empty() {
+        mensaje.push_str(&format!(
+            "\n{} protegidos con contraseña movidos a {}",
+            resultado.archivos_protegidos.len(),
+            pdf_extractor::CARPETA_PROTEGIDOS
+        ));
+    }
+    let ilegibles = resultado
+        .datos
+        .iter()
+        .filter(|d| d.resultado == pdf_extractor::Resultado::RevisionManual)
+        .count();
+    if ilegibles > 0 {
+        mensaje.push_str(&format!(
+            "\n{} sin texto legible movidos a {}",
+            ilegibles,
+            pdf_extractor::CARPETA_ILEGIBLES
+        ));
+    }
+    Ok((mensaje, resultado.datos))
+}
+
+/// Mensaje final de una descarga: procesadas, comunicaciones sin archivos y
+/// descargas que Chrome bloqueó
+fn resumen_descarga(r: &web_automation::ResultadoDescarga) -> String {
+    let mut mensaje = if r.cancelada {
+        format!(
+            "Cancelado: {} de {} comunicaciones procesadas",
+            r.comunicaciones_procesadas, r.total_comunicaciones
+        )
+    } else {
+        format!(
+            "{} de {} comunicaciones procesadas",
+            r.comunicaciones_procesadas, r.total_comunicaciones
+        )
+    };
+    if !r.cancelada && !r.fallidas.is_empty() {
+        mensaje.push_str(&format!(
+            ". Sin archivos: {}",
+            r.fallidas
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    if r.sin_espacio {
+        mensaje.push_str(". Se detuvo por falta de espacio en disco");
+    }
+    if !r.descargas_bloqueadas.is_empty() {
+        mensaje.push_str(&format!(
+            "\n{} descargas bloqueadas por Chrome: {}",
+            r.descargas_bloqueadas.len(),
+            r.descargas_bloqueadas.join(", ")
+        ));
+    }
+    mensaje
+}
+
+/// Abre una carpeta o archivo con la aplicación predeterminada del sistema
+fn abrir_en_sistema(ruta: &Path) -> anyhow::Result<()> {
+    let programa = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(programa)
+        .arg(ruta)
+        .spawn()
+        .with_context(|| format!("No se pudo abrir {:?}", ruta))?;
+    Ok(())
+}
+
+/// Estilo visual de egui según el tema elegido
+fn visuales(oscuro: bool) -> egui::Visuals {
+    if oscuro {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    }
+}
+
+/// Lee la configuración y valida las columnas del Excel, para avisar de un mapeo
+/// inválido al arrancar y no recién al guardar
+fn cargar_configuracion() -> anyhow::Result<Config> {
+    let config = Config::from_env()?;
+    excel_handler::ColumnasConfig::desde_config(&config)?;
+    Ok(config)
+}
+
+/// Pide un nuevo directorio de trabajo y recalcula las rutas que dependen de él
+fn elegir_directorio_trabajo(config: &mut Config) {
+    if let Some(dir) = rfd::FileDialog::new()
+        .set_title("Seleccionar directorio de trabajo")
+        .set_directory(&config.directorio_trabajo)
+        .pick_folder()
+    {
+        config.establecer_directorio_trabajo(dir);
+        registro::inicializar(&config.ruta_archivos);
+    }
+}
+
 /// Aplicación principal
 pub struct AutoSadeApp {
     config: Option<Config>,
     usuario: String,
     comunicacion_inicio: String,
     comunicacion_final: String,
+    /// Comunicaciones sueltas pegadas por el usuario; si tiene texto reemplaza al rango
+    lista_comunicaciones: String,
+    /// Minutos máximos de una descarga; vacío es sin límite
+    tiempo_maximo: String,
+    usar_ocr: bool,
+    descartar_eliminados: bool,
+    modo_colision: ModoColision,
+    incluir_subcarpetas: bool,
+    /// Guardar los PDFs sin novedad en Procesados/<organismo>
+    agrupar_por_organismo: bool,
+    /// Orden de las filas del Excel guardado
+    ordenar_por: OrdenarPor,
+    novedades_al_final: bool,
+    /// Qué hacer si el Excel de salida ya existe
+    modo_guardado: ModoGuardado,
+    perfiles: Vec<PerfilTrabajo>,
+    perfil_activo: String,
+    nombre_perfil_nuevo: String,
     estado: Arc<Mutex<EstadoApp>>,
-    botones_habilitados: Arc<Mutex<bool>>,
+    log: Arc<Mutex<VecDeque<String>>>,
+    progreso: Arc<Mutex<Option<Progreso>>>,
+    /// Estado y avance del procesamiento de PDFs, separados de los de la descarga
+    /// o búsqueda para que no se pisen cuando corren a la par
+    estado_procesamiento: Arc<Mutex<EstadoApp>>,
+    progreso_procesamiento: Arc<Mutex<Option<Progreso>>>,
+    /// Pedido de cancelación de la descarga o búsqueda en curso
+    cancelar: Arc<AtomicBool>,
+    /// Pausa de la descarga en curso, sin cerrar el navegador
+    pausado: Arc<AtomicBool>,
+    tema_oscuro: bool,
+    /// Runtime de tokio creado una vez y reutilizado por descargas y búsquedas
+    runtime: Arc<tokio::runtime::Runtime>,
+    /// Resultado de la última operación que generó un Excel
+    resultado_generado: Arc<Mutex<Option<ResultadoGenerado>>>,
+    /// Datos procesados pendientes de confirmar antes de guardar el Excel
+    previsualizacion: Arc<Mutex<Option<Previsualizacion>>>,
+    /// Operaciones en segundo plano, para habilitar cada acción por separado
+    operaciones: Arc<Mutex<OperacionesEnCurso>>,
+    /// Ventanas abiertas desde el menú
+    ver_credenciales: bool,
+    ver_acerca_de: bool,
+    /// Editor de credenciales abierto
+    editor_credenciales: Option<EditorCredenciales>,
+    /// Operación que mueve archivos (`Mover` o `Todo`) esperando que el operador
+    /// confirme la eliminación
+    confirmar_mover: Option<Operacion>,
+    /// PDFs descargados en la operación en curso
+    conteo_descargas: Option<ConteoDescargas>,
 }
 
-impl Default for AutoSadeApp {
-    fn default() -> Self {
-        let (config, estado_inicial) = match Config::from_env() {
-            Ok(cfg) => (Some(cfg), EstadoApp::Listo),
-            Err(e) => {
-                eprintln!("Error al cargar configuración: {}", e);
-                (
-                    None,
-                    EstadoApp::Error(format!("Error de configuración: {}", e)),
-                )
-            }
-        };
+impl AutoSadeApp {
+    /// Estado inicial de la aplicación, con el runtime de tokio compartido
+    /// por todas las operaciones async
+    fn inicial(runtime: tokio::runtime::Runtime) -> Self {
+        let (config, estado_inicial) = match cargar_configuracion() {
+            Ok(cfg) => (Some(cfg), EstadoApp::Listo),
+            Err(e) => {
+                eprintln!("Error al cargar configuración: {}", e);
+                (
+                    None,
+                    EstadoApp::Error(format!("Error de configuración: {}", e)),
+                )
+            }
+        };
+
+        Self {
+            config,
+            usuario: String::new(),
+            comunicacion_inicio: String::new(),
+            comunicacion_final: String::new(),
+            tiempo_maximo: String::new(),
+            lista_comunicaciones: String::new(),
+            usar_ocr: false,
+            descartar_eliminados: false,
+            modo_colision: ModoColision::default(),
+            incluir_subcarpetas: false,
+            agrupar_por_organismo: false,
+            ordenar_por: OrdenarPor::default(),
+            novedades_al_final: false,
+            modo_guardado: ModoGuardado::default(),
+            perfiles: Vec::new(),
+            perfil_activo: String::new(),
+            nombre_perfil_nuevo: String::new(),
+            estado: Arc::new(Mutex::new(estado_inicial)),
+            log: Arc::new(Mutex::new(VecDeque::new())),
+            progreso: Arc::new(Mutex::new(None)),
+            estado_procesamiento: Arc::new(Mutex::new(EstadoApp::Listo)),
+            progreso_procesamiento: Arc::new(Mutex::new(None)),
+            cancelar: Arc::new(AtomicBool::new(false)),
+            pausado: Arc::new(AtomicBool::new(false)),
+            tema_oscuro: true,
+            runtime: Arc::new(runtime),
+            resultado_generado: Arc::new(Mutex::new(None)),
+            previsualizacion: Arc::new(Mutex::new(None)),
+            operaciones: Arc::new(Mutex::new(OperacionesEnCurso::default())),
+            ver_credenciales: false,
+            ver_acerca_de: false,
+            editor_credenciales: None,
+            confirmar_mover: None,
+            conteo_descargas: None,
+        }
+    }
+
+    pub fn new(cc: &eframe::CreationContext<'_>) -> anyhow::Result<Self> {
+        let runtime =
+            tokio::runtime::Runtime::new().context("No se pudo iniciar el runtime de tokio")?;
+        let mut app = Self::inicial(runtime);
+
+        // Restaurar el directorio de trabajo elegido en sesiones anteriores
+        if let Some(storage) = cc.storage {
+            if let Some(dir) = eframe::get_value::<PathBuf>(storage, CLAVE_DIRECTORIO_TRABAJO) {
+                if let Some(config) = &mut app.config {
+                    config.establecer_directorio_trabajo(dir);
+                }
+            }
+
+            // Carpeta de PDFs y Excel elegidos puntualmente (pisan al directorio de trabajo)
+            if let Some(config) = &mut app.config {
+                if let Some(ruta) = eframe::get_value::<PathBuf>(storage, CLAVE_RUTA_ARCHIVOS) {
+                    config.establecer_ruta_archivos(ruta);
+                }
+                if let Some(ruta) = eframe::get_value::<PathBuf>(storage, CLAVE_RUTA_EXCEL) {
+                    config.ruta_excel = ruta;
+                }
+            }
+
+            if let Some(entradas) =
+                eframe::get_value::<EntradasFormulario>(storage, CLAVE_FORMULARIO)
+            {
+                app.usuario = entradas.usuario;
+                app.comunicacion_inicio = entradas.comunicacion_inicio;
+                app.comunicacion_final = entradas.comunicacion_final;
+                app.usar_ocr = entradas.usar_ocr;
+                app.descartar_eliminados = entradas.descartar_eliminados;
+                app.modo_colision = entradas.modo_colision;
+                app.incluir_subcarpetas = entradas.incluir_subcarpetas;
+                app.agrupar_por_organismo = entradas.agrupar_por_organismo;
+                app.ordenar_por = entradas.ordenar_por;
+                app.novedades_al_final = entradas.novedades_al_final;
+                app.modo_guardado = entradas.modo_guardado;
+                app.tiempo_maximo = entradas.tiempo_maximo;
+            }
+
+            app.perfiles = eframe::get_value(storage, CLAVE_PERFILES).unwrap_or_default();
+            app.perfil_activo = eframe::get_value(storage, CLAVE_PERFIL_ACTIVO).unwrap_or_default();
+            i18n::establecer_idioma(eframe::get_value(storage, CLAVE_IDIOMA).unwrap_or_default());
+            app.tema_oscuro = eframe::get_value(storage, CLAVE_TEMA_OSCURO).unwrap_or(true);
+        }
+
+        cc.egui_ctx.set_visuals(visuales(app.tema_oscuro));
+        app.inicializar_registro();
+        // Perfiles de Chrome que corridas anteriores no pudieron borrar
+        std::thread::spawn(perfil_temporal::limpiar_perfiles_huerfanos);
+        Ok(app)
+    }
+
+    /// Vuelve a leer el `.env` junto al ejecutable (pisando los valores ya cargados)
+    /// y recrea la configuración, informando en el estado si cargó o qué falta.
+    /// Con `conservar_directorio` se mantiene el directorio de trabajo elegido.
+    fn recargar_configuracion(&mut self, conservar_directorio: bool) {
+        let ruta_env = obtener_directorio_exe().join(".env");
+        if ruta_env.exists() {
+            if let Err(e) = dotenvy::from_path_override(&ruta_env) {
+                self.actualizar_estado(EstadoApp::Error(format!(
+                    "No se pudo leer {}: {}",
+                    ruta_env.display(),
+                    e
+                )));
+                return;
+            }
+        }
+
+        let directorio = self
+            .config
+            .as_ref()
+            .filter(|_| conservar_directorio)
+            .map(|c| c.directorio_trabajo.clone());
+        match cargar_configuracion() {
+            Ok(mut cfg) => {
+                if let Some(dir) = directorio {
+                    cfg.establecer_directorio_trabajo(dir);
+                }
+                let usuarios = cfg.usuarios.len();
+                self.config = Some(cfg);
+                self.actualizar_estado(EstadoApp::Finalizado(format!(
+                    "Configuración recargada: {} usuario(s)",
+                    usuarios
+                )));
+            }
+            Err(e) => {
+                self.config = None;
+                self.actualizar_estado(EstadoApp::Error(format!(
+                    "Error de configuración: {:#}",
+                    e
+                )));
+            }
+        }
+        self.inicializar_registro();
+    }
+
+    /// Apunta el log persistente a la carpeta de PDFs actual
+    fn inicializar_registro(&self) {
+        let ruta = self
+            .config
+            .as_ref()
+            .map(|c| c.ruta_archivos.clone())
+            .unwrap_or_else(|| Config::default().ruta_archivos);
+        registro::inicializar(&ruta);
+    }
+
+    /// Guarda el estado actual del formulario como perfil con el nombre dado,
+    /// reemplazando uno existente con el mismo nombre
+    fn guardar_perfil(&mut self, nombre: &str) {
+        let perfil = PerfilTrabajo {
+            nombre: nombre.to_string(),
+            usuario: self.usuario.clone(),
+            directorio_trabajo: self.config.as_ref().map(|c| c.directorio_trabajo.clone()),
+            comunicacion_inicio: self.comunicacion_inicio.clone(),
+            comunicacion_final: self.comunicacion_final.clone(),
+            usar_ocr: self.usar_ocr,
+        };
+
+        match self.perfiles.iter_mut().find(|p| p.nombre == nombre) {
+            Some(existente) => *existente = perfil,
+            None => self.perfiles.push(perfil),
+        }
+        self.perfil_activo = nombre.to_string();
+    }
+
+    /// Activa un perfil: recarga la configuración y aplica sus valores
+    fn aplicar_perfil(&mut self, nombre: &str) {
+        let Some(perfil) = self.perfiles.iter().find(|p| p.nombre == nombre).cloned() else {
+            return;
+        };
+
+        match cargar_configuracion() {
+            Ok(mut cfg) => {
+                if let Some(dir) = perfil.directorio_trabajo {
+                    cfg.establecer_directorio_trabajo(dir);
+                }
+                self.config = Some(cfg);
+                self.actualizar_estado(EstadoApp::Listo);
+            }
+            Err(e) => {
+                self.config = None;
+                self.actualizar_estado(EstadoApp::Error(format!("Error de configuración: {}", e)));
+            }
+        }
+
+        self.usuario = perfil.usuario;
+        self.comunicacion_inicio = perfil.comunicacion_inicio;
+        self.comunicacion_final = perfil.comunicacion_final;
+        self.usar_ocr = perfil.usar_ocr;
+        self.perfil_activo = perfil.nombre;
+        self.inicializar_registro();
+    }
+
+    fn actualizar_estado(&self, nuevo_estado: EstadoApp) {
+        establecer_estado(&self.estado, &self.log, nuevo_estado);
+    }
+
+    fn iniciar_operacion(&self, operacion: Operacion) {
+        if let Ok(mut ops) = self.operaciones.lock() {
+            // Sin nada corriendo ya no tiene sentido mostrar el procesamiento anterior
+            if ops.vacias() {
+                if let Ok(mut e) = self.estado_procesamiento.lock() {
+                    *e = EstadoApp::Listo;
+                }
+            }
+            ops.activas.push(operacion);
+        }
+        // Al arrancar otra operación ya no se ofrece abrir el resultado anterior
+        self.establecer_resultado(None);
+    }
+
+    fn establecer_resultado(&self, resultado: Option<ResultadoGenerado>) {
+        if let Ok(mut r) = self.resultado_generado.lock() {
+            *r = resultado;
+        }
+    }
+
+    fn revision_pendiente(&self) -> bool {
+        self.previsualizacion
+            .lock()
+            .map(|p| p.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Los ajustes generales (directorio, perfiles, credenciales) solo se tocan
+    /// cuando no corre ninguna operación ni hay datos esperando revisión
+    fn botones_estan_habilitados(&self) -> bool {
+        self.operaciones.lock().map(|o| o.vacias()).unwrap_or(true)
+            && !self.revision_pendiente()
+            && self.confirmar_mover.is_none()
+    }
+
+    /// Cada acción se habilita si ninguna operación en curso usa sus recursos;
+    /// la revisión pendiente ocupa la carpeta de trabajo hasta confirmarla
+    fn accion_habilitada(&self, operacion: Operacion) -> bool {
+        if self.confirmar_mover.is_some()
+            || (operacion.usa(Recurso::CarpetaTrabajo) && self.revision_pendiente())
+        {
+            return false;
+        }
+        self.operaciones
+            .lock()
+            .map(|o| o.permite(operacion))
+            .unwrap_or(true)
+    }
+
+    /// Guarda en el Excel los datos revisados y ofrece abrir el resultado.
+    /// Las exportaciones por organismo y solo con novedades van a un libro aparte
+    /// y dejan la revisión abierta para guardar también el Excel completo.
+    fn confirmar_previsualizacion(&self, exportacion: Exportacion) {
+        let Some(revision) = self.previsualizacion.lock().ok().and_then(|mut p| p.take()) else {
+            return;
+        };
+        let orden = OrdenFilas {
+            por: self.ordenar_por,
+            novedades_al_final: self.novedades_al_final,
+        };
+        let destino = match exportacion {
+            Exportacion::Completa => revision.ruta_excel.clone(),
+            Exportacion::PorOrganismo => {
+                excel_handler::ruta_con_sufijo(&revision.ruta_excel, "por organismo")
+            }
+            Exportacion::ConNovedades => excel_handler::ruta_con_sufijo(
+                &revision.ruta_excel,
+                excel_handler::SUFIJO_CON_NOVEDADES,
+            ),
+        };
+        let guardado = match exportacion {
+            Exportacion::Completa => excel_handler::guardar_excel(
+                &revision.datos,
+                &destino,
+                &revision.columnas,
+                orden,
+                self.modo_guardado,
+            ),
+            Exportacion::PorOrganismo => excel_handler::guardar_excel_por_organismo(
+                &revision.datos,
+                &destino,
+                &revision.columnas,
+                orden,
+            ),
+            Exportacion::ConNovedades => excel_handler::guardar_excel_filtrado(
+                &revision.datos,
+                &destino,
+                &revision.columnas,
+                orden,
+            ),
+        };
+        match guardado {
+            Ok(excel) => {
+                let metadatos = excel_handler::MetadatosCorrida::nuevos(
+                    &revision.datos,
+                    revision.usuario.as_deref(),
+                );
+                let mut mensaje = format!("Guardado en: {}", excel.display());
+                // Si el Excel ya existía se guarda una copia con fecha y hora
+                if excel != destino {
+                    mensaje.push_str(&format!(
+                        "\n{} ya existía, se guardó una copia nueva",
+                        destino.display()
+                    ));
+                }
+                if let Err(e) = excel_handler::guardar_metadatos(&excel, &metadatos) {
+                    mensaje.push_str(&format!("\n{:#}", e));
+                }
+                let carpeta = revision.carpeta.clone();
+                if !matches!(exportacion, Exportacion::Completa) {
+                    mensaje.push_str("\nLa revisión sigue abierta para guardar el Excel completo");
+                    if let Ok(mut p) = self.previsualizacion.lock() {
+                        *p = Some(revision);
+                    }
+                }
+                self.actualizar_estado(EstadoApp::Finalizado(mensaje));
+                self.establecer_resultado(Some(ResultadoGenerado { carpeta, excel }));
+            }
+            Err(e) => {
+                self.actualizar_estado(EstadoApp::Error(format!(
+                    "Error al guardar Excel: {:#}",
+                    e
+                )));
+                // Conservar los datos para poder reintentar (p. ej. con el Excel cerrado)
+                if let Ok(mut p) = self.previsualizacion.lock() {
+                    *p = Some(revision);
+                }
+            }
+        }
+    }
+
+    /// Descarta los datos procesados sin guardar el Excel y devuelve los PDFs a
+    /// donde estaban, para poder procesarlos de nuevo
+    fn descartar_previsualizacion(&self) {
+        let Some(revision) = self.previsualizacion.lock().ok().and_then(|mut p| p.take()) else {
+            return;
+        };
+        let no_devueltos = pdf_extractor::devolver_a_origen(&revision.carpeta, &revision.datos);
+        self.actualizar_estado(if no_devueltos.is_empty() {
+            EstadoApp::Finalizado(
+                "Excel no guardado: se descartaron los datos y los PDFs volvieron a su carpeta"
+                    .to_string(),
+            )
+        } else {
+            EstadoApp::Error(format!(
+                "Excel no guardado. No se pudieron devolver {} PDFs a su carpeta: {}",
+                no_devueltos.len(),
+                no_devueltos.join(", ")
+            ))
+        });
+    }
+
+    /// Ventana con los datos extraídos para revisarlos antes de guardar el Excel.
+    /// Las filas con novedades se resaltan en rojo y el resultado se puede corregir;
+    /// al corregirlo, el PDF se mueve a la carpeta que corresponde.
+    fn mostrar_previsualizacion(&self, ctx: &egui::Context) {
+        let Ok(mut pendiente) = self.previsualizacion.lock() else {
+            return;
+        };
+        let Some(revision) = pendiente.as_mut() else {
+            return;
+        };
+
+        let mut correccion = None;
+        let mut exportar = None;
+        let mut exportar_json = false;
+        let mut descartar = false;
+        egui::Window::new(t("previsualizacion"))
+            .collapsible(false)
+            .default_size([720.0, 420.0])
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} {}",
+                    revision.datos.len(),
+                    t("archivos_a_guardar")
+                ));
+                ui.add_space(6.0);
+                egui::ScrollArea::both()
+                    .max_height(320.0)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        egui::Grid::new("previsualizacion_grid")
+                            .num_columns(5)
+                            .striped(true)
+                            .spacing([12.0, 4.0])
+                            .show(ui, |ui| {
+                                for encabezado in
+                                    ["CCOO", "Organismo", "Patrimonial", "Fecha", "Resultado"]
+                                {
+                                    ui.label(egui::RichText::new(encabezado).strong());
+                                }
+                                ui.end_row();
+
+                                let rojo = ui.visuals().error_fg_color;
+                                for (indice, dato) in revision.datos.iter().enumerate() {
+                                    let color = (dato.resultado
+                                        == pdf_extractor::Resultado::ConNovedades)
+                                        .then_some(rojo);
+                                    let fecha = dato
+                                        .fecha
+                                        .map(|f| f.format("%d/%m/%Y").to_string())
+                                        .unwrap_or_default();
+                                    for celda in [
+                                        dato.ccoo.as_str(),
+                                        dato.organismo.as_str(),
+                                        dato.patrimonial.as_str(),
+                                        fecha.as_str(),
+                                    ] {
+                                        let mut texto = egui::RichText::new(celda);
+                                        if let Some(color) = color {
+                                            texto = texto.color(color);
+                                        }
+                                        ui.label(texto);
+                                    }
+
+                                    let mut seleccionado = dato.resultado;
+                                    egui::ComboBox::from_id_salt(("resultado", indice))
+                                        .selected_text(seleccionado.texto())
+                                        .show_ui(ui, |ui| {
+                                            for opcion in pdf_extractor::Resultado::TODOS {
+                                                ui.selectable_value(
+                                                    &mut seleccionado,
+                                                    opcion,
+                                                    opcion.texto(),
+                                                );
+                                            }
+                                        });
+                                    if seleccionado != dato.resultado {
+                                        correccion = Some((indice, seleccionado));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button(t("guardar_excel")).clicked() {
+                        exportar = Some(Exportacion::Completa);
+                    }
+                    if ui.button(t("guardar_por_organismo")).clicked() {
+                        exportar = Some(Exportacion::PorOrganismo);
+                    }
+                    if ui.button(t("guardar_con_novedades")).clicked() {
+                        exportar = Some(Exportacion::ConNovedades);
+                    }
+                    exportar_json = ui.button(t("exportar_json")).clicked();
+                    descartar = ui.button(t("descartar")).clicked();
+                });
+            });
+        if let Some((indice, nuevo)) = correccion {
+            if let Err(e) =
+                pdf_extractor::reclasificar(&revision.carpeta, &mut revision.datos[indice], nuevo)
+            {
+                self.actualizar_estado(EstadoApp::Error(format!("{:#}", e)));
+            }
+        }
+        // El JSON no cierra la revisión: se puede guardar también el Excel
+        if exportar_json {
+            let ruta = excel_handler::ruta_json(&revision.ruta_excel);
+            self.actualizar_estado(match excel_handler::guardar_json(&revision.datos, &ruta) {
+                Ok(()) => EstadoApp::Finalizado(format!("JSON guardado en {}", ruta.display())),
+                Err(e) => EstadoApp::Error(format!("{:#}", e)),
+            });
+        }
+        drop(pendiente);
+
+        if let Some(exportacion) = exportar {
+            self.confirmar_previsualizacion(exportacion);
+        } else if descartar {
+            self.descartar_previsualizacion();
+        }
+    }
+
+    /// Columnas del Excel de salida según la configuración (ya validadas al cargarla)
+    fn columnas_excel(&self) -> excel_handler::ColumnasConfig {
+        self.config
+            .as_ref()
+            .and_then(|c| excel_handler::ColumnasConfig::desde_config(c).ok())
+            .unwrap_or_default()
+    }
+
+    /// Comunicaciones a descargar: la lista pegada si tiene texto, o el rango
+    /// inicial-final del formulario
+    fn comunicaciones_a_descargar(&self) -> anyhow::Result<Vec<u32>> {
+        if !self.lista_comunicaciones.trim().is_empty() {
+            return web_automation::parsear_lista_comunicaciones(&self.lista_comunicaciones);
+        }
+        let numero = |texto: &str, campo: &str| {
+            texto
+                .trim()
+                .parse::<u32>()
+                .with_context(|| format!("La comunicación {} '{}' no es un número", campo, texto))
+        };
+        let inicio = numero(&self.comunicacion_inicio, "inicial")?;
+        let final_ = numero(&self.comunicacion_final, "final")?;
+        web_automation::rango_comunicaciones(inicio, final_)
+    }
+
+    /// Tiempo máximo de la descarga según el formulario, en minutos; vacío es sin límite
+    fn tiempo_maximo_descarga(&self) -> anyhow::Result<Option<Duration>> {
+        let texto = self.tiempo_maximo.trim();
+        if texto.is_empty() {
+            return Ok(None);
+        }
+        match texto.parse::<u64>() {
+            Ok(minutos) if minutos > 0 => Ok(Some(Duration::from_secs(minutos * 60))),
+            _ => anyhow::bail!(
+                "El tiempo máximo '{}' debe ser una cantidad de minutos mayor a 0",
+                texto
+            ),
+        }
+    }
+
+    /// Nombre del operador elegido, para los metadatos del Excel
+    fn nombre_operador(&self) -> Option<String> {
+        self.config
+            .as_ref()
+            .and_then(|c| c.get_credenciales(&self.usuario))
+            .map(|c| c.nombre.clone())
+    }
+
+    /// Opciones de procesamiento de PDFs según el formulario y la configuración
+    fn opciones_procesamiento(&self) -> pdf_extractor::OpcionesProcesamiento {
+        pdf_extractor::OpcionesProcesamiento {
+            ocr: self.usar_ocr,
+            password: self.config.as_ref().and_then(|c| c.password_pdf.clone()),
+            recursivo: self.incluir_subcarpetas,
+            archivos: None,
+            por_organismo: self.agrupar_por_organismo,
+            mover: true,
+            diagnostico: cfg!(debug_assertions),
+            fuente_fecha: pdf_extractor::FuenteFecha::default(),
+        }
+    }
+
+    /// Procesa los PDFs de una carpeta y guarda el Excel en la ruta indicada,
+    /// en un thread aparte para no congelar la ventana. Informa su avance por su
+    /// propio canal: una descarga o búsqueda puede empezar o terminar mientras tanto.
+    fn procesar_carpeta(&self, ruta_archivos: PathBuf, ruta_excel: PathBuf, mensaje: String) {
+        // Si no corre nada más, el mensaje de la operación anterior ya no corresponde
+        if self.operaciones.lock().is_ok_and(|o| o.vacias()) {
+            self.actualizar_estado(EstadoApp::Listo);
+        }
+        let estado = Arc::clone(&self.estado_procesamiento);
+        let progreso = Arc::clone(&self.progreso_procesamiento);
+        self.iniciar_operacion(Operacion::Procesamiento);
+        establecer_estado(&estado, &self.log, EstadoApp::Procesando(mensaje));
+
+        let opciones = self.opciones_procesamiento();
+        let columnas = self.columnas_excel();
+        let log = Arc::clone(&self.log);
+        let previsualizacion = Arc::clone(&self.previsualizacion);
+        let usuario = self.nombre_operador();
+        let operaciones = Arc::clone(&self.operaciones);
+
+        std::thread::spawn(move || {
+            let final_estado =
+                match procesar_y_resumir(&ruta_archivos, &opciones, &estado, &progreso) {
+                    Ok((mensaje, datos)) => {
+                        if let Ok(mut p) = previsualizacion.lock() {
+                            *p = Some(Previsualizacion {
+                                datos,
+                                carpeta: ruta_archivos.clone(),
+                                ruta_excel,
+                                columnas,
+                                usuario,
+                            });
+                        }
+                        EstadoApp::Finalizado(format!(
+                            "{}\nRevise los datos y guarde el Excel",
+                            mensaje
+                        ))
+                    }
+                    Err(e) => EstadoApp::Error(e.to_string()),
+                };
+            establecer_estado(&estado, &log, final_estado);
+
+            terminar_operacion(&operaciones, Operacion::Procesamiento);
+        });
+    }
+
+    /// Mueve los PDFs de la carpeta de descargas del usuario a la de trabajo,
+    /// en un thread aparte para no congelar la ventana
+    fn mover_archivos(&self) {
+        let Some(config) = self.config.clone() else {
+            return;
+        };
+
+        self.iniciar_operacion(Operacion::Mover);
+        self.actualizar_estado(EstadoApp::Procesando("Moviendo archivos...".to_string()));
+
+        let usuario = self.usuario.clone();
+        let opciones = file_processor::OpcionesMover {
+            descartar: self.descartar_eliminados,
+            colision: self.modo_colision,
+        };
+        let estado = Arc::clone(&self.estado);
+        let log = Arc::clone(&self.log);
+        let operaciones = Arc::clone(&self.operaciones);
+
+        std::thread::spawn(move || {
+            let resultado = config
+                .ruta_descargas_usuario(&usuario)
+                .and_then(|ruta_descarga| {
+                    file_processor::mover_archivos(&ruta_descarga, &config.ruta_archivos, &opciones)
+                });
+
+            let final_estado = match resultado {
+                Ok(resultado) => {
+                    if !resultado.archivos_eliminados_nombres.is_empty() {
+                        establecer_estado(
+                            &estado,
+                            &log,
+                            EstadoApp::Procesando(format!(
+                                "{}: {}",
+                                if opciones.descartar {
+                                    "Descartados"
+                                } else {
+                                    "Eliminados"
+                                },
+                                resultado.archivos_eliminados_nombres.join(", ")
+                            )),
+                        );
+                    }
+                    let neto = resultado
+                        .archivos_movidos
+                        .saturating_sub(resultado.archivos_eliminados);
+                    EstadoApp::Finalizado(format!(
+                        "{} movidos, {} eliminados, {} colisiones, {} duplicados",
+                        neto,
+                        resultado.archivos_eliminados,
+                        resultado.colisiones,
+                        resultado.duplicados
+                    ))
+                }
+                Err(e) => EstadoApp::Error(e.to_string()),
+            };
+            establecer_estado(&estado, &log, final_estado);
+
+            terminar_operacion(&operaciones, Operacion::Mover);
+        });
+    }
+
+    /// Descarga las comunicaciones del formulario (rango o lista) en un thread aparte
+    fn descargar(&mut self) {
+        let numeros_y_limite = self
+            .comunicaciones_a_descargar()
+            .and_then(|n| Ok((n, self.tiempo_maximo_descarga()?)));
+        let (numeros, tiempo_maximo) = match numeros_y_limite {
+            Ok(numeros_y_limite) => numeros_y_limite,
+            Err(e) => {
+                self.actualizar_estado(EstadoApp::Error(format!("{:#}", e)));
+                return;
+            }
+        };
+
+        self.iniciar_operacion(Operacion::Descarga);
+        self.actualizar_estado(EstadoApp::Procesando(
+            "Descargando comunicaciones...".to_string(),
+        ));
+        self.iniciar_conteo_descargas();
+
+        let usuario = self.usuario.clone();
+        let config = self.config.clone();
+        let estado = Arc::clone(&self.estado);
+        let log = Arc::clone(&self.log);
+        let progreso = Arc::clone(&self.progreso);
+        let cancelar = self.iniciar_cancelable();
+        let pausado = self.iniciar_pausable();
+        let runtime = Arc::clone(&self.runtime);
+        let operaciones = Arc::clone(&self.operaciones);
+
+        std::thread::spawn(move || {
+            let resultado = runtime.block_on(async {
+                if let Some(cfg) = config {
+                    web_automation::descargar_lista(
+                        &numeros,
+                        &usuario,
+                        &cfg,
+                        &cancelar,
+                        &pausado,
+                        tiempo_maximo,
+                        |msg| {
+                            establecer_estado(
+                                &estado,
+                                &log,
+                                EstadoApp::Procesando(msg.to_string()),
+                            );
+                        },
+                        |actual, total| actualizar_progreso(&progreso, actual, total, true),
+                    )
+                    .await
+                } else {
+                    Err(anyhow::anyhow!("Configuración no disponible"))
+                }
+            });
+
+            let final_estado = match resultado {
+                Ok(r) => EstadoApp::Finalizado(resumen_descarga(&r)),
+                Err(e) => EstadoApp::Error(e.to_string()),
+            };
+            establecer_estado(&estado, &log, final_estado);
+
+            if let Ok(mut p) = progreso.lock() {
+                *p = None;
+            }
+
+            terminar_operacion(&operaciones, Operacion::Descarga);
+        });
+    }
+
+    /// Empieza a contar los PDFs que llegan a la carpeta de descargas del usuario
+    fn iniciar_conteo_descargas(&mut self) {
+        self.conteo_descargas = self
+            .config
+            .as_ref()
+            .and_then(|c| c.ruta_descargas_usuario(&self.usuario).ok())
+            .map(ConteoDescargas::nuevo);
+    }
+
+    /// Reinicia el pedido de cancelación y devuelve el flag para la operación a lanzar
+    fn iniciar_cancelable(&self) -> Arc<AtomicBool> {
+        self.cancelar.store(false, Ordering::Relaxed);
+        Arc::clone(&self.cancelar)
+    }
+
+    /// Quita una pausa que haya quedado de otra corrida y devuelve el flag para
+    /// la descarga a lanzar
+    fn iniciar_pausable(&self) -> Arc<AtomicBool> {
+        self.pausado.store(false, Ordering::Relaxed);
+        Arc::clone(&self.pausado)
+    }
+
+    /// Encadena descargar, mover, procesar y guardar el Excel en un thread,
+    /// deteniéndose en la primera etapa que falle
+    fn ejecutar_todo(&mut self) {
+        let Some(config) = self.config.clone() else {
+            self.actualizar_estado(EstadoApp::Error("Configuración no disponible".to_string()));
+            return;
+        };
+
+        let numeros_y_limite = self
+            .comunicaciones_a_descargar()
+            .and_then(|n| Ok((n, self.tiempo_maximo_descarga()?)));
+        let (numeros, tiempo_maximo) = match numeros_y_limite {
+            Ok(numeros_y_limite) => numeros_y_limite,
+            Err(e) => {
+                self.actualizar_estado(EstadoApp::Error(format!("{:#}", e)));
+                return;
+            }
+        };
+
+        self.iniciar_operacion(Operacion::Todo);
+        self.actualizar_estado(EstadoApp::Procesando(
+            "Descargando comunicaciones...".to_string(),
+        ));
+        self.iniciar_conteo_descargas();
+
+        let usuario = self.usuario.clone();
+        let operador = self.nombre_operador();
+        let opciones_mover = file_processor::OpcionesMover {
+            descartar: self.descartar_eliminados,
+            colision: self.modo_colision,
+        };
+        let opciones_procesamiento = self.opciones_procesamiento();
+        let columnas = self.columnas_excel();
+        let orden = OrdenFilas {
+            por: self.ordenar_por,
+            novedades_al_final: self.novedades_al_final,
+        };
+        let modo_guardado = self.modo_guardado;
+        let estado = Arc::clone(&self.estado);
+        let log = Arc::clone(&self.log);
+        let progreso = Arc::clone(&self.progreso);
+        let cancelar = self.iniciar_cancelable();
+        let pausado = self.iniciar_pausable();
+        let resultado_generado = Arc::clone(&self.resultado_generado);
+        let runtime = Arc::clone(&self.runtime);
+        let operaciones = Arc::clone(&self.operaciones);
+
+        std::thread::spawn(move || {
+            let resultado = (|| -> anyhow::Result<String> {
+                let descarga = runtime
+                    .block_on(web_automation::descargar_lista(
+                        &numeros,
+                        &usuario,
+                        &config,
+                        &cancelar,
+                        &pausado,
+                        tiempo_maximo,
+                        |msg| {
+                            establecer_estado(
+                                &estado,
+                                &log,
+                                EstadoApp::Procesando(msg.to_string()),
+                            );
+                        },
+                        |actual, total| actualizar_progreso(&progreso, actual, total, true),
+                    ))
+                    .context("Falló la etapa de descarga")?;
+                if let Ok(mut p) = progreso.lock() {
+                    *p = None;
+                }
+                if descarga.cancelada {
+                    return Ok(format!(
+                        "Cancelado: {} de {} comunicaciones descargadas, no se movió ni procesó",
+                        descarga.comunicaciones_procesadas, descarga.total_comunicaciones
+                    ));
+                }
+                if descarga.sin_espacio {
+                    anyhow::bail!(
+                        "Sin espacio en disco: {} de {} comunicaciones descargadas, \
+                         no se movió ni procesó",
+                        descarga.comunicaciones_procesadas,
+                        descarga.total_comunicaciones
+                    );
+                }
+
+                establecer_estado(
+                    &estado,
+                    &log,
+                    EstadoApp::Procesando("Moviendo archivos...".to_string()),
+                );
+                let movidos = config
+                    .ruta_descargas_usuario(&usuario)
+                    .and_then(|ruta_descarga| {
+                        file_processor::mover_archivos(
+                            &ruta_descarga,
+                            &config.ruta_archivos,
+                            &opciones_mover,
+                        )
+                    })
+                    .context("Falló la etapa de mover archivos")?;
+
+                establecer_estado(
+                    &estado,
+                    &log,
+                    EstadoApp::Procesando("Procesando PDFs...".to_string()),
+                );
+                let (procesados, datos) = procesar_y_resumir(
+                    &config.ruta_archivos,
+                    &opciones_procesamiento,
+                    &estado,
+                    &progreso,
+                )
+                .context("Falló la etapa de procesamiento")?;
+
+                // Sin revisión intermedia: el Excel se guarda al final de la cadena
+                let excel = excel_handler::guardar_excel(
+                    &datos,
+                    &config.ruta_excel,
+                    &columnas,
+                    orden,
+                    modo_guardado,
+                )
+                .context("Error al guardar Excel")?;
+                let metadatos =
+                    excel_handler::MetadatosCorrida::nuevos(&datos, operador.as_deref());
+
+                let mut mensaje = format!(
+                    "{} de {} comunicaciones descargadas, {} archivos movidos\n{}\n\
+                     Guardado en: {}",
+                    descarga.comunicaciones_procesadas,
+                    descarga.total_comunicaciones,
+                    movidos.archivos_movidos,
+                    procesados,
+                    excel.display()
+                );
+                if let Err(e) = excel_handler::guardar_metadatos(&excel, &metadatos) {
+                    mensaje.push_str(&format!("\n{:#}", e));
+                }
+                if let Ok(mut r) = resultado_generado.lock() {
+                    *r = Some(ResultadoGenerado {
+                        carpeta: config.ruta_archivos.clone(),
+                        excel,
+                    });
+                }
+                if !descarga.descargas_bloqueadas.is_empty() {
+                    mensaje.push_str(&format!(
+                        "\n{} descargas bloqueadas por Chrome: {}",
+                        descarga.descargas_bloqueadas.len(),
+                        descarga.descargas_bloqueadas.join(", ")
+                    ));
+                }
+                Ok(mensaje)
+            })();
+
+            let final_estado = match resultado {
+                Ok(mensaje) => EstadoApp::Finalizado(mensaje),
+                Err(e) => EstadoApp::Error(format!("{:#}", e)),
+            };
+            establecer_estado(&estado, &log, final_estado);
+
+            if let Ok(mut p) = progreso.lock() {
+                *p = None;
+            }
+            terminar_operacion(&operaciones, Operacion::Todo);
+        });
+    }
+
+    /// Procesa una carpeta fuera de la configuración (elegida o arrastrada),
+    /// generando el Excel dentro de esa misma carpeta
+    fn procesar_carpeta_adhoc(&self, carpeta: &Path) {
+        self.procesar_carpeta(
+            carpeta.to_path_buf(),
+            carpeta.join(NOMBRE_EXCEL),
+            format!("Procesando PDFs de {}...", carpeta.display()),
+        );
+    }
+
+    /// Barra de menú superior: archivo, configuración y ayuda
+    fn mostrar_menu(&mut self, ctx: &egui::Context) {
+        let habilitado = self.botones_estan_habilitados();
+        egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button(t("menu_archivo"), |ui| {
+                    let hay_config = self.config.is_some();
+                    if ui
+                        .add_enabled(
+                            habilitado && hay_config,
+                            egui::Button::new(t("elegir_directorio_trabajo")),
+                        )
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(config) = &mut self.config {
+                            elegir_directorio_trabajo(config);
+                        }
+                    }
+                    if ui
+                        .add_enabled(hay_config, egui::Button::new(t("abrir_excel")))
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        if let Some(config) = &self.config {
+                            let resultado = if config.ruta_excel.exists() {
+                                abrir_en_sistema(&config.ruta_excel)
+                            } else {
+                                Err(anyhow::anyhow!(
+                                    "Todavía no existe el Excel {}",
+                                    config.ruta_excel.display()
+                                ))
+                            };
+                            if let Err(e) = resultado {
+                                self.actualizar_estado(EstadoApp::Error(e.to_string()));
+                            }
+                        }
+                    }
+                    ui.separator();
+                    if ui.button(t("salir")).clicked() {
+                        ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
+
+                ui.menu_button(t("menu_configuracion"), |ui| {
+                    if ui
+                        .checkbox(&mut self.tema_oscuro, t("tema_oscuro"))
+                        .on_hover_text(t("cambiar_tema"))
+                        .changed()
+                    {
+                        ui.ctx().set_visuals(visuales(self.tema_oscuro));
+                    }
+                    ui.menu_button(format!("🌐 {}", t("idioma")), |ui| {
+                        let mut idioma = i18n::idioma_actual();
+                        for opcion in Idioma::TODOS {
+                            ui.radio_value(&mut idioma, opcion, opcion.nombre());
+                        }
+                        if idioma != i18n::idioma_actual() {
+                            i18n::establecer_idioma(idioma);
+                            ui.close_menu();
+                        }
+                    });
+                    ui.separator();
+                    if ui.button(t("credenciales_cargadas")).clicked() {
+                        self.ver_credenciales = true;
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(habilitado, egui::Button::new(t("editar_credenciales")))
+                        .clicked()
+                    {
+                        self.editor_credenciales = Some(EditorCredenciales::desde_entorno());
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add_enabled(habilitado, egui::Button::new(t("recargar_configuracion")))
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        self.recargar_configuracion(false);
+                    }
+                });
 
-        Self {
-            config,
-            usuario: String::new(),
-            comunicacion_inicio: String::new(),
-            comunicacion_final: String::new(),
-            estado: Arc::new(Mutex::new(estado_inicial)),
-            botones_habilitados: Arc::new(Mutex::new(true)),
-        }
+                ui.menu_button(t("menu_ayuda"), |ui| {
+                    if ui.button(t("acerca_de")).clicked() {
+                        self.ver_acerca_de = true;
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
     }
-}
 
-impl AutoSadeApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        Self::default()
+    /// Usuarios cargados del `.env`, sin mostrar las contraseñas
+    fn mostrar_credenciales(&mut self, ctx: &egui::Context) {
+        let usuarios: Vec<(String, String, String)> = self
+            .config
+            .as_ref()
+            .map(|c| {
+                c.usuarios_ordenados()
+                    .into_iter()
+                    .map(|(id, cred)| (id.clone(), cred.nombre.clone(), cred.usuario.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        egui::Window::new(t("credenciales_cargadas"))
+            .open(&mut self.ver_credenciales)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if usuarios.is_empty() {
+                    ui.label(t("sin_credenciales"));
+                    return;
+                }
+                egui::Grid::new("credenciales_grid")
+                    .num_columns(3)
+                    .striped(true)
+                    .spacing([12.0, 4.0])
+                    .show(ui, |ui| {
+                        for (id, nombre, usuario) in &usuarios {
+                            ui.label(id);
+                            ui.label(nombre);
+                            ui.label(egui::RichText::new(usuario).monospace());
+                            ui.end_row();
+                        }
+                    });
+                ui.label(
+                    egui::RichText::new(
+                        obtener_directorio_exe().join(".env").display().to_string(),
+                    )
+                    .small()
+                    .monospace(),
+                );
+            });
     }
 
-    fn actualizar_estado(&self, nuevo_estado: EstadoApp) {
-        if let Ok(mut estado) = self.estado.lock() {
-            *estado = nuevo_estado;
+    /// Ventana para cargar usuarios y contraseñas de SADE: al guardar escribe el
+    /// `.env` junto al ejecutable y recarga la configuración
+    fn mostrar_editor_credenciales(&mut self, ctx: &egui::Context) {
+        let Some(editor) = self.editor_credenciales.as_mut() else {
+            return;
+        };
+        let mut abierto = true;
+        let mut guardar = false;
+        let mut cancelar = false;
+
+        egui::Window::new(t("editar_credenciales"))
+            .open(&mut abierto)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("editor_credenciales_grid")
+                    .num_columns(3)
+                    .spacing([12.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.strong(t("operador"));
+                        ui.strong(t("usuario_sade"));
+                        ui.strong(t("contrasena"));
+                        ui.end_row();
+                        for fila in &mut editor.filas {
+                            ui.label(fila.operador.nombre);
+                            ui.add(
+                                egui::TextEdit::singleline(&mut fila.usuario).desired_width(140.0),
+                            );
+                            ui.add(
+                                egui::TextEdit::singleline(&mut fila.password)
+                                    .password(!editor.mostrar_passwords)
+                                    .desired_width(140.0),
+                            );
+                            ui.end_row();
+                        }
+                    });
+                ui.checkbox(&mut editor.mostrar_passwords, t("mostrar_contrasenas"));
+                ui.label(
+                    egui::RichText::new(
+                        obtener_directorio_exe().join(".env").display().to_string(),
+                    )
+                    .small()
+                    .monospace(),
+                );
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    guardar = ui.button(t("guardar_credenciales")).clicked();
+                    cancelar = ui.button(t("cancelar_accion")).clicked();
+                });
+            });
+
+        if guardar {
+            let resultado = editor
+                .variables()
+                .map_err(anyhow::Error::msg)
+                .and_then(|v| {
+                    config::guardar_variables_env(&obtener_directorio_exe().join(".env"), &v)
+                });
+            match resultado {
+                Ok(()) => {
+                    self.editor_credenciales = None;
+                    // Conservar el directorio de trabajo elegido en esta sesión
+                    self.recargar_configuracion(true);
+                }
+                Err(e) => self.actualizar_estado(EstadoApp::Error(format!("{:#}", e))),
+            }
+        } else if cancelar || !abierto {
+            self.editor_credenciales = None;
         }
     }
 
-    fn habilitar_botones(&self, habilitado: bool) {
-        if let Ok(mut hab) = self.botones_habilitados.lock() {
-            *hab = habilitado;
-        }
+    /// Nombre y versión de la aplicación
+    fn mostrar_acerca_de(&mut self, ctx: &egui::Context) {
+        egui::Window::new(t("acerca_de"))
+            .open(&mut self.ver_acerca_de)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(egui::RichText::new("Auto SADE").heading());
+                    ui.label(format!("{} {}", t("version"), env!("CARGO_PKG_VERSION")));
+                    ui.add_space(6.0);
+                    ui.label(t("titulo"));
+                });
+            });
     }
 
-    fn botones_estan_habilitados(&self) -> bool {
-        self.botones_habilitados.lock().map(|h| *h).unwrap_or(true)
+    /// Confirmación antes de mover: avisa qué organismos se eliminan (o se
+    /// descartan) y solo lanza la operación si el operador confirma
+    fn mostrar_confirmacion_mover(&mut self, ctx: &egui::Context) {
+        let Some(operacion) = self.confirmar_mover else {
+            return;
+        };
+        let aviso = if self.descartar_eliminados {
+            t("confirmar_mover_descartar")
+        } else {
+            t("confirmar_mover_eliminar")
+        };
+
+        let mut confirmado = None;
+        egui::Window::new(t("confirmar_mover"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(aviso);
+                ui.label(
+                    egui::RichText::new(file_processor::ORGANISMOS_ELIMINAR.join(", "))
+                        .strong()
+                        .color(ui.visuals().warn_fg_color),
+                );
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui.button(t("confirmar")).clicked() {
+                        confirmado = Some(true);
+                    }
+                    if ui.button(t("cancelar_accion")).clicked() {
+                        confirmado = Some(false);
+                    }
+                });
+            });
+
+        if let Some(confirmado) = confirmado {
+            self.confirmar_mover = None;
+            if confirmado {
+                match operacion {
+                    Operacion::Todo => self.ejecutar_todo(),
+                    _ => self.mover_archivos(),
+                }
+            }
+        }
     }
 
     fn obtener_estado(&self) -> EstadoApp {
@@ -81,189 +1784,535 @@ impl AutoSadeApp {
 
 impl eframe::App for AutoSadeApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // Cerrar sin confirmar la revisión la descarta: los PDFs vuelven a su carpeta
+        if ctx.input(|i| i.viewport().close_requested()) && self.revision_pendiente() {
+            self.descartar_previsualizacion();
+        }
+
+        // Carpetas arrastradas a la ventana: procesarlas de forma puntual
+        let carpeta_soltada = ctx.input(|i| {
+            i.raw
+                .dropped_files
+                .iter()
+                .filter_map(|f| f.path.clone())
+                .find(|p| p.is_dir())
+        });
+        if let Some(carpeta) = carpeta_soltada {
+            if self.accion_habilitada(Operacion::Procesamiento) {
+                self.procesar_carpeta_adhoc(&carpeta);
+            }
+        }
+
+        self.mostrar_menu(ctx);
+        self.mostrar_credenciales(ctx);
+        self.mostrar_editor_credenciales(ctx);
+        self.mostrar_acerca_de(ctx);
+        self.mostrar_confirmacion_mover(ctx);
+        self.mostrar_previsualizacion(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| {
             // Obtener el ancho disponible para centrar contenido
             let panel_width = ui.available_width();
             let content_width = 340.0_f32.min(panel_width - 40.0);
 
-            ui.vertical_centered(|ui| {
-                ui.add_space(30.0);
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(10.0);
 
-                // Título con estilo
-                ui.label(
-                    egui::RichText::new("Manejo Comunicaciones Inventario")
-                        .heading()
-                        .size(22.0),
-                );
+                        // Título con estilo
+                        ui.label(
+                            egui::RichText::new(t("titulo"))
+                                .heading()
+                                .size(22.0),
+                        );
 
-                ui.add_space(25.0);
-
-                // Frame de inputs centrado
-                ui.allocate_ui_with_layout(
-                    egui::vec2(content_width, 0.0),
-                    egui::Layout::top_down(egui::Align::Center),
-                    |ui| {
-                        egui::Frame::default()
-                            .inner_margin(egui::Margin::same(20.0))
-                            .fill(ui.style().visuals.extreme_bg_color)
-                            .rounding(egui::Rounding::same(10.0))
-                            .stroke(egui::Stroke::new(
-                                1.0,
-                                ui.style().visuals.widgets.noninteractive.bg_stroke.color,
-                            ))
-                            .show(ui, |ui| {
-                                ui.set_width(content_width - 40.0);
+                        ui.add_space(15.0);
 
-                                egui::Grid::new("input_grid")
-                                    .num_columns(2)
-                                    .spacing([15.0, 12.0])
-                                    .show(ui, |ui| {
-                                        ui.label("Usuario (1=Erica, 2=Cecilia):");
-                                        let usuario_response = ui.add(
-                                            egui::TextEdit::singleline(&mut self.usuario)
-                                                .desired_width(80.0)
-                                                .horizontal_align(egui::Align::Center),
-                                        );
-                                        // Dar foco al campo de usuario cuando está vacío
-                                        if self.usuario.is_empty()
-                                            && self.comunicacion_inicio.is_empty()
+                        // Sin configuración no hay credenciales: explicar cómo resolverlo
+                        if self.config.is_none() {
+                            egui::Frame::default()
+                                .inner_margin(egui::Margin::same(12.0))
+                                .rounding(egui::Rounding::same(8.0))
+                                .stroke(egui::Stroke::new(1.5, ui.visuals().error_fg_color))
+                                .show(ui, |ui| {
+                                    ui.set_width(content_width - 24.0);
+                                    ui.label(
+                                        egui::RichText::new(t("falta_configuracion"))
+                                            .strong()
+                                            .color(ui.visuals().error_fg_color),
+                                    );
+                                    ui.label(t("falta_configuracion_detalle"));
+                                    let carpeta_env = obtener_directorio_exe();
+                                    ui.label(
+                                        egui::RichText::new(carpeta_env.display().to_string())
+                                            .small()
+                                            .monospace(),
+                                    );
+                                    ui.horizontal(|ui| {
+                                        if ui.button(t("cargar_credenciales")).clicked() {
+                                            self.editor_credenciales =
+                                                Some(EditorCredenciales::desde_entorno());
+                                        }
+                                        if ui.button(t("abrir_carpeta_env")).clicked() {
+                                            if let Err(e) = abrir_en_sistema(&carpeta_env) {
+                                                self.actualizar_estado(EstadoApp::Error(
+                                                    e.to_string(),
+                                                ));
+                                            }
+                                        }
+                                        if ui.button(t("recargar_configuracion")).clicked() {
+                                            self.recargar_configuracion(false);
+                                        }
+                                    });
+                                });
+                            ui.add_space(15.0);
+                        }
+
+                        let botones_habilitados =
+                            self.botones_estan_habilitados() && self.config.is_some();
+
+                        // Perfiles de trabajo guardados
+                        ui.add_enabled_ui(botones_habilitados, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label(t("perfil"));
+                                let mut seleccionado = self.perfil_activo.clone();
+                                egui::ComboBox::from_id_salt("perfil_trabajo")
+                                    .selected_text(if seleccionado.is_empty() {
+                                        t("ninguno")
+                                    } else {
+                                        seleccionado.as_str()
+                                    })
+                                    .show_ui(ui, |ui| {
+                                        for perfil in &self.perfiles {
+                                            ui.selectable_value(
+                                                &mut seleccionado,
+                                                perfil.nombre.clone(),
+                                                &perfil.nombre,
+                                            );
+                                        }
+                                    });
+                                if seleccionado != self.perfil_activo {
+                                    self.aplicar_perfil(&seleccionado);
+                                }
+
+                                if !self.perfil_activo.is_empty() && ui.button("🗑").clicked() {
+                                    let activo = self.perfil_activo.clone();
+                                    self.perfiles.retain(|p| p.nombre != activo);
+                                    self.perfil_activo.clear();
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.nombre_perfil_nuevo)
+                                        .hint_text(t("nombre_perfil"))
+                                        .desired_width(150.0),
+                                );
+                                let nombre = self.nombre_perfil_nuevo.trim().to_string();
+                                if ui
+                                    .add_enabled(
+                                        !nombre.is_empty(),
+                                        egui::Button::new(t("guardar_perfil")),
+                                    )
+                                    .clicked()
+                                {
+                                    self.guardar_perfil(&nombre);
+                                    self.nombre_perfil_nuevo.clear();
+                                }
+                            });
+                        });
+
+                        ui.add_space(10.0);
+
+                        // Directorio de trabajo (base de PDFs, Procesados, Revisar y Excel)
+                        if let Some(config) = &mut self.config {
+                            ui.label(egui::RichText::new(t("directorio_trabajo")).strong());
+                            ui.label(
+                                egui::RichText::new(
+                                    config.directorio_trabajo.display().to_string(),
+                                )
+                                .monospace(),
+                            );
+                            if ui
+                                .add_enabled(
+                                    botones_habilitados,
+                                    egui::Button::new(t("cambiar")),
+                                )
+                                .clicked()
+                            {
+                                elegir_directorio_trabajo(config);
+                            }
+
+                            ui.add_space(6.0);
+
+                            egui::Grid::new("rutas_grid")
+                                .num_columns(3)
+                                .spacing([8.0, 6.0])
+                                .show(ui, |ui| {
+                                    ui.label(t("pdfs"));
+                                    ui.label(
+                                        egui::RichText::new(
+                                            config.ruta_archivos.display().to_string(),
+                                        )
+                                        .small()
+                                        .monospace(),
+                                    );
+                                    if ui
+                                        .add_enabled(
+                                            botones_habilitados,
+                                            egui::Button::new(t("elegir")),
+                                        )
+                                        .on_hover_text(t("elegir_carpeta_pdfs"))
+                                        .clicked()
+                                    {
+                                        if let Some(dir) = rfd::FileDialog::new()
+                                            .set_title("Elegir carpeta de PDFs")
+                                            .set_directory(&config.ruta_archivos)
+                                            .pick_folder()
                                         {
-                                            usuario_response.request_focus();
+                                            config.establecer_ruta_archivos(dir);
+                                            registro::inicializar(&config.ruta_archivos);
                                         }
-                                        ui.end_row();
+                                    }
+                                    ui.end_row();
 
-                                        ui.label("Comunicación Inicial:");
-                                        ui.add(
-                                            egui::TextEdit::singleline(
-                                                &mut self.comunicacion_inicio,
-                                            )
-                                            .desired_width(80.0)
-                                            .horizontal_align(egui::Align::Center),
-                                        );
-                                        ui.end_row();
+                                    ui.label(t("excel"));
+                                    ui.label(
+                                        egui::RichText::new(
+                                            config.ruta_excel.display().to_string(),
+                                        )
+                                        .small()
+                                        .monospace(),
+                                    );
+                                    if ui
+                                        .add_enabled(
+                                            botones_habilitados,
+                                            egui::Button::new(t("elegir")),
+                                        )
+                                        .on_hover_text(t("elegir_excel_salida"))
+                                        .clicked()
+                                    {
+                                        let mut dialogo = rfd::FileDialog::new()
+                                            .add_filter("Archivos Excel", &["xlsx"])
+                                            .set_title("Elegir archivo Excel")
+                                            .set_file_name(NOMBRE_EXCEL);
+                                        if let Some(dir) = config.ruta_excel.parent() {
+                                            dialogo = dialogo.set_directory(dir);
+                                        }
+                                        if let Some(ruta) = dialogo.save_file() {
+                                            config.ruta_excel = ruta;
+                                        }
+                                    }
+                                    ui.end_row();
+                                });
+                        }
 
-                                        ui.label("Comunicación Final:");
-                                        ui.add(
-                                            egui::TextEdit::singleline(
-                                                &mut self.comunicacion_final,
-                                            )
-                                            .desired_width(80.0)
-                                            .horizontal_align(egui::Align::Center),
+                        ui.add_space(15.0);
+
+                        // Frame de inputs centrado
+                        ui.allocate_ui_with_layout(
+                            egui::vec2(content_width, 0.0),
+                            egui::Layout::top_down(egui::Align::Center),
+                            |ui| {
+                                egui::Frame::default()
+                                    .inner_margin(egui::Margin::same(20.0))
+                                    .fill(ui.style().visuals.extreme_bg_color)
+                                    .rounding(egui::Rounding::same(10.0))
+                                    .stroke(egui::Stroke::new(
+                                        1.0,
+                                        ui.style().visuals.widgets.noninteractive.bg_stroke.color,
+                                    ))
+                                    .show(ui, |ui| {
+                                        ui.set_width(content_width - 40.0);
+
+                                        egui::Grid::new("input_grid")
+                                            .num_columns(2)
+                                            .spacing([15.0, 12.0])
+                                            .show(ui, |ui| {
+                                                ui.label(t("usuario"));
+                                                let usuarios: Vec<(String, String)> = self
+                                                    .config
+                                                    .as_ref()
+                                                    .map(|c| {
+                                                        c.usuarios_ordenados()
+                                                            .into_iter()
+                                                            .map(|(id, cred)| {
+                                                                (id.clone(), cred.nombre.clone())
+                                                            })
+                                                            .collect()
+                                                    })
+                                                    .unwrap_or_default();
+
+                                                // Si el usuario guardado ya no existe, preseleccionar el primero
+                                                if !usuarios
+                                                    .iter()
+                                                    .any(|(id, _)| *id == self.usuario)
+                                                {
+                                                    self.usuario = usuarios
+                                                        .first()
+                                                        .map(|(id, _)| id.clone())
+                                                        .unwrap_or_default();
+                                                }
+
+                                                let nombre_actual = usuarios
+                                                    .iter()
+                                                    .find(|(id, _)| *id == self.usuario)
+                                                    .map(|(_, nombre)| nombre.as_str())
+                                                    .unwrap_or(t("sin_usuarios"));
+                                                egui::ComboBox::from_id_salt("usuario")
+                                                    .selected_text(nombre_actual)
+                                                    .show_ui(ui, |ui| {
+                                                        for (id, nombre) in &usuarios {
+                                                            ui.selectable_value(
+                                                                &mut self.usuario,
+                                                                id.clone(),
+                                                                nombre,
+                                                            );
+                                                        }
+                                                    });
+                                                ui.end_row();
+
+                                                ui.label(t("comunicacion_inicial"));
+                                                ui.add(
+                                                    egui::TextEdit::singleline(
+                                                        &mut self.comunicacion_inicio,
+                                                    )
+                                                    .desired_width(80.0)
+                                                    .horizontal_align(egui::Align::Center),
+                                                );
+                                                ui.end_row();
+
+                                                ui.label(t("comunicacion_final"));
+                                                ui.add(
+                                                    egui::TextEdit::singleline(
+                                                        &mut self.comunicacion_final,
+                                                    )
+                                                    .desired_width(80.0)
+                                                    .horizontal_align(egui::Align::Center),
+                                                );
+                                                ui.end_row();
+
+                                                ui.label(t("tiempo_maximo"));
+                                                ui.add(
+                                                    egui::TextEdit::singleline(
+                                                        &mut self.tiempo_maximo,
+                                                    )
+                                                    .hint_text(t("sin_limite"))
+                                                    .desired_width(80.0)
+                                                    .horizontal_align(egui::Align::Center),
+                                                )
+                                                .on_hover_text(t("tiempo_maximo_ayuda"));
+                                                ui.end_row();
+                                            });
+
+                                        egui::CollapsingHeader::new(t("lista_comunicaciones"))
+                                            .default_open(!self.lista_comunicaciones.is_empty())
+                                            .show(ui, |ui| {
+                                                ui.add(
+                                                    egui::TextEdit::multiline(
+                                                        &mut self.lista_comunicaciones,
+                                                    )
+                                                    .hint_text(t("lista_comunicaciones_ayuda"))
+                                                    .desired_rows(3)
+                                                    .desired_width(f32::INFINITY),
+                                                );
+                                            });
+
+                                        ui.add_space(8.0);
+                                        ui.checkbox(
+                                            &mut self.usar_ocr,
+                                            t("usar_ocr"),
+                                        );
+                                        ui.checkbox(
+                                            &mut self.incluir_subcarpetas,
+                                            t("incluir_subcarpetas"),
+                                        );
+                                        ui.checkbox(
+                                            &mut self.agrupar_por_organismo,
+                                            t("agrupar_por_organismo"),
+                                        );
+                                        ui.checkbox(
+                                            &mut self.descartar_eliminados,
+                                            t("guardar_descartados"),
                                         );
-                                        ui.end_row();
+                                        ui.horizontal(|ui| {
+                                            ui.label(t("si_existe"));
+                                            egui::ComboBox::from_id_salt("modo_colision")
+                                                .selected_text(self.modo_colision.descripcion())
+                                                .show_ui(ui, |ui| {
+                                                    for modo in ModoColision::TODOS {
+                                                        ui.selectable_value(
+                                                            &mut self.modo_colision,
+                                                            modo,
+                                                            modo.descripcion(),
+                                                        );
+                                                    }
+                                                });
+                                        });
+                                        ui.horizontal(|ui| {
+                                            ui.label(t("ordenar_excel"));
+                                            egui::ComboBox::from_id_salt("ordenar_por")
+                                                .selected_text(self.ordenar_por.descripcion())
+                                                .show_ui(ui, |ui| {
+                                                    for orden in OrdenarPor::TODOS {
+                                                        ui.selectable_value(
+                                                            &mut self.ordenar_por,
+                                                            orden,
+                                                            orden.descripcion(),
+                                                        );
+                                                    }
+                                                });
+                                        });
+                                        ui.checkbox(
+                                            &mut self.novedades_al_final,
+                                            t("novedades_al_final"),
+                                        );
+                                        ui.horizontal(|ui| {
+                                            ui.label(t("si_existe_excel"));
+                                            egui::ComboBox::from_id_salt("modo_guardado")
+                                                .selected_text(self.modo_guardado.descripcion())
+                                                .show_ui(ui, |ui| {
+                                                    for modo in ModoGuardado::TODOS {
+                                                        // Sobrescribir se elige una sola vez,
+                                                        // tenga o no respaldo
+                                                        let elegido = std::mem::discriminant(
+                                                            &self.modo_guardado,
+                                                        ) == std::mem::discriminant(&modo);
+                                                        if ui
+                                                            .selectable_label(
+                                                                elegido,
+                                                                modo.descripcion(),
+                                                            )
+                                                            .clicked()
+                                                            && !elegido
+                                                        {
+                                                            self.modo_guardado = modo;
+                                                        }
+                                                    }
+                                                });
+                                        });
+                                        if let ModoGuardado::Sobrescribir { respaldo } =
+                                            &mut self.modo_guardado
+                                        {
+                                            ui.checkbox(respaldo, t("respaldo_excel"));
+                                        }
                                     });
-                            });
-                    },
-                );
+                            },
+                        );
 
-                ui.add_space(25.0);
+                        ui.add_space(25.0);
 
-                // Botones con ancho uniforme
-                let button_width = 180.0;
-                let botones_habilitados = self.botones_estan_habilitados();
+                        // Botones con ancho uniforme
+                        let button_width = 180.0;
+                        let boton = |ui: &mut egui::Ui, habilitado: bool, texto: &str| {
+                            ui.add_enabled_ui(habilitado, |ui| {
+                                ui.add_sized([button_width, 32.0], egui::Button::new(texto))
+                            })
+                            .inner
+                        };
 
-                ui.add_enabled_ui(botones_habilitados, |ui| {
-                    if ui
-                        .add_sized([button_width, 32.0], egui::Button::new("⬇  Descargar"))
+                        // Cada acción se habilita según lo que esté corriendo
+                        let hay_config = self.config.is_some();
+                        let puede_todo = hay_config && self.accion_habilitada(Operacion::Todo);
+                        let puede_descargar = hay_config && self.accion_habilitada(Operacion::Descarga);
+                        let puede_buscar = hay_config && self.accion_habilitada(Operacion::Busqueda);
+                        let puede_mover = hay_config && self.accion_habilitada(Operacion::Mover);
+                        let puede_procesar = hay_config && self.accion_habilitada(Operacion::Procesamiento);
+
+                    if boton(ui, puede_todo, t("ejecutar_todo"))
+                        .on_hover_text(t("ejecutar_todo_ayuda"))
                         .clicked()
                     {
-                        self.habilitar_botones(false);
-                        self.actualizar_estado(EstadoApp::Procesando(
-                            "Descargando comunicaciones...".to_string(),
-                        ));
-
-                        let inicio: u32 = self.comunicacion_inicio.parse().unwrap_or(1);
-                        let final_: u32 = self.comunicacion_final.parse().unwrap_or(1);
-                        let usuario = self.usuario.clone();
-                        let config = self.config.clone();
-                        let estado = Arc::clone(&self.estado);
-                        let botones = Arc::clone(&self.botones_habilitados);
-
-                        std::thread::spawn(move || {
-                            let rt = tokio::runtime::Runtime::new().unwrap();
-                            let resultado = rt.block_on(async {
-                                if let Some(cfg) = config {
-                                    web_automation::descargar_comunicaciones(
-                                        inicio,
-                                        final_,
-                                        &usuario,
-                                        &cfg,
-                                        |msg| {
-                                            if let Ok(mut e) = estado.lock() {
-                                                *e = EstadoApp::Procesando(msg.to_string());
-                                            }
-                                        },
-                                    )
-                                    .await
-                                } else {
-                                    Err(anyhow::anyhow!("Configuración no disponible"))
-                                }
-                            });
+                        // La cadena completa también mueve archivos: se confirma antes
+                        self.confirmar_mover = Some(Operacion::Todo);
+                    }
 
-                            if let Ok(mut e) = estado.lock() {
-                                *e = match resultado {
-                                    Ok(r) => EstadoApp::Finalizado(format!(
-                                        "{} de {} comunicaciones procesadas",
-                                        r.comunicaciones_procesadas, r.total_comunicaciones
-                                    )),
-                                    Err(e) => EstadoApp::Error(e.to_string()),
-                                };
-                            }
+                    ui.add_space(8.0);
 
-                            if let Ok(mut b) = botones.lock() {
-                                *b = true;
-                            }
-                        });
+                    if boton(ui, puede_descargar, t("descargar"))
+                        .clicked()
+                    {
+                        self.descargar();
                     }
 
                     ui.add_space(8.0);
 
-                    if ui
-                        .add_sized([button_width, 32.0], egui::Button::new("🔍  Busquedas"))
+                    if boton(ui, puede_buscar, t("buscar_sin_organismo"))
                         .clicked()
                     {
-                        // Abrir diálogo de selección de archivo
+                        // Abrir diálogo de selección del Excel de referencia
+                        let directorio = self
+                            .config
+                            .as_ref()
+                            .map(|c| c.directorio_trabajo.clone())
+                            .unwrap_or_default();
                         if let Some(path) = rfd::FileDialog::new()
                             .add_filter("Archivos Excel", &["xlsx", "xls"])
-                            .set_title("Seleccionar archivo Excel")
+                            .set_title("Seleccionar Excel de referencia")
+                            .set_directory(&directorio)
                             .pick_file()
                         {
-                            self.habilitar_botones(false);
+                            self.iniciar_operacion(Operacion::Busqueda);
                             self.actualizar_estado(EstadoApp::Procesando(
                                 "Leyendo archivo Excel...".to_string(),
                             ));
 
                             // Leer comunicaciones del Excel
+                            let hoja = self.config.as_ref().and_then(|c| c.hoja_busqueda.clone());
                             match busqueda_comunicaciones::obtener_comunicaciones_sin_organismo(
                                 &path,
+                                hoja.as_deref(),
                             ) {
-                                Ok(comunicaciones) => {
-                                    if comunicaciones.is_empty() {
-                                        self.actualizar_estado(EstadoApp::Finalizado(
-                                            "No hay comunicaciones sin organismo".to_string(),
-                                        ));
-                                        self.habilitar_botones(true);
+                                Ok(lectura) => {
+                                    let pendientes = lectura.pendientes();
+                                    if pendientes != Pendientes::Buscar {
+                                        let mensaje = pendientes.to_string();
+                                        self.actualizar_estado(match pendientes {
+                                            Pendientes::ExcelSinComunicaciones => {
+                                                EstadoApp::Error(mensaje)
+                                            }
+                                            _ => EstadoApp::Finalizado(mensaje),
+                                        });
+                                        terminar_operacion(&self.operaciones, Operacion::Busqueda);
                                     } else {
+                                        let comunicaciones = lectura.comunicaciones;
+                                        self.actualizar_estado(EstadoApp::Procesando(format!(
+                                            "{} comunicaciones sin organismo, iniciando búsqueda...",
+                                            comunicaciones.len()
+                                        )));
+
                                         let usuario = self.usuario.clone();
                                         let config = self.config.clone();
                                         let estado = Arc::clone(&self.estado);
-                                        let botones = Arc::clone(&self.botones_habilitados);
-                                        let total = comunicaciones.len();
+                                        let log = Arc::clone(&self.log);
+                                        let progreso = Arc::clone(&self.progreso);
+                                        let cancelar = self.iniciar_cancelable();
+                                        let runtime = Arc::clone(&self.runtime);
+                                        let operaciones = Arc::clone(&self.operaciones);
 
                                         std::thread::spawn(move || {
-                                            let rt = tokio::runtime::Runtime::new().unwrap();
-                                            let resultado = rt.block_on(async {
+                                            let resultado = runtime.block_on(async {
                                                 if let Some(cfg) = config {
                                                     busqueda_comunicaciones::buscar_comunicaciones(
                                                         &comunicaciones,
                                                         &usuario,
                                                         &cfg,
+                                                        &cancelar,
                                                         |msg| {
-                                                            if let Ok(mut e) = estado.lock() {
-                                                                *e = EstadoApp::Procesando(
+                                                            establecer_estado(
+                                                                &estado,
+                                                                &log,
+                                                                EstadoApp::Procesando(
                                                                     msg.to_string(),
-                                                                );
-                                                            }
+                                                                ),
+                                                            );
+                                                        },
+                                                        |actual, total| {
+                                                            actualizar_progreso(
+                                                                &progreso, actual, total, true,
+                                                            )
                                                         },
                                                     )
                                                     .await
@@ -274,19 +2323,21 @@ impl eframe::App for AutoSadeApp {
                                                 }
                                             });
 
-                                            if let Ok(mut e) = estado.lock() {
-                                                *e = match resultado {
-                                                    Ok(r) => EstadoApp::Finalizado(format!(
-                                                        "{} de {} comunicaciones descargadas",
-                                                        r.comunicaciones_descargadas, total
-                                                    )),
-                                                    Err(e) => EstadoApp::Error(e.to_string()),
-                                                };
-                                            }
+                                            let final_estado = match resultado {
+                                                Ok(r) => EstadoApp::Finalizado(format!(
+                                                    "{}{} de {} comunicaciones descargadas",
+                                                    if r.cancelada { "Cancelado: " } else { "" },
+                                                    r.comunicaciones_descargadas,
+                                                    r.total_comunicaciones
+                                                )),
+                                                Err(e) => EstadoApp::Error(e.to_string()),
+                                            };
+                                            establecer_estado(&estado, &log, final_estado);
 
-                                            if let Ok(mut b) = botones.lock() {
-                                                *b = true;
+                                            if let Ok(mut p) = progreso.lock() {
+                                                *p = None;
                                             }
+                                            terminar_operacion(&operaciones, Operacion::Busqueda);
                                         });
                                     }
                                 }
@@ -295,7 +2346,7 @@ impl eframe::App for AutoSadeApp {
                                         "Error leyendo Excel: {}",
                                         e
                                     )));
-                                    self.habilitar_botones(true);
+                                    terminar_operacion(&self.operaciones, Operacion::Busqueda);
                                 }
                             }
                         }
@@ -303,102 +2354,278 @@ impl eframe::App for AutoSadeApp {
 
                     ui.add_space(8.0);
 
-                    if ui
-                        .add_sized(
-                            [button_width, 32.0],
-                            egui::Button::new("📁  Mover archivos"),
-                        )
+                    if boton(ui, puede_mover, t("mover_archivos"))
                         .clicked()
                     {
-                        self.habilitar_botones(false);
-                        self.actualizar_estado(EstadoApp::Procesando(
-                            "Moviendo archivos...".to_string(),
-                        ));
+                        self.confirmar_mover = Some(Operacion::Mover);
+                    }
+
+                    ui.add_space(8.0);
 
+                    if boton(ui, puede_procesar, t("procesar_archivos"))
+                        .clicked()
+                    {
                         if let Some(config) = &self.config {
-                            let ruta_descarga =
-                                file_processor::obtener_ruta_descargas().unwrap_or_default();
-                            let ruta_destino = config.ruta_archivos.clone();
-
-                            match file_processor::mover_archivos(&ruta_descarga, &ruta_destino) {
-                                Ok(resultado) => {
-                                    let neto = resultado
-                                        .archivos_movidos
-                                        .saturating_sub(resultado.archivos_eliminados);
+                            self.procesar_carpeta(
+                                config.ruta_archivos.clone(),
+                                config.ruta_excel.clone(),
+                                "Procesando PDFs...".to_string(),
+                            );
+                        }
+                    }
+
+                    ui.add_space(8.0);
+
+                    if boton(ui, puede_procesar, t("procesar_otra_carpeta"))
+                        .clicked()
+                    {
+                        if let Some(carpeta) = rfd::FileDialog::new()
+                            .set_title("Seleccionar carpeta de PDFs a procesar")
+                            .pick_folder()
+                        {
+                            self.procesar_carpeta_adhoc(&carpeta);
+                        }
+                    }
+
+                    ui.add_space(8.0);
+
+                    if boton(ui, puede_procesar, t("consolidar_excels"))
+                        .clicked()
+                    {
+                        // Elegir los Excels a consolidar
+                        let directorio = self
+                            .config
+                            .as_ref()
+                            .map(|c| c.ruta_archivos.clone())
+                            .unwrap_or_default();
+
+                        if let Some(rutas) = rfd::FileDialog::new()
+                            .add_filter("Archivos Excel", &["xlsx"])
+                            .set_title("Seleccionar Excels a consolidar")
+                            .set_directory(&directorio)
+                            .pick_files()
+                        {
+                            let ruta_salida = directorio.join("Consolidado.xlsx");
+
+                            match excel_handler::consolidar_excels(
+                                &rutas,
+                                &ruta_salida,
+                                &self.columnas_excel(),
+                            ) {
+                                Ok(r) => {
+                                    self.establecer_resultado(Some(ResultadoGenerado {
+                                        carpeta: directorio.clone(),
+                                        excel: r.ruta_salida.clone(),
+                                    }));
                                     self.actualizar_estado(EstadoApp::Finalizado(format!(
-                                        "{} movidos, {} eliminados",
-                                        neto, resultado.archivos_eliminados
+                                        "{} comunicaciones de {} archivos consolidadas en {}",
+                                        r.filas,
+                                        r.archivos_leidos,
+                                        r.ruta_salida.display()
                                     )));
                                 }
                                 Err(e) => {
-                                    self.actualizar_estado(EstadoApp::Error(e.to_string()));
+                                    self.actualizar_estado(EstadoApp::Error(format!(
+                                        "Error al consolidar: {}",
+                                        e
+                                    )));
                                 }
                             }
                         }
-
-                        self.habilitar_botones(true);
                     }
 
-                    ui.add_space(8.0);
+                        ui.add_space(6.0);
+                        ui.label(
+                            egui::RichText::new(
+                                t("arrastrar_carpeta"),
+                            )
+                            .small()
+                            .color(egui::Color32::GRAY),
+                        );
 
-                    if ui
-                        .add_sized(
-                            [button_width, 32.0],
-                            egui::Button::new("⚙  Procesar archivos"),
-                        )
-                        .clicked()
-                    {
-                        self.habilitar_botones(false);
-                        self.actualizar_estado(EstadoApp::Procesando(
-                            "Procesando PDFs...".to_string(),
-                        ));
+                        ui.add_space(14.0);
 
-                        if let Some(config) = &self.config {
-                            match pdf_extractor::procesar_pdfs(&config.ruta_archivos) {
-                                Ok(datos) => {
-                                    match excel_handler::guardar_excel(&datos, &config.ruta_excel) {
-                                        Ok(_) => {
-                                            self.actualizar_estado(EstadoApp::Finalizado(format!(
-                                                "{} archivos procesados",
-                                                datos.len()
-                                            )));
-                                        }
-                                        Err(e) => {
-                                            self.actualizar_estado(EstadoApp::Error(format!(
-                                                "Error al guardar Excel: {}",
-                                                e
-                                            )));
+                        // Estado
+                        let estado = self.obtener_estado();
+                        mostrar_estado(ui, &estado);
+
+                        // Accesos a lo generado por la última operación terminada
+                        if matches!(estado, EstadoApp::Finalizado(_)) {
+                            let generado =
+                                self.resultado_generado.lock().ok().and_then(|r| r.clone());
+                            if let Some(generado) = generado {
+                                ui.horizontal(|ui| {
+                                    let mut abrir = None;
+                                    if ui.button(t("abrir_carpeta")).clicked() {
+                                        abrir = Some(generado.carpeta.clone());
+                                    }
+                                    if ui.button(t("abrir_excel")).clicked() {
+                                        abrir = Some(generado.excel.clone());
+                                    }
+                                    if let Some(ruta) = abrir {
+                                        if let Err(e) = abrir_en_sistema(&ruta) {
+                                            self.actualizar_estado(EstadoApp::Error(e.to_string()));
                                         }
                                     }
-                                }
-                                Err(e) => {
-                                    self.actualizar_estado(EstadoApp::Error(e.to_string()));
-                                }
+                                });
                             }
                         }
 
-                        self.habilitar_botones(true);
-                    }
-                });
+                        // Tiempo restante estimado de la operación en curso
+                        if matches!(estado, EstadoApp::Procesando(_)) {
+                            let progreso = self.progreso.lock().ok().and_then(|p| *p);
+                            if let Some(p) = progreso {
+                                mostrar_progreso(ui, &p);
+                                if let Some(conteo) = &mut self.conteo_descargas {
+                                    conteo.actualizar();
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "📥 {} {}",
+                                            conteo.descargados,
+                                            t("pdfs_descargados")
+                                        ))
+                                        .small(),
+                                    );
+                                }
+
+                                let (descarga_en_curso, pausable) = self
+                                    .operaciones
+                                    .lock()
+                                    .map(|o| {
+                                        let activa = |op| o.activas.contains(&op);
+                                        (
+                                            activa(Operacion::Descarga)
+                                                || activa(Operacion::Busqueda),
+                                            activa(Operacion::Descarga)
+                                                || (activa(Operacion::Todo) && p.cancelable),
+                                        )
+                                    })
+                                    .unwrap_or((false, false));
+                                let cancelando = self.cancelar.load(Ordering::Relaxed);
+                                ui.horizontal(|ui| {
+                                    // Solo la descarga de comunicaciones admite pausa
+                                    if pausable && !cancelando {
+                                        let pausado = self.pausado.load(Ordering::Relaxed);
+                                        let texto = if pausado { t("reanudar") } else { t("pausar") };
+                                        if ui.button(texto).clicked() {
+                                            self.pausado.store(!pausado, Ordering::Relaxed);
+                                            self.actualizar_estado(EstadoApp::Procesando(
+                                                if pausado {
+                                                    "Reanudando la descarga..."
+                                                } else {
+                                                    "Pausando, se termina la comunicación en curso..."
+                                                }
+                                                .to_string(),
+                                            ));
+                                        }
+                                    }
+                                    if (p.cancelable || descarga_en_curso)
+                                        && ui
+                                            .add_enabled(
+                                                !cancelando,
+                                                egui::Button::new(t("cancelar")),
+                                            )
+                                            .clicked()
+                                    {
+                                        self.cancelar.store(true, Ordering::Relaxed);
+                                        self.actualizar_estado(EstadoApp::Procesando(
+                                            "Cancelando, se termina la comunicación en curso..."
+                                                .to_string(),
+                                        ));
+                                    }
+                                });
+                            }
+                        }
 
-                ui.add_space(20.0);
+                        // Procesamiento de PDFs, que puede correr a la par de una descarga
+                        let estado_procesamiento = self
+                            .estado_procesamiento
+                            .lock()
+                            .map(|e| e.clone())
+                            .unwrap_or(EstadoApp::Listo);
+                        if estado_procesamiento != EstadoApp::Listo {
+                            ui.add_space(6.0);
+                            mostrar_estado(ui, &estado_procesamiento);
+                            let progreso =
+                                self.progreso_procesamiento.lock().ok().and_then(|p| *p);
+                            if let (EstadoApp::Procesando(_), Some(p)) =
+                                (&estado_procesamiento, progreso)
+                            {
+                                mostrar_progreso(ui, &p);
+                            }
+                        }
 
-                // Estado
-                let estado = self.obtener_estado();
-                let (texto, color) = match estado {
-                    EstadoApp::Listo => ("Listo", egui::Color32::GRAY),
-                    EstadoApp::Procesando(ref msg) => (msg.as_str(), egui::Color32::YELLOW),
-                    EstadoApp::Finalizado(ref msg) => (msg.as_str(), egui::Color32::GREEN),
-                    EstadoApp::Error(ref msg) => (msg.as_str(), egui::Color32::RED),
-                };
+                        ui.add_space(10.0);
 
-                ui.label(egui::RichText::new(texto).color(color));
-            });
+                        // Historial de mensajes de la sesión
+                        egui::CollapsingHeader::new(t("registro"))
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                let Ok(mut lineas) = self.log.lock() else {
+                                    return;
+                                };
+                                egui::ScrollArea::vertical()
+                                    .max_height(140.0)
+                                    .auto_shrink([false, true])
+                                    .stick_to_bottom(true)
+                                    .show(ui, |ui| {
+                                        for linea in lineas.iter() {
+                                            ui.label(
+                                                egui::RichText::new(linea).small().monospace(),
+                                            );
+                                        }
+                                    });
+                                if !lineas.is_empty() && ui.small_button(t("limpiar")).clicked() {
+                                    lineas.clear();
+                                }
+                            });
+                    });
+                });
         });
 
         // Solicitar repintado continuo mientras está procesando
+        let procesando_pdfs = self
+            .estado_procesamiento
+            .lock()
+            .is_ok_and(|e| matches!(*e, EstadoApp::Procesando(_)));
         if matches!(self.obtener_estado(), EstadoApp::Procesando(_)) {
             ctx.request_repaint();
+        } else {
+            self.conteo_descargas = None;
+            if procesando_pdfs {
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let entradas = EntradasFormulario {
+            usuario: self.usuario.clone(),
+            comunicacion_inicio: self.comunicacion_inicio.clone(),
+            comunicacion_final: self.comunicacion_final.clone(),
+            usar_ocr: self.usar_ocr,
+            descartar_eliminados: self.descartar_eliminados,
+            modo_colision: self.modo_colision,
+            incluir_subcarpetas: self.incluir_subcarpetas,
+            agrupar_por_organismo: self.agrupar_por_organismo,
+            ordenar_por: self.ordenar_por,
+            novedades_al_final: self.novedades_al_final,
+            modo_guardado: self.modo_guardado,
+            tiempo_maximo: self.tiempo_maximo.clone(),
+        };
+        eframe::set_value(storage, CLAVE_FORMULARIO, &entradas);
+        eframe::set_value(storage, CLAVE_PERFILES, &self.perfiles);
+        eframe::set_value(storage, CLAVE_PERFIL_ACTIVO, &self.perfil_activo);
+        eframe::set_value(storage, CLAVE_IDIOMA, &i18n::idioma_actual());
+        eframe::set_value(storage, CLAVE_TEMA_OSCURO, &self.tema_oscuro);
+        if let Some(config) = &self.config {
+            eframe::set_value(
+                storage,
+                CLAVE_DIRECTORIO_TRABAJO,
+                &config.directorio_trabajo,
+            );
+            eframe::set_value(storage, CLAVE_RUTA_ARCHIVOS, &config.ruta_archivos);
+            eframe::set_value(storage, CLAVE_RUTA_EXCEL, &config.ruta_excel);
         }
     }
 }
@@ -426,8 +2653,8 @@ fn cargar_icono() -> Option<egui::IconData> {
 /// Ejecuta la aplicación GUI
 pub fn run() -> eframe::Result<()> {
     let mut viewport = egui::ViewportBuilder::default()
-        .with_inner_size([400.0, 450.0])
-        .with_min_inner_size([350.0, 400.0]);
+        .with_inner_size([440.0, 800.0])
+        .with_min_inner_size([380.0, 640.0]);
 
     // Cargar icono si está disponible
     if let Some(icon) = cargar_icono() {
@@ -443,6 +2670,50 @@ pub fn run() -> eframe::Result<()> {
     eframe::run_native(
         "Auto-Sade",
         options,
-        Box::new(|cc| Ok(Box::new(AutoSadeApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(AutoSadeApp::new(cc)?))),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn en_curso(activas: &[Operacion]) -> OperacionesEnCurso {
+        OperacionesEnCurso {
+            activas: activas.to_vec(),
+        }
+    }
+
+    #[test]
+    fn sin_operaciones_todo_esta_permitido() {
+        let ops = en_curso(&[]);
+        for op in [
+            Operacion::Descarga,
+            Operacion::Busqueda,
+            Operacion::Mover,
+            Operacion::Procesamiento,
+            Operacion::Todo,
+        ] {
+            assert!(ops.permite(op), "{:?}", op);
+        }
+    }
+
+    #[test]
+    fn permite_solo_operaciones_que_no_comparten_recursos() {
+        let descargando = en_curso(&[Operacion::Descarga]);
+        assert!(descargando.permite(Operacion::Procesamiento));
+        assert!(!descargando.permite(Operacion::Busqueda));
+        assert!(!descargando.permite(Operacion::Mover));
+        assert!(!descargando.permite(Operacion::Todo));
+
+        let procesando = en_curso(&[Operacion::Procesamiento]);
+        assert!(procesando.permite(Operacion::Descarga));
+        assert!(procesando.permite(Operacion::Busqueda));
+        assert!(!procesando.permite(Operacion::Mover));
+        assert!(!procesando.permite(Operacion::Procesamiento));
+
+        let todo = en_curso(&[Operacion::Todo]);
+        assert!(!todo.permite(Operacion::Procesamiento));
+        assert!(!todo.permite(Operacion::Descarga));
+    }
+}
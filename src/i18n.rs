@@ -0,0 +1,196 @@
+//! Textos de la interfaz en los idiomas disponibles
+//!
+//! Las cadenas se buscan por clave con `t`. Si una clave no tiene traducción
+//! se devuelve la clave misma, para que el faltante se note en pantalla.
+//! Los mensajes de estado generados por los módulos siguen en español.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Idioma de la interfaz
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Idioma {
+    #[default]
+    Espanol,
+    Ingles,
+}
+
+impl Idioma {
+    pub const TODOS: [Idioma; 2] = [Idioma::Espanol, Idioma::Ingles];
+
+    /// Nombre del idioma en su propia lengua, para el selector
+    pub fn nombre(&self) -> &'static str {
+        match self {
+            Idioma::Espanol => "Español",
+            Idioma::Ingles => "English",
+        }
+    }
+}
+
+/// Idioma seleccionado (índice en `Idioma::TODOS`)
+static IDIOMA_ACTUAL: AtomicU8 = AtomicU8::new(0);
+
+/// Cambia el idioma de la interfaz
+pub fn establecer_idioma(idioma: Idioma) {
+    let indice = Idioma::TODOS.iter().position(|i| *i == idioma).unwrap_or(0);
+    IDIOMA_ACTUAL.store(indice as u8, Ordering::Relaxed);
+}
+
+/// Idioma actual de la interfaz
+pub fn idioma_actual() -> Idioma {
+    Idioma::TODOS
+        .get(IDIOMA_ACTUAL.load(Ordering::Relaxed) as usize)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Textos (español, inglés) de cada clave
+fn textos(clave: &str) -> Option<(&'static str, &'static str)> {
+    let par = match clave {
+        "titulo" => (
+            "Manejo Comunicaciones Inventario",
+            "Inventory Communications",
+        ),
+        "idioma" => ("Idioma", "Language"),
+        "menu_archivo" => ("Archivo", "File"),
+        "menu_configuracion" => ("Configuración", "Settings"),
+        "menu_ayuda" => ("Ayuda", "Help"),
+        "elegir_directorio_trabajo" => (
+            "📁  Elegir directorio de trabajo...",
+            "📁  Choose working folder...",
+        ),
+        "salir" => ("Salir", "Quit"),
+        "tema_oscuro" => ("Tema oscuro", "Dark theme"),
+        "credenciales_cargadas" => ("Credenciales cargadas", "Loaded credentials"),
+        "sin_credenciales" => ("No hay usuarios configurados", "No users configured"),
+        "editar_credenciales" => ("Editar credenciales...", "Edit credentials..."),
+        "operador" => ("Operador", "Operator"),
+        "usuario_sade" => ("Usuario de SADE", "SADE user"),
+        "contrasena" => ("Contraseña", "Password"),
+        "mostrar_contrasenas" => ("Mostrar contraseñas", "Show passwords"),
+        "guardar_credenciales" => ("💾  Guardar", "💾  Save"),
+        "recargar_configuracion" => ("🔄  Recargar configuración", "🔄  Reload settings"),
+        "acerca_de" => ("Acerca de", "About"),
+        "version" => ("Versión", "Version"),
+        "cambiar_tema" => (
+            "Cambiar entre tema claro y oscuro",
+            "Switch light/dark theme",
+        ),
+        "falta_configuracion" => ("Falta la configuración", "Configuration missing"),
+        "falta_configuracion_detalle" => (
+            "Cargue las credenciales de SADE o cree un archivo .env con SADE_USER_ERICA y \
+             SADE_PASSWORD_ERICA (o SADE_USER_CECILIA y SADE_PASSWORD_CECILIA) en esta carpeta:",
+            "Enter the SADE credentials or create a .env file with SADE_USER_ERICA and \
+             SADE_PASSWORD_ERICA (or SADE_USER_CECILIA and SADE_PASSWORD_CECILIA) in this folder:",
+        ),
+        "abrir_carpeta_env" => ("📂  Abrir carpeta", "📂  Open folder"),
+        "cargar_credenciales" => ("🔑  Cargar credenciales", "🔑  Enter credentials"),
+        "perfil" => ("Perfil:", "Profile:"),
+        "ninguno" => ("(ninguno)", "(none)"),
+        "nombre_perfil" => ("Nombre del perfil", "Profile name"),
+        "guardar_perfil" => ("💾  Guardar perfil", "💾  Save profile"),
+        "directorio_trabajo" => ("Directorio de trabajo:", "Working directory:"),
+        "cambiar" => ("📂  Cambiar...", "📂  Change..."),
+        "pdfs" => ("PDFs:", "PDFs:"),
+        "excel" => ("Excel:", "Excel:"),
+        "elegir" => ("Elegir...", "Choose..."),
+        "elegir_carpeta_pdfs" => ("Elegir carpeta de PDFs", "Choose PDF folder"),
+        "elegir_excel_salida" => ("Elegir archivo Excel de salida", "Choose output Excel file"),
+        "usuario" => ("Usuario:", "User:"),
+        "sin_usuarios" => ("(sin usuarios)", "(no users)"),
+        "comunicacion_inicial" => ("Comunicación Inicial:", "First communication:"),
+        "comunicacion_final" => ("Comunicación Final:", "Last communication:"),
+        "tiempo_maximo" => ("Tiempo máximo (min):", "Time limit (min):"),
+        "sin_limite" => ("sin límite", "no limit"),
+        "tiempo_maximo_ayuda" => (
+            "Al excederlo se corta la descarga, se cierra el navegador y se borra el perfil",
+            "When exceeded the download stops, the browser is closed and the profile deleted",
+        ),
+        "lista_comunicaciones" => ("Lista de comunicaciones", "Communication list"),
+        "lista_comunicaciones_ayuda" => (
+            "Números o rangos (ej. 12, 15, 20-25). Si se completa, reemplaza al rango",
+            "Numbers or ranges (e.g. 12, 15, 20-25). If filled, replaces the range",
+        ),
+        "usar_ocr" => ("Usar OCR en PDFs escaneados", "Use OCR on scanned PDFs"),
+        "incluir_subcarpetas" => ("Incluir subcarpetas al procesar", "Include subfolders"),
+        "agrupar_por_organismo" => (
+            "Agrupar Procesados por organismo",
+            "Group processed files by agency",
+        ),
+        "guardar_descartados" => (
+            "Guardar eliminados en Descartados",
+            "Keep removed files in Descartados",
+        ),
+        "si_existe" => ("Si el archivo ya existe:", "If the file already exists:"),
+        "ordenar_excel" => ("Ordenar Excel por:", "Sort Excel by:"),
+        "si_existe_excel" => ("Si el Excel ya existe:", "If the Excel already exists:"),
+        "respaldo_excel" => (
+            "Guardar una copia del Excel anterior",
+            "Keep a copy of the previous Excel",
+        ),
+        "novedades_al_final" => (
+            "Filas con novedades al final",
+            "Rows with changes at the end",
+        ),
+        "ejecutar_todo" => ("▶  Ejecutar todo", "▶  Run everything"),
+        "ejecutar_todo_ayuda" => (
+            "Descargar, mover, procesar y generar el Excel",
+            "Download, move, process and build the Excel",
+        ),
+        "descargar" => ("⬇  Descargar", "⬇  Download"),
+        "buscar_sin_organismo" => ("🔍  Buscar sin organismo", "🔍  Search missing agency"),
+        "mover_archivos" => ("📁  Mover archivos", "📁  Move files"),
+        "confirmar_mover" => ("Confirmar mover archivos", "Confirm moving files"),
+        "confirmar_mover_eliminar" => (
+            "¿Confirma mover y eliminar archivos? Se eliminarán los de los organismos:",
+            "Move and delete files? Files from these agencies will be deleted:",
+        ),
+        "confirmar_mover_descartar" => (
+            "¿Confirma mover archivos? Se guardarán en Descartados los de los organismos:",
+            "Move files? Files from these agencies will be kept in Descartados:",
+        ),
+        "confirmar" => ("✔  Confirmar", "✔  Confirm"),
+        "cancelar_accion" => ("Cancelar", "Cancel"),
+        "procesar_archivos" => ("⚙  Procesar archivos", "⚙  Process files"),
+        "procesar_otra_carpeta" => ("📂  Procesar otra carpeta", "📂  Process another folder"),
+        "consolidar_excels" => ("📑  Consolidar Excels", "📑  Merge Excels"),
+        "arrastrar_carpeta" => (
+            "También puede arrastrar una carpeta para procesarla",
+            "You can also drop a folder here to process it",
+        ),
+        "listo" => ("Listo", "Ready"),
+        "abrir_carpeta" => ("📂  Abrir carpeta", "📂  Open folder"),
+        "abrir_excel" => ("📊  Abrir Excel", "📊  Open Excel"),
+        "previsualizacion" => ("Revisar datos extraídos", "Review extracted data"),
+        "archivos_a_guardar" => (
+            "archivos procesados. Revise los datos antes de guardar el Excel.",
+            "files processed. Review the data before saving the Excel.",
+        ),
+        "guardar_excel" => ("💾  Guardar Excel", "💾  Save Excel"),
+        "guardar_por_organismo" => ("🗂  Guardar por organismo", "🗂  Save by agency"),
+        "guardar_con_novedades" => (
+            "⚠  Guardar solo con novedades",
+            "⚠  Save only rows with changes",
+        ),
+        "exportar_json" => ("{ }  Exportar JSON", "{ }  Export JSON"),
+        "descartar" => ("✖  Descartar", "✖  Discard"),
+        "pdfs_descargados" => ("PDFs descargados", "PDFs downloaded"),
+        "calculando_eta" => ("calculando tiempo restante...", "estimating time left..."),
+        "cancelar" => ("⏹  Cancelar", "⏹  Cancel"),
+        "pausar" => ("⏸  Pausar", "⏸  Pause"),
+        "reanudar" => ("▶  Reanudar", "▶  Resume"),
+        "registro" => ("Registro", "Log"),
+        "limpiar" => ("Limpiar", "Clear"),
+        _ => return None,
+    };
+    Some(par)
+}
+
+/// Devuelve el texto de la clave en el idioma actual
+pub fn t(clave: &'static str) -> &'static str {
+    match (textos(clave), idioma_actual()) {
+        (Some((es, _)), Idioma::Espanol) => es,
+        (Some((_, en)), Idioma::Ingles) => en,
+        (None, _) => clave,
+    }
+}
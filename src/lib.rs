@@ -0,0 +1,16 @@
+//! Lógica de Auto SADE sin la interfaz gráfica
+//!
+//! Descarga de comunicaciones, procesamiento de PDFs, manejo de archivos y
+//! generación de Excel, para usar desde la GUI, tests de integración u otros programas.
+
+pub mod busqueda_comunicaciones;
+pub mod config;
+mod esperas;
+pub mod excel_handler;
+pub mod file_processor;
+pub mod ocr;
+pub mod pdf_extractor;
+pub mod perfil_temporal;
+pub mod registro;
+mod selectores;
+pub mod web_automation;
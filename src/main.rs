@@ -1,17 +1,15 @@
 // Ocultar ventana de consola en Windows (solo en modo release)
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-mod busqueda_comunicaciones;
-mod config;
-mod excel_handler;
-mod file_processor;
+mod cli;
 mod gui;
-mod pdf_extractor;
-mod web_automation;
+mod i18n;
 
+use clap::Parser;
 use std::env;
+use std::process::ExitCode;
 
-fn main() -> eframe::Result<()> {
+fn main() -> ExitCode {
     // Intentar cargar .env desde múltiples ubicaciones
 
     // 1. Junto al ejecutable
@@ -35,6 +33,42 @@ fn main() -> eframe::Result<()> {
         let _ = dotenvy::from_path(&env_path);
     }
 
+    // Con subcomando se corre en modo desatendido; sin argumentos, la GUI.
+    // `--help` y los errores de argumentos ya se imprimen al parsear
+    if env::args_os().len() > 1 {
+        adjuntar_consola();
+    }
+    let cli = cli::Cli::parse();
+    if !cli.usa_gui() {
+        return cli::ejecutar(cli);
+    }
+
     // Ejecutar la GUI
-    gui::run()
+    match gui::run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error en la interfaz: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// En release el ejecutable no abre consola (subsistema "windows"); en modo CLI
+/// se engancha a la de quien lo lanzó para que se vean la salida y los errores
+#[cfg(windows)]
+fn adjuntar_consola() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(dw_process_id: u32) -> i32;
+    }
+
+    // SAFETY: no recibe punteros; si no hay consola padre simplemente falla
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
 }
+
+#[cfg(not(windows))]
+fn adjuntar_consola() {}
@@ -0,0 +1,153 @@
+//! Reconocimiento óptico de caracteres (OCR) para PDFs escaneados
+//!
+//! Extrae las imágenes de cada página con `lopdf` y las pasa al ejecutable
+//! `tesseract` por línea de comandos. La ruta del ejecutable puede definirse
+//! con la variable de entorno `SADE_TESSERACT_PATH`.
+
+use crate::registro::registrar;
+use anyhow::{Context, Result};
+use lopdf::Document;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Cantidad mínima de caracteres para considerar que un PDF tiene capa de texto
+pub const MIN_CARACTERES_TEXTO: usize = 30;
+
+/// Obtiene el ejecutable de tesseract a usar
+fn obtener_tesseract() -> PathBuf {
+    std::env::var("SADE_TESSERACT_PATH")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("tesseract"))
+}
+
+/// Indica si el texto extraído es demasiado corto para ser útil
+pub fn texto_insuficiente(texto: &str) -> bool {
+    texto.trim().chars().count() < MIN_CARACTERES_TEXTO
+}
+
+/// Guarda en `dir` las imágenes de todas las páginas del PDF en un formato
+/// que tesseract pueda leer. Devuelve las rutas en orden de página.
+fn exportar_imagenes(doc: &Document, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut rutas = Vec::new();
+
+    for (num_pagina, page_id) in doc.page_iter().enumerate() {
+        let imagenes = match doc.get_page_images(page_id) {
+            Ok(i) => i,
+            Err(_) => continue,
+        };
+
+        for (num_imagen, imagen) in imagenes.iter().enumerate() {
+            let filtros = imagen.filters.clone().unwrap_or_default();
+            let base = dir.join(format!("p{:04}_{:02}", num_pagina, num_imagen));
+
+            // JPEG y JPEG 2000 se pueden escribir tal cual
+            if filtros.iter().any(|f| f == "DCTDecode") {
+                let ruta = base.with_extension("jpg");
+                std::fs::write(&ruta, imagen.content)?;
+                rutas.push(ruta);
+                continue;
+            }
+            if filtros.iter().any(|f| f == "JPXDecode") {
+                let ruta = base.with_extension("jp2");
+                std::fs::write(&ruta, imagen.content)?;
+                rutas.push(ruta);
+                continue;
+            }
+
+            // Imágenes con píxeles crudos (8 bits, gris o RGB) se convierten a PNG
+            if imagen.bits_per_component != Some(8) {
+                registrar!(
+                    "OCR: imagen de la página {} con formato no soportado",
+                    num_pagina + 1
+                );
+                continue;
+            }
+
+            let pixeles = match doc
+                .get_object(imagen.id)
+                .and_then(|o| o.as_stream())
+                .map(|s| {
+                    s.decompressed_content()
+                        .unwrap_or_else(|_| s.content.clone())
+                }) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+
+            let (ancho, alto) = (imagen.width as u32, imagen.height as u32);
+            let dinamica = match imagen.color_space.as_deref() {
+                Some("DeviceGray") => image::GrayImage::from_raw(ancho, alto, pixeles)
+                    .map(image::DynamicImage::ImageLuma8),
+                Some("DeviceRGB") => image::RgbImage::from_raw(ancho, alto, pixeles)
+                    .map(image::DynamicImage::ImageRgb8),
+                _ => None,
+            };
+
+            match dinamica {
+                Some(img) => {
+                    let ruta = base.with_extension("png");
+                    img.save(&ruta)
+                        .with_context(|| format!("No se pudo guardar la imagen {:?}", ruta))?;
+                    rutas.push(ruta);
+                }
+                None => registrar!(
+                    "OCR: imagen de la página {} con espacio de color no soportado",
+                    num_pagina + 1
+                ),
+            }
+        }
+    }
+
+    Ok(rutas)
+}
+
+/// Extrae el texto de un PDF escaneado aplicando OCR a las imágenes de sus páginas
+pub fn extraer_texto_ocr(ruta_pdf: &Path) -> Result<String> {
+    let doc =
+        Document::load(ruta_pdf).with_context(|| format!("Error al cargar PDF: {:?}", ruta_pdf))?;
+
+    let unique_id = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_nanos();
+    let dir_temporal = std::env::temp_dir().join(format!("auto_sade_ocr_{}", unique_id));
+    std::fs::create_dir_all(&dir_temporal)
+        .context("No se pudo crear el directorio temporal para OCR")?;
+
+    let resultado = (|| -> Result<String> {
+        let imagenes = exportar_imagenes(&doc, &dir_temporal)?;
+        let tesseract = obtener_tesseract();
+        let mut texto = String::new();
+
+        for imagen in imagenes {
+            let salida = Command::new(&tesseract)
+                .arg(&imagen)
+                .arg("stdout")
+                .args(["-l", "spa"])
+                .output()
+                .with_context(|| {
+                    format!(
+                        "No se pudo ejecutar tesseract ({}). Instálelo o defina SADE_TESSERACT_PATH",
+                        tesseract.display()
+                    )
+                })?;
+
+            if !salida.status.success() {
+                anyhow::bail!(
+                    "tesseract falló en {:?}: {}",
+                    imagen,
+                    String::from_utf8_lossy(&salida.stderr).trim()
+                );
+            }
+
+            texto.push_str(&String::from_utf8_lossy(&salida.stdout));
+            texto.push('\n');
+        }
+
+        Ok(texto)
+    })();
+
+    let _ = std::fs::remove_dir_all(&dir_temporal);
+    resultado
+}
@@ -1,12 +1,19 @@
+use crate::registro::registrar;
+use crate::{excel_handler, file_processor, ocr};
 use anyhow::{Context, Result};
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
 use lopdf::Document;
-use pdf_extract::extract_text;
+use pdf_extract::{
+    extract_text, extract_text_by_pages, extract_text_by_pages_encrypted, extract_text_encrypted,
+};
 use regex::Regex;
+use serde::Serialize;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use walkdir::WalkDir;
 
-/// Decodifica bytes de un PDF, intentando primero UTF-8 y luego Latin-1 (ISO-8859-1)
+/// Decodifica bytes de un PDF, intentando primero UTF-8 y luego Windows-1252
 /// Esto es necesario para manejar correctamente caracteres especiales del español
 /// como acentos (á, é, í, ó, ú), la ñ, diéresis (ü), etc.
 fn decodificar_texto_pdf(bytes: &[u8]) -> String {
@@ -15,27 +22,97 @@ fn decodificar_texto_pdf(bytes: &[u8]) -> String {
         return texto.to_string();
     }
 
-    // Si falla, asumimos Latin-1 (ISO-8859-1) que es común en PDFs en español
-    // Latin-1 mapea directamente bytes 0-255 a caracteres Unicode
-    bytes.iter().map(|&b| b as char).collect()
+    // Si falla, asumimos Windows-1252, común en PDFs en español. A diferencia de
+    // Latin-1 puro, los bytes 0x80-0x9F son comillas tipográficas, guiones, etc.
+    let (texto, _, _) = encoding_rs::WINDOWS_1252.decode(bytes);
+    texto.into_owned()
 }
 
 /// Datos extraídos de un PDF
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DatosPdf {
     pub ccoo: String,
     pub organismo: String,
+    /// Organismos adicionales cuando intervienen varias reparticiones
+    pub organismos_extra: Vec<String>,
     pub patrimonial: String,
+    /// En JSON va como `AAAA-MM-DD`, o `null` si el PDF no tenía fecha
+    #[serde(serialize_with = "serializar_fecha")]
     pub fecha: Option<NaiveDate>,
-    pub resultado: String,
+    pub resultado: Resultado,
+    /// Carpeta a la que se movió el archivo (`Procesados`, `Revisar` o `Ilegibles`),
+    /// o `Procesados/<organismo>` al agrupar por organismo. Vacía si no se movió.
+    pub carpeta: String,
+    /// Nombre completo del PDF del que salió la fila, para ubicar el documento
+    pub archivo: String,
+    /// Ruta que tenía el PDF antes de moverlo, para devolverlo si se descartan los
+    /// datos. No va al Excel ni al JSON.
+    #[serde(skip)]
+    pub origen: Option<PathBuf>,
+}
+
+/// Fila de prueba con el CCOO, el organismo y el resultado dados
+#[cfg(test)]
+pub(crate) fn dato(ccoo: &str, organismo: &str, resultado: Resultado) -> DatosPdf {
+    DatosPdf {
+        ccoo: ccoo.to_string(),
+        organismo: organismo.to_string(),
+        organismos_extra: Vec::new(),
+        patrimonial: String::new(),
+        fecha: None,
+        resultado,
+        carpeta: String::new(),
+        archivo: format!("{}.pdf", ccoo),
+        origen: None,
+    }
+}
+
+/// Serializa la fecha en formato ISO 8601 (`null` si falta)
+fn serializar_fecha<S: serde::Serializer>(
+    fecha: &Option<NaiveDate>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match fecha {
+        Some(f) => serializer.serialize_str(&f.format("%Y-%m-%d").to_string()),
+        None => serializer.serialize_none(),
+    }
 }
 
+/// Carpeta de destino de los PDFs sin novedades
+pub const CARPETA_PROCESADOS: &str = "Procesados";
+
+/// Carpeta de destino de los PDFs que requieren revisión
+pub const CARPETA_REVISAR: &str = "Revisar";
+
+/// Carpeta de destino de los PDFs sin texto legible, para revisar a mano
+pub const CARPETA_ILEGIBLES: &str = "Ilegibles";
+
+/// Carpeta de destino de los PDFs protegidos con contraseña
+pub const CARPETA_PROTEGIDOS: &str = "Protegidos";
+
 /// Extrae el organismo de las anotaciones del PDF
 /// Equivalente a `extraer_organismo` en Python (usa pikepdf)
 pub fn extraer_organismo(ruta_pdf: &Path) -> Result<String> {
+    Ok(extraer_organismos(ruta_pdf)?
+        .into_iter()
+        .next()
+        .unwrap_or_default())
+}
+
+/// Extrae todos los organismos de las anotaciones `reparticion_N` del PDF,
+/// ordenados por índice
+pub fn extraer_organismos(ruta_pdf: &Path) -> Result<Vec<String>> {
     let doc =
         Document::load(ruta_pdf).with_context(|| format!("Error al cargar PDF: {:?}", ruta_pdf))?;
 
+    Ok(extraer_organismos_de_documento(&doc))
+}
+
+/// Igual que `extraer_organismos` pero sobre un documento ya cargado
+/// (por ejemplo, uno que hubo que desencriptar)
+pub fn extraer_organismos_de_documento(doc: &Document) -> Vec<String> {
+    let mut organismos: Vec<(usize, String)> = Vec::new();
+
     // Iterar sobre las páginas
     for page_id in doc.page_iter() {
         if let Ok(page) = doc.get_dictionary(page_id) {
@@ -89,144 +166,818 @@ pub fn extraer_organismo(ruta_pdf: &Path) -> Result<String> {
                     continue;
                 };
 
-                // Buscar "reparticion_0"
-                if annot_name == "reparticion_0" {
-                    if let Ok(v_value) = annot.get(b"V") {
-                        // El valor puede ser string o bytes
-                        let value = if let Ok(bytes) = v_value.as_str() {
-                            decodificar_texto_pdf(bytes)
-                        } else if let Ok(name) = v_value.as_name_str() {
-                            name.to_string()
-                        } else {
-                            continue;
-                        };
-
-                        // Tomar solo la primera línea
-                        let organismo = value.lines().next().unwrap_or("").to_string();
-
-                        if !organismo.is_empty() {
-                            return Ok(organismo);
-                        }
+                // Buscar "reparticion_N"
+                let indice = match annot_name
+                    .strip_prefix("reparticion_")
+                    .and_then(|n| n.parse::<usize>().ok())
+                {
+                    Some(i) => i,
+                    None => continue,
+                };
+
+                if organismos.iter().any(|(i, _)| *i == indice) {
+                    continue;
+                }
+
+                if let Ok(v_value) = annot.get(b"V") {
+                    // El valor puede ser string o bytes
+                    let value = if let Ok(bytes) = v_value.as_str() {
+                        decodificar_texto_pdf(bytes)
+                    } else if let Ok(name) = v_value.as_name_str() {
+                        name.to_string()
+                    } else {
+                        continue;
+                    };
+
+                    // Tomar solo la primera línea
+                    let organismo = value.lines().next().unwrap_or("").to_string();
+
+                    if !organismo.is_empty() {
+                        organismos.push((indice, organismo));
                     }
                 }
             }
         }
     }
-    Ok(String::new())
+
+    organismos.sort_by_key(|(indice, _)| *indice);
+    organismos.into_iter().map(|(_, o)| o).collect()
+}
+
+/// Desfasaje de Buenos Aires respecto de UTC (UTC-3, sin horario de verano)
+const SEGUNDOS_OFFSET_BUENOS_AIRES: i32 = -3 * 3600;
+
+/// Interpreta la zona horaria de una fecha PDF (`Z`, `+HH'mm'`, `-HH'mm'` o `+HH`).
+/// Retorna None si no hay zona o no se puede interpretar.
+fn parsear_offset_pdf(zona: &str) -> Option<FixedOffset> {
+    let mut chars = zona.chars();
+    let signo = match chars.next()? {
+        'Z' | 'z' => return FixedOffset::east_opt(0),
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+
+    let digitos: String = chars.filter(|c| *c != '\'').collect();
+    let horas: i32 = digitos.get(..2)?.parse().ok()?;
+    let minutos: i32 = match digitos.get(2..4) {
+        Some(m) => m.parse().ok()?,
+        None => 0,
+    };
+
+    FixedOffset::east_opt(signo * (horas * 3600 + minutos * 60))
+}
+
+/// Metadato del que se toma la fecha de la comunicación
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FuenteFecha {
+    /// Fecha de modificación (`ModDate`), con la de creación como respaldo
+    #[default]
+    Modificacion,
+    /// Fecha de creación (`CreationDate`), útil cuando el documento se firmó y
+    /// nunca se modificó; la de modificación queda como respaldo
+    Creacion,
+}
+
+impl FuenteFecha {
+    /// Claves del diccionario `Info` a probar, en orden de preferencia
+    pub fn claves(self) -> &'static [&'static [u8]] {
+        match self {
+            FuenteFecha::Modificacion => &[b"ModDate", b"CreationDate"],
+            FuenteFecha::Creacion => &[b"CreationDate", b"ModDate"],
+        }
+    }
+}
+
+/// Busca la fecha en el diccionario `Info` del documento probando las claves en
+/// orden y devuelve la primera que se pueda convertir
+pub fn extraer_fecha_pdf(doc: &Document, claves: &[&[u8]]) -> Option<NaiveDate> {
+    let info = match doc.trailer.get(b"Info").ok()? {
+        lopdf::Object::Reference(id) => doc.get_dictionary(*id).ok()?,
+        objeto => objeto.as_dict().ok()?,
+    };
+    claves.iter().find_map(|clave| {
+        info.get(clave)
+            .ok()
+            .and_then(|d| d.as_str().ok())
+            .and_then(|s| convertir_fecha_pdf(&decodificar_texto_pdf(s)))
+    })
 }
 
 /// Convierte la fecha del PDF a NaiveDate
+/// Si la fecha trae zona horaria se pasa a la hora de Buenos Aires antes de
+/// quedarse con el día; sin zona se toma tal cual.
 /// Retorna Option para permitir manejo de fechas inválidas
 pub fn convertir_fecha_pdf(fecha_pdf: &str) -> Option<NaiveDate> {
-    // Eliminar prefijo "D:" y zona horaria
-    let fecha_limpia = fecha_pdf
-        .strip_prefix("D:")
-        .unwrap_or(fecha_pdf)
-        .split('-')
-        .next()
-        .unwrap_or("")
-        .split('+')
-        .next()
-        .unwrap_or("");
-
-    if fecha_limpia.len() < 14 {
-        return None;
-    }
+    // Eliminar prefijo "D:" y separar la zona horaria (formato: YYYYMMDDHHMMSS+HH'mm')
+    let fecha = fecha_pdf.trim();
+    let fecha = fecha.strip_prefix("D:").unwrap_or(fecha);
+    let fecha_hora = fecha.get(..14)?;
+    let zona = &fecha[14..];
 
     // Parsear la fecha (formato: YYYYMMDDHHMMSS)
-    let fecha_parseada = NaiveDateTime::parse_from_str(fecha_limpia, "%Y%m%d%H%M%S").ok()?;
+    let fecha_parseada = NaiveDateTime::parse_from_str(fecha_hora, "%Y%m%d%H%M%S").ok()?;
+
+    let Some(offset) = parsear_offset_pdf(zona) else {
+        return Some(fecha_parseada.date());
+    };
+    let buenos_aires = FixedOffset::east_opt(SEGUNDOS_OFFSET_BUENOS_AIRES)?;
+    let fecha_local = offset
+        .from_local_datetime(&fecha_parseada)
+        .single()?
+        .with_timezone(&buenos_aires);
 
-    Some(fecha_parseada.date())
+    Some(fecha_local.date_naive())
 }
 
+/// Patrones de código patrimonial, en orden de prioridad.
+/// El primero es el formato canónico; los siguientes son variantes observadas
+/// con más dígitos en los segmentos finales.
+pub const PATRONES_PATRIMONIAL: &[&str] = &[
+    r"\d\.\d{2}\.\d\.\d\.\d{3,5}\.\d\.\d",
+    r"\d\.\d{2}\.\d\.\d\.\d{3,6}\.\d{1,2}\.\d{1,2}",
+];
+
 /// Extrae el código patrimonial del texto
 /// Equivalente a `extraer_patrimonial` en Python
 pub fn extraer_patrimonial(texto: &str) -> Option<String> {
-    let patron = Regex::new(r"\d\.\d{2}\.\d\.\d\.\d{3,5}\.\d\.\d").ok()?;
-    patron.find(texto).map(|m| m.as_str().to_string())
+    extraer_patrimonial_con_patrones(texto, PATRONES_PATRIMONIAL)
+}
+
+/// Extrae el código patrimonial probando los patrones dados en orden.
+/// Devuelve la primera coincidencia del primer patrón que encuentre alguna;
+/// los patrones inválidos se ignoran.
+pub fn extraer_patrimonial_con_patrones(texto: &str, patrones: &[&str]) -> Option<String> {
+    patrones
+        .iter()
+        .filter_map(|p| Regex::new(p).ok())
+        .find_map(|regex| {
+            coincidencias_completas(&regex, texto)
+                .next()
+                .map(|m| m.as_str().to_string())
+        })
+}
+
+/// Coincidencias que no están pegadas a otros dígitos, para no devolver
+/// un código recortado cuando el real tiene más dígitos que el patrón
+fn coincidencias_completas<'r, 't>(
+    regex: &'r Regex,
+    texto: &'t str,
+) -> impl Iterator<Item = regex::Match<'t>> + 'r
+where
+    't: 'r,
+{
+    regex.find_iter(texto).filter(move |m| {
+        let antes = texto[..m.start()].chars().next_back();
+        let despues = texto[m.end()..].chars().next();
+        !antes.is_some_and(|c| c.is_ascii_digit()) && !despues.is_some_and(|c| c.is_ascii_digit())
+    })
+}
+
+/// Dígitos admitidos en cada segmento del código patrimonial (mínimo, máximo),
+/// cubriendo el formato canónico y sus variantes
+const SEGMENTOS_PATRIMONIAL: [(usize, usize); 7] =
+    [(1, 1), (2, 2), (1, 1), (1, 1), (3, 6), (1, 2), (1, 2)];
+
+/// Candidatos a código patrimonial con separadores irregulares (comas, guiones o
+/// espacios que mete la extracción), para pasar por `validar_patrimonial`
+const PATRON_PATRIMONIAL_LAXO: &str = r"\d(?:\s*[.,\-]\s*\d+(?:\s\d+)?){6}";
+
+/// Normaliza un código patrimonial (separadores `.` y sin espacios) y verifica
+/// que cada segmento tenga la cantidad de dígitos esperada. Devuelve `None` si
+/// no es un código válido.
+pub fn validar_patrimonial(raw: &str) -> Option<String> {
+    let segmentos: Vec<String> = raw
+        .trim()
+        .split(['.', ',', '-'])
+        .map(|s| s.chars().filter(|c| !c.is_whitespace()).collect())
+        .collect();
+    if segmentos.len() != SEGMENTOS_PATRIMONIAL.len() {
+        return None;
+    }
+    let valido = segmentos
+        .iter()
+        .zip(SEGMENTOS_PATRIMONIAL)
+        .all(|(segmento, (minimo, maximo))| {
+            (minimo..=maximo).contains(&segmento.len())
+                && segmento.chars().all(|c| c.is_ascii_digit())
+        });
+    valido.then(|| segmentos.join("."))
+}
+
+/// Código patrimonial del texto ya validado y normalizado. Si los patrones
+/// estrictos no encuentran uno válido, prueba con separadores irregulares.
+pub fn extraer_patrimonial_validado(texto: &str) -> Option<String> {
+    if let Some(codigo) = extraer_patrimonial(texto).and_then(|p| validar_patrimonial(&p)) {
+        return Some(codigo);
+    }
+    let regex = Regex::new(PATRON_PATRIMONIAL_LAXO).ok()?;
+    let codigo =
+        coincidencias_completas(&regex, texto).find_map(|m| validar_patrimonial(m.as_str()));
+    codigo
+}
+
+/// Extrae todos los códigos patrimoniales del texto, en orden de aparición.
+/// Si dos patrones coinciden sobre el mismo fragmento se conserva el de mayor prioridad.
+pub fn extraer_patrimoniales_todos(texto: &str) -> Vec<String> {
+    let mut encontrados: Vec<(usize, usize, String)> = Vec::new();
+
+    for regex in PATRONES_PATRIMONIAL
+        .iter()
+        .filter_map(|p| Regex::new(p).ok())
+    {
+        for m in coincidencias_completas(&regex, texto) {
+            let se_superpone = encontrados
+                .iter()
+                .any(|(inicio, fin, _)| m.start() < *fin && *inicio < m.end());
+            if !se_superpone {
+                encontrados.push((m.start(), m.end(), m.as_str().to_string()));
+            }
+        }
+    }
+
+    encontrados.sort_by_key(|(inicio, _, _)| *inicio);
+    encontrados
+        .into_iter()
+        .map(|(_, _, codigo)| codigo)
+        .collect()
+}
+
+/// Clasificación del resultado del inventario informado en la comunicación
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Resultado {
+    SinNovedad,
+    ConNovedades,
+    /// No se pudo leer texto suficiente para clasificar el documento
+    RevisionManual,
+}
+
+impl Resultado {
+    pub const TODOS: [Resultado; 3] = [
+        Resultado::SinNovedad,
+        Resultado::ConNovedades,
+        Resultado::RevisionManual,
+    ];
+
+    /// Texto con que se muestra en el Excel
+    pub fn texto(&self) -> &'static str {
+        match self {
+            Resultado::SinNovedad => "Sin novedad",
+            Resultado::ConNovedades => "Con novedades (ver)",
+            Resultado::RevisionManual => "Revisar manualmente",
+        }
+    }
+
+    /// Carpeta a la que se mueven los PDFs con este resultado
+    pub fn carpeta(&self) -> &'static str {
+        match self {
+            Resultado::SinNovedad => CARPETA_PROCESADOS,
+            Resultado::ConNovedades => CARPETA_REVISAR,
+            Resultado::RevisionManual => CARPETA_ILEGIBLES,
+        }
+    }
+
+    /// Interpreta el texto de una celda del Excel; lo que no se reconozca
+    /// se considera con novedades
+    pub fn desde_texto(texto: &str) -> Self {
+        Resultado::TODOS
+            .into_iter()
+            .find(|r| texto.trim().eq_ignore_ascii_case(r.texto()))
+            .unwrap_or(Resultado::ConNovedades)
+    }
+}
+
+impl std::fmt::Display for Resultado {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.texto())
+    }
 }
 
 /// Determina el resultado del inventario basado en patrones de texto
 /// Equivalente a `extraer_resultado` en Python
 pub fn extraer_resultado(texto: &str) -> String {
+    clasificar_resultado(texto).texto().to_string()
+}
+
+/// Patrones de "sin novedad", buscados en el texto sin espacios
+pub const PATRONES_SIN_NOVEDAD: &[&str] = &[
+    r"(?i)sinnovedad",
+    r"(?i)sinnovedades",
+    r"(?i)encuentransincambio",
+    r"(?i)encuentrasincambio",
+    r"(?i)noexistennovedad",
+    r"(?i)nosurgennovedades",
+    r"(?i)nopresentanovedad",
+    r"(?i)EXCEDENTESNIFALTANTESANOTIFICAR",
+    r"(?i)noexistediferenciaentreloobranteenelSigaf",
+    r"(?i)nohabiéndoseenprincipioverificadodiferencias",
+    r"(?i)nohabiendoseenprincipioverificadodiferencias",
+    r"(?i)seencuentraninventariadoscomobienesmuebles",
+    r"(?i)noposeebienesasignadosporelSIGAFWEB",
+    r"(?i)noseregistrannovedad",
+    r#"(?i)noregistra"excedentes"ni"faltantes""#,
+    r"(?i)noregistraexcedentesnifaltantes",
+    r"(?i)Sinnovedadesalrespecto",
+    r"(?i)Hasidoverificadayseencuentracorrecta",
+    r"(?i)sinencontrarnovedades",
+    r"(?i)sinvariaciones",
+    r"(?i)SINNOVEDA",
+    r"(?i)noregistranovedad",
+    r"(?i)nohubonovedad",
+    r"(?i)SinExcedentesySinFaltantes",
+    r"(?i)NosehanlocalizadobienesExcedentesy/oFaltantes",
+    r"(?i)S/NOVEDAD",
+    r"(?i)nohaynovedad",
+    r"(?i)nosehanverificadonovedades",
+    r"(?i)novedadalguna",
+    r"(?i)nohahabidonovedades",
+    r"(?i)noposeesaldosenbienesprecarios",
+    r"(?i)sinvariacion",
+    r"(?i)noseregistraronnovedades",
+    r"(?i)sinmodificacion",
+    r"(?i)noregistramovimientos",
+    r"(?i)notienenovedad",
+    r"(?i)nohabiéndoseencontradodiferencias",
+    r"(?i)noarrojanovedad",
+    r"(?i)notuvonovedad",
+];
+
+/// Clasifica el resultado del inventario según los patrones de "sin novedad".
+/// Un texto vacío o demasiado corto no alcanza para decidir y queda para revisión manual.
+pub fn clasificar_resultado(texto: &str) -> Resultado {
+    extraer_resultado_detallado(texto).0
+}
+
+/// Como `clasificar_resultado`, pero devuelve además el patrón que coincidió y el
+/// fragmento del texto (sin espacios) donde lo hizo, para auditar falsos positivos
+pub fn extraer_resultado_detallado(texto: &str) -> (Resultado, Option<String>) {
+    if ocr::texto_insuficiente(texto) {
+        return (Resultado::RevisionManual, None);
+    }
+
     let texto_sin_espacios = texto.replace(' ', "");
 
-    let patrones = [
-        r"(?i)sinnovedad",
-        r"(?i)sinnovedades",
-        r"(?i)encuentransincambio",
-        r"(?i)encuentrasincambio",
-        r"(?i)noexistennovedad",
-        r"(?i)nosurgennovedades",
-        r"(?i)nopresentanovedad",
-        r"(?i)EXCEDENTESNIFALTANTESANOTIFICAR",
-        r"(?i)noexistediferenciaentreloobranteenelSigaf",
-        r"(?i)nohabiéndoseenprincipioverificadodiferencias",
-        r"(?i)nohabiendoseenprincipioverificadodiferencias",
-        r"(?i)seencuentraninventariadoscomobienesmuebles",
-        r"(?i)noposeebienesasignadosporelSIGAFWEB",
-        r"(?i)noseregistrannovedad",
-        r#"(?i)noregistra"excedentes"ni"faltantes""#,
-        r"(?i)noregistraexcedentesnifaltantes",
-        r"(?i)Sinnovedadesalrespecto",
-        r"(?i)Hasidoverificadayseencuentracorrecta",
-        r"(?i)sinencontrarnovedades",
-        r"(?i)sinvariaciones",
-        r"(?i)SINNOVEDA",
-        r"(?i)noregistranovedad",
-        r"(?i)nohubonovedad",
-        r"(?i)SinExcedentesySinFaltantes",
-        r"(?i)NosehanlocalizadobienesExcedentesy/oFaltantes",
-        r"(?i)S/NOVEDAD",
-        r"(?i)nohaynovedad",
-        r"(?i)nosehanverificadonovedades",
-        r"(?i)novedadalguna",
-        r"(?i)nohahabidonovedades",
-        r"(?i)noposeesaldosenbienesprecarios",
-        r"(?i)sinvariacion",
-        r"(?i)noseregistraronnovedades",
-        r"(?i)sinmodificacion",
-        r"(?i)noregistramovimientos",
-        r"(?i)notienenovedad",
-        r"(?i)nohabiéndoseencontradodiferencias",
-        r"(?i)noarrojanovedad",
-        r"(?i)notuvonovedad",
-    ];
+    for patron in PATRONES_SIN_NOVEDAD {
+        let Ok(regex) = Regex::new(patron) else {
+            continue;
+        };
+        if let Some(coincidencia) = regex.find(&texto_sin_espacios) {
+            let detalle = format!(
+                "{} en \"{}\"",
+                patron.trim_start_matches("(?i)"),
+                contexto_coincidencia(&texto_sin_espacios, coincidencia.range())
+            );
+            return (Resultado::SinNovedad, Some(detalle));
+        }
+    }
+
+    (Resultado::ConNovedades, None)
+}
+
+/// Caracteres de texto que se muestran a cada lado de la coincidencia
+const CONTEXTO_COINCIDENCIA: usize = 30;
+
+/// Fragmento de `texto` alrededor de `rango`, con `…` donde se recortó
+fn contexto_coincidencia(texto: &str, rango: std::ops::Range<usize>) -> String {
+    let antes: String = texto[..rango.start]
+        .chars()
+        .rev()
+        .take(CONTEXTO_COINCIDENCIA)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let despues: String = texto[rango.end..]
+        .chars()
+        .take(CONTEXTO_COINCIDENCIA)
+        .collect();
+    let inicio = if antes.len() < rango.start { "…" } else { "" };
+    let fin = if despues.len() < texto.len() - rango.end {
+        "…"
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", inicio, antes, &texto[rango], despues, fin)
+}
+
+/// Resultado del procesamiento de una carpeta de PDFs
+#[derive(Debug, Default)]
+pub struct ResultadoProcesamiento {
+    pub datos: Vec<DatosPdf>,
+    /// Archivos que no se pudieron mover ni siquiera en el reintento final
+    /// (generalmente porque están abiertos en otro programa)
+    pub archivos_bloqueados: Vec<String>,
+    /// PDFs protegidos con contraseña que se movieron a [`CARPETA_PROTEGIDOS`]
+    pub archivos_protegidos: Vec<String>,
+    /// PDFs de los que no se pudo extraer el texto
+    pub archivos_fallidos: Vec<String>,
+}
 
-    for patron in &patrones {
-        if let Ok(regex) = Regex::new(patron) {
-            if regex.is_match(&texto_sin_espacios) {
-                return "Sin novedad".to_string();
+impl ResultadoProcesamiento {
+    /// Agregados de la corrida para informar sin abrir el Excel
+    pub fn resumen(&self) -> ResumenProcesamiento {
+        let cantidad = |r: Resultado| self.datos.iter().filter(|d| d.resultado == r).count();
+        ResumenProcesamiento {
+            total: self.datos.len() + self.archivos_fallidos.len() + self.archivos_protegidos.len(),
+            sin_novedad: cantidad(Resultado::SinNovedad),
+            con_novedades: cantidad(Resultado::ConNovedades),
+            ilegibles: cantidad(Resultado::RevisionManual),
+            fallidos: self.archivos_fallidos.len() + self.archivos_protegidos.len(),
+            revisar: self
+                .datos
+                .iter()
+                .filter(|d| d.resultado == Resultado::ConNovedades)
+                .map(|d| d.ccoo.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Nombre del resumen en texto plano que se deja junto a los PDFs cuando se
+/// mueven todos los de la carpeta
+pub const ARCHIVO_RESUMEN: &str = "resumen.txt";
+
+/// Conteos de una corrida de `procesar_pdfs`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResumenProcesamiento {
+    /// PDFs encontrados, incluidos los que fallaron
+    pub total: usize,
+    pub sin_novedad: usize,
+    pub con_novedades: usize,
+    /// Sin texto legible, para revisar a mano
+    pub ilegibles: usize,
+    /// Sin texto extraíble o protegidos con una contraseña desconocida
+    pub fallidos: usize,
+    /// CCOO con novedades, que quedan en `Revisar`
+    pub revisar: Vec<String>,
+}
+
+impl ResumenProcesamiento {
+    /// Resumen en texto plano, pensado para pegar en un correo
+    pub fn texto(&self) -> String {
+        let mut texto = format!(
+            "Total de PDFs: {}\nSin novedad: {}\nCon novedades: {}\nSin texto legible: {}\nFallidos: {}\n",
+            self.total, self.sin_novedad, self.con_novedades, self.ilegibles, self.fallidos
+        );
+        if !self.revisar.is_empty() {
+            texto.push_str(&format!("\nComunicaciones en {}:\n", CARPETA_REVISAR));
+            for ccoo in &self.revisar {
+                texto.push_str(&format!("- {}\n", ccoo));
             }
         }
+        texto
     }
+}
 
-    "Con novedades (ver)".to_string()
+/// Opciones del procesamiento de PDFs
+#[derive(Debug, Clone)]
+pub struct OpcionesProcesamiento {
+    /// Aplicar OCR a los PDFs sin capa de texto (costoso, requiere tesseract)
+    pub ocr: bool,
+    /// Contraseña para abrir PDFs protegidos
+    pub password: Option<String>,
+    /// Recorrer también las subcarpetas de la ruta de archivos
+    pub recursivo: bool,
+    /// Procesar solo estos archivos (nombre con o sin `.pdf`); `None` procesa todos
+    pub archivos: Option<Vec<String>>,
+    /// Agrupar los PDFs sin novedad en una subcarpeta por organismo dentro de Procesados
+    pub por_organismo: bool,
+    /// Mover cada PDF a la carpeta de su resultado; con `false` solo se extraen los
+    /// datos y los archivos quedan donde están, para poder repetir la corrida
+    pub mover: bool,
+    /// Registrar en el log qué patrón clasificó cada PDF como sin novedad
+    pub diagnostico: bool,
+    /// Metadato de fecha que se prefiere; si falta se usa el otro
+    pub fuente_fecha: FuenteFecha,
 }
 
-/// Procesa todos los archivos PDF en una carpeta
-/// Equivalente a `procesar_pdfs` en Python
-pub fn procesar_pdfs(ruta_archivos: &Path) -> Result<Vec<DatosPdf>> {
-    let mut lista_datos = Vec::new();
+impl Default for OpcionesProcesamiento {
+    fn default() -> Self {
+        Self {
+            ocr: false,
+            password: None,
+            recursivo: false,
+            archivos: None,
+            por_organismo: false,
+            mover: true,
+            diagnostico: false,
+            fuente_fecha: FuenteFecha::default(),
+        }
+    }
+}
 
-    // Crear directorios de destino si no existen
-    let dir_procesados = ruta_archivos.join("Procesados");
-    let dir_revisar = ruta_archivos.join("Revisar");
-    fs::create_dir_all(&dir_procesados)?;
-    fs::create_dir_all(&dir_revisar)?;
+impl OpcionesProcesamiento {
+    /// Indica si el PDF entra en la selección de `archivos`
+    fn incluye(&self, ruta: &Path) -> bool {
+        let Some(archivos) = &self.archivos else {
+            return true;
+        };
+        let nombre = ruta.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let stem = ruta.file_stem().and_then(|n| n.to_str()).unwrap_or("");
+        archivos
+            .iter()
+            .map(|a| a.trim())
+            .any(|a| a.eq_ignore_ascii_case(nombre) || a.eq_ignore_ascii_case(stem))
+    }
+}
+
+/// Carpetas de resultados que no se recorren para no reprocesar archivos
+const CARPETAS_EXCLUIDAS: [&str; 5] = [
+    CARPETA_PROCESADOS,
+    CARPETA_REVISAR,
+    CARPETA_ILEGIBLES,
+    "Errores",
+    CARPETA_PROTEGIDOS,
+];
+
+/// Lista los PDFs de la carpeta, opcionalmente incluyendo subcarpetas
+fn listar_pdfs(ruta_archivos: &Path, recursivo: bool) -> Result<Vec<PathBuf>> {
+    let es_pdf = |p: &Path| p.extension().and_then(|e| e.to_str()) == Some("pdf");
+
+    if !recursivo {
+        let entries = fs::read_dir(ruta_archivos)
+            .with_context(|| format!("Error al leer directorio: {:?}", ruta_archivos))?;
+        // Solo archivos sueltos: las subcarpetas (Procesados, Revisar...) se ignoran
+        return Ok(entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && es_pdf(p))
+            .collect());
+    }
+
+    let mut rutas = Vec::new();
+    let recorrido = WalkDir::new(ruta_archivos).into_iter().filter_entry(|e| {
+        e.depth() == 0
+            || !e.file_type().is_dir()
+            || !CARPETAS_EXCLUIDAS.contains(&e.file_name().to_string_lossy().as_ref())
+    });
+    for entry in recorrido {
+        match entry {
+            Ok(e) if e.file_type().is_file() && es_pdf(e.path()) => rutas.push(e.into_path()),
+            Ok(_) => {}
+            Err(e) => registrar!("Error al recorrer {:?}: {}", ruta_archivos, e),
+        }
+    }
+    Ok(rutas)
+}
+
+/// Intenta desencriptar el documento, primero con contraseña vacía y luego
+/// con la configurada. Devuelve la contraseña que funcionó.
+fn desencriptar(doc: &Document, password: Option<&str>) -> Option<(Document, String)> {
+    std::iter::once("").chain(password).find_map(|pw| {
+        let mut copia = doc.clone();
+        copia.decrypt(pw).ok().map(|_| (copia, pw.to_string()))
+    })
+}
+
+/// Proporción de caracteres basura a partir de la cual se intenta otra extracción
+const UMBRAL_BASURA: f32 = 0.05;
+
+/// Indica si un carácter es típico de un texto mal decodificado: el de reemplazo,
+/// controles que no son espacios o el área de uso privado de las fuentes
+fn es_basura(c: char) -> bool {
+    c == '\u{FFFD}'
+        || (c.is_control() && !c.is_whitespace())
+        || ('\u{E000}'..='\u{F8FF}').contains(&c)
+}
+
+/// Proporción de caracteres basura entre los que no son espacios (0 si está vacío)
+fn proporcion_basura(texto: &str) -> f32 {
+    let (mut total, mut basura) = (0usize, 0usize);
+    for c in texto.chars().filter(|c| !c.is_whitespace()) {
+        total += 1;
+        if es_basura(c) {
+            basura += 1;
+        }
+    }
+    if total == 0 {
+        0.0
+    } else {
+        basura as f32 / total as f32
+    }
+}
+
+/// Elige entre dos extracciones de la misma página la que tiene menos basura;
+/// una vacía solo gana si la otra también lo está
+fn elegir_menos_basura(a: String, b: String) -> String {
+    match (a.trim().is_empty(), b.trim().is_empty()) {
+        (true, false) => b,
+        (false, true) => a,
+        _ if proporcion_basura(&b) < proporcion_basura(&a) => b,
+        _ => a,
+    }
+}
+
+/// Extrae el texto de una página leyendo sus operadores de texto con lopdf y
+/// decodificando cada cadena con `decodificar_texto_pdf`. No interpreta las
+/// tablas de las fuentes, por eso solo sirve como alternativa a `pdf_extract`.
+fn texto_pagina_lopdf(doc: &Document, pagina: lopdf::ObjectId) -> String {
+    let Some(contenido) = doc
+        .get_page_content(pagina)
+        .ok()
+        .and_then(|c| lopdf::content::Content::decode(&c).ok())
+    else {
+        return String::new();
+    };
+
+    let mut texto = String::new();
+    for operacion in contenido.operations {
+        match operacion.operator.as_str() {
+            "Tj" | "'" | "\"" => {
+                if let Some(lopdf::Object::String(bytes, _)) = operacion.operands.last() {
+                    texto.push_str(&decodificar_texto_pdf(bytes));
+                }
+            }
+            "TJ" => {
+                let Some(lopdf::Object::Array(partes)) = operacion.operands.first() else {
+                    continue;
+                };
+                for parte in partes {
+                    match parte {
+                        lopdf::Object::String(bytes, _) => {
+                            texto.push_str(&decodificar_texto_pdf(bytes))
+                        }
+                        // Un desplazamiento grande hacia la derecha separa palabras
+                        lopdf::Object::Integer(n) if *n < -200 => texto.push(' '),
+                        lopdf::Object::Real(n) if *n < -200.0 => texto.push(' '),
+                        _ => {}
+                    }
+                }
+            }
+            "Td" | "TD" | "T*" | "Tm" | "ET" if !texto.is_empty() && !texto.ends_with('\n') => {
+                texto.push('\n');
+            }
+            _ => {}
+        }
+    }
+    texto
+}
+
+/// Segunda pasada para textos con mucha basura: extrae página por página con
+/// `pdf_extract` y con lopdf y se queda en cada una con la versión más limpia.
+/// Devuelve `None` si no se pudo separar por páginas.
+fn extraer_texto_por_paginas(path: &Path, password: &str, doc: &Document) -> Option<String> {
+    let paginas_pdf = if password.is_empty() {
+        extract_text_by_pages(path)
+    } else {
+        extract_text_by_pages_encrypted(path, password)
+    }
+    .unwrap_or_default();
+    let paginas_lopdf: Vec<String> = doc
+        .page_iter()
+        .map(|pagina| texto_pagina_lopdf(doc, pagina))
+        .collect();
+    if paginas_pdf.is_empty() && paginas_lopdf.is_empty() {
+        return None;
+    }
+
+    let cantidad = paginas_pdf.len().max(paginas_lopdf.len());
+    let paginas: Vec<String> = (0..cantidad)
+        .map(|i| {
+            elegir_menos_basura(
+                paginas_pdf.get(i).cloned().unwrap_or_default(),
+                paginas_lopdf.get(i).cloned().unwrap_or_default(),
+            )
+        })
+        .collect();
+    Some(paginas.join("\n"))
+}
 
-    let entries = fs::read_dir(ruta_archivos)
-        .with_context(|| format!("Error al leer directorio: {:?}", ruta_archivos))?;
+/// Normaliza el texto extraído: une las líneas y quita caracteres de control
+fn limpiar_texto(texto: &str) -> String {
+    texto
+        .replace('\n', " ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+/// Corrige a mano el resultado de un PDF ya procesado y lo mueve de la carpeta
+/// en que quedó a la que corresponde al nuevo resultado. Si el procesamiento no
+/// movió el archivo, solo cambia el resultado.
+pub fn reclasificar(ruta_archivos: &Path, dato: &mut DatosPdf, nuevo: Resultado) -> Result<()> {
+    if dato.resultado == nuevo {
+        return Ok(());
+    }
+    if dato.carpeta.is_empty() {
+        registrar!(
+            "Resultado de {} corregido: {} -> {}",
+            dato.ccoo,
+            dato.resultado,
+            nuevo
+        );
+        dato.resultado = nuevo;
+        return Ok(());
+    }
+
+    // Filas de Excels viejos no traen el nombre del archivo
+    let archivo = if dato.archivo.is_empty() {
+        format!("{}.pdf", dato.ccoo)
+    } else {
+        dato.archivo.clone()
+    };
+    let origen = ruta_archivos.join(&dato.carpeta).join(&archivo);
+    let dir_destino = ruta_archivos.join(nuevo.carpeta());
+    fs::create_dir_all(&dir_destino)?;
+    fs::rename(&origen, dir_destino.join(&archivo))
+        .with_context(|| format!("No se pudo mover {} a {}", archivo, nuevo.carpeta()))?;
 
-    for entry in entries.flatten() {
-        let path = entry.path();
+    registrar!(
+        "Resultado de {} corregido: {} -> {}",
+        dato.ccoo,
+        dato.resultado,
+        nuevo
+    );
+    dato.resultado = nuevo;
+    dato.carpeta = nuevo.carpeta().to_string();
+    Ok(())
+}
 
-        // Solo procesar archivos PDF
-        if path.extension().and_then(|e| e.to_str()) != Some("pdf") {
+/// Devuelve a su ubicación original los PDFs que movió el procesamiento, para
+/// poder repetirlo si se descartan los datos. Retorna los que no se pudieron devolver.
+pub fn devolver_a_origen(ruta_archivos: &Path, datos: &[DatosPdf]) -> Vec<String> {
+    let mut no_devueltos = Vec::new();
+    for dato in datos {
+        let Some(origen) = dato.origen.as_ref().filter(|_| !dato.carpeta.is_empty()) else {
             continue;
+        };
+        let actual = ruta_archivos.join(&dato.carpeta).join(&dato.archivo);
+        if let Err(e) = fs::rename(&actual, file_processor::ruta_libre(origen)) {
+            registrar!("No se pudo devolver {}: {}", dato.archivo, e);
+            no_devueltos.push(dato.archivo.clone());
         }
+    }
+    no_devueltos
+}
+
+/// Carpeta (dentro de la ruta de archivos) donde se vuelca el texto extraído
+pub const CARPETA_DEBUG: &str = "debug";
 
+/// Indica si se pidió volcar el texto de cada PDF con `SADE_DEBUG_TEXTO=1`
+fn volcar_texto_activado() -> bool {
+    std::env::var("SADE_DEBUG_TEXTO").is_ok_and(|v| v.trim() == "1")
+}
+
+/// Guarda el texto extraído de un PDF en `debug/<ccoo>.txt`, para ver por qué se
+/// clasificó como lo hizo. Los errores solo se registran.
+fn volcar_texto_debug(ruta_archivos: &Path, ccoo: &str, texto: &str) {
+    let carpeta = ruta_archivos.join(CARPETA_DEBUG);
+    let resultado = fs::create_dir_all(&carpeta)
+        .and_then(|()| fs::write(carpeta.join(format!("{}.txt", ccoo)), texto));
+    if let Err(e) = resultado {
+        registrar!("No se pudo volcar el texto de {}: {}", ccoo, e);
+    }
+}
+
+/// Carpeta relativa a la ruta de archivos donde va el PDF: la del resultado o,
+/// agrupando por organismo, `Procesados/<organismo>` para los que no tienen novedad
+fn carpeta_destino(resultado: Resultado, organismo: &str, por_organismo: bool) -> String {
+    if !por_organismo || resultado != Resultado::SinNovedad {
+        return resultado.carpeta().to_string();
+    }
+    let subcarpeta = file_processor::nombre_carpeta_valido(organismo)
+        .unwrap_or_else(|| excel_handler::SIN_ORGANISMO.to_string());
+    Path::new(CARPETA_PROCESADOS)
+        .join(subcarpeta)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Procesa todos los archivos PDF en una carpeta
+/// Equivalente a `procesar_pdfs` en Python.
+/// `on_status` recibe (número de PDF, total, nombre del archivo) antes de procesar cada uno.
+pub fn procesar_pdfs(
+    ruta_archivos: &Path,
+    opciones: &OpcionesProcesamiento,
+    on_status: impl Fn(usize, usize, &str),
+) -> Result<ResultadoProcesamiento> {
+    let mut lista_datos = Vec::new();
+    // Archivos que no se pudieron mover en el primer intento, con su destino
+    let mut pendientes: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+    // Crear directorios de destino si no existen
+    let dir_procesados = ruta_archivos.join(CARPETA_PROCESADOS);
+    let dir_revisar = ruta_archivos.join(CARPETA_REVISAR);
+    let dir_ilegibles = ruta_archivos.join(CARPETA_ILEGIBLES);
+    let dir_protegidos = ruta_archivos.join(CARPETA_PROTEGIDOS);
+    if opciones.mover {
+        fs::create_dir_all(&dir_procesados)?;
+        fs::create_dir_all(&dir_revisar)?;
+        fs::create_dir_all(&dir_ilegibles)?;
+    }
+    let mut archivos_protegidos = Vec::new();
+    let mut archivos_fallidos = Vec::new();
+    let volcar_texto = volcar_texto_activado();
+
+    let pdfs: Vec<PathBuf> = listar_pdfs(ruta_archivos, opciones.recursivo)?
+        .into_iter()
+        .filter(|p| opciones.incluye(p))
+        .collect();
+    let total = pdfs.len();
+
+    for (indice, path) in pdfs.into_iter().enumerate() {
         let archivo_pdf = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        on_status(indice + 1, total, archivo_pdf);
 
         let ccoo = path
             .file_stem()
@@ -234,67 +985,581 @@ pub fn procesar_pdfs(ruta_archivos: &Path) -> Result<Vec<DatosPdf>> {
             .unwrap_or("")
             .to_string();
 
+        // Cargar el documento; los encriptados se intentan abrir con la contraseña
+        let mut doc = Document::load(&path).ok();
+        let mut password_usada = String::new();
+        if let Some(d) = doc.as_ref().filter(|d| d.is_encrypted()) {
+            match desencriptar(d, opciones.password.as_deref()) {
+                Some((desencriptado, pw)) => {
+                    doc = Some(desencriptado);
+                    password_usada = pw;
+                }
+                None if !opciones.mover => {
+                    registrar!("PDF protegido con contraseña: {}", archivo_pdf);
+                    archivos_protegidos.push(archivo_pdf.to_string());
+                    continue;
+                }
+                None => {
+                    registrar!(
+                        "PDF protegido con contraseña, se mueve a {}: {}",
+                        CARPETA_PROTEGIDOS,
+                        archivo_pdf
+                    );
+                    fs::create_dir_all(&dir_protegidos)?;
+                    let destino = file_processor::ruta_libre(&dir_protegidos.join(archivo_pdf));
+                    if let Err(e) = fs::rename(&path, destino) {
+                        registrar!("Error al mover archivo {}: {}", archivo_pdf, e);
+                    }
+                    archivos_protegidos.push(archivo_pdf.to_string());
+                    continue;
+                }
+            }
+        }
+
         // Extraer texto del PDF
-        let texto = match extract_text(&path) {
-            Ok(t) => t
-                .replace('\n', " ")
-                .chars()
-                .filter(|c| !c.is_control())
-                .collect::<String>(),
-            Err(_) => continue,
+        let extraido = if password_usada.is_empty() {
+            extract_text(&path)
+        } else {
+            extract_text_encrypted(&path, &password_usada)
+        };
+        // Si pdf_extract falla, todavía se puede leer con lopdf o con OCR
+        let (mut crudo, fallo_extraccion) = match extraido {
+            Ok(t) => (t, false),
+            Err(e) => {
+                registrar!("Error al extraer texto de {}: {}", archivo_pdf, e);
+                let alternativo = doc
+                    .as_ref()
+                    .and_then(|d| extraer_texto_por_paginas(&path, &password_usada, d))
+                    .unwrap_or_default();
+                (alternativo, true)
+            }
         };
 
-        // Extraer fecha de metadatos
-        let fecha: Option<NaiveDate> = if let Ok(doc) = Document::load(&path) {
-            if let Some(info) = doc
-                .trailer
-                .get(b"Info")
-                .ok()
-                .and_then(|i| i.as_reference().ok())
+        // Algunos documentos mezclan encodings: probar otra extracción por página
+        if !fallo_extraccion && proporcion_basura(&crudo) > UMBRAL_BASURA {
+            if let Some(alternativo) = doc
+                .as_ref()
+                .and_then(|d| extraer_texto_por_paginas(&path, &password_usada, d))
             {
-                if let Ok(info_dict) = doc.get_dictionary(info) {
-                    info_dict
-                        .get(b"ModDate")
-                        .ok()
-                        .and_then(|d| d.as_str().ok())
-                        .and_then(|s| {
-                            let s_str = decodificar_texto_pdf(s);
-                            convertir_fecha_pdf(&s_str)
-                        })
-                } else {
-                    None
+                if proporcion_basura(&alternativo) < proporcion_basura(&crudo) {
+                    registrar!(
+                        "Texto de {} mejorado con la extracción por página",
+                        archivo_pdf
+                    );
+                    crudo = alternativo;
                 }
-            } else {
-                None
             }
-        } else {
-            None
-        };
+        }
+        let mut texto = limpiar_texto(&crudo);
+
+        // PDFs escaneados: sin capa de texto, intentar con OCR si está habilitado
+        if opciones.ocr && ocr::texto_insuficiente(&texto) {
+            match ocr::extraer_texto_ocr(&path) {
+                Ok(t) if !ocr::texto_insuficiente(&t) => {
+                    texto = limpiar_texto(&t);
+                    crudo = t;
+                }
+                Ok(_) => registrar!("OCR sin resultados para {}", archivo_pdf),
+                Err(e) => registrar!("Error de OCR en {}: {}", archivo_pdf, e),
+            }
+        }
+        if fallo_extraccion && ocr::texto_insuficiente(&texto) {
+            archivos_fallidos.push(archivo_pdf.to_string());
+            continue;
+        }
+        if volcar_texto {
+            volcar_texto_debug(ruta_archivos, &ccoo, &crudo);
+        }
+
+        // Extraer fecha de metadatos
+        let fecha = doc
+            .as_ref()
+            .and_then(|d| extraer_fecha_pdf(d, opciones.fuente_fecha.claves()));
 
         // Extraer datos
-        let organismo = extraer_organismo(&path).unwrap_or_default();
-        let patrimonial = extraer_patrimonial(&texto).unwrap_or_default();
-        let resultado = extraer_resultado(&texto);
+        let mut organismos = doc
+            .as_ref()
+            .map(extraer_organismos_de_documento)
+            .unwrap_or_default()
+            .into_iter();
+        let organismo = organismos.next().unwrap_or_default();
+        let organismos_extra: Vec<String> = organismos.collect();
+        let patrimonial = extraer_patrimonial_validado(&texto).unwrap_or_default();
+        let (resultado, patron) = extraer_resultado_detallado(&texto);
+        if opciones.diagnostico {
+            if let Some(patron) = patron {
+                registrar!("{}: sin novedad por {}", archivo_pdf, patron);
+            }
+        }
+
+        // Solo extraer: el archivo queda donde está
+        if !opciones.mover {
+            lista_datos.push(DatosPdf {
+                ccoo,
+                organismo,
+                organismos_extra,
+                patrimonial,
+                fecha,
+                resultado,
+                carpeta: String::new(),
+                archivo: archivo_pdf.to_string(),
+                origen: None,
+            });
+            continue;
+        }
+
+        // Mover archivo según resultado
+        let carpeta = carpeta_destino(resultado, &organismo, opciones.por_organismo);
+        let dir_destino = ruta_archivos.join(&carpeta);
+        if let Err(e) = fs::create_dir_all(&dir_destino) {
+            registrar!("No se pudo crear la carpeta {:?}: {}", dir_destino, e);
+        }
+        // En modo recursivo dos subcarpetas pueden tener PDFs con el mismo nombre
+        let destino = file_processor::ruta_libre(&dir_destino.join(archivo_pdf));
+        let archivo = destino
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| archivo_pdf.to_string());
+        if archivo != archivo_pdf {
+            registrar!(
+                "{} ya existe en {}, se guarda como {}",
+                archivo_pdf,
+                carpeta,
+                archivo
+            );
+        }
 
         lista_datos.push(DatosPdf {
             ccoo,
             organismo,
+            organismos_extra,
             patrimonial,
             fecha,
-            resultado: resultado.clone(),
+            resultado,
+            carpeta,
+            archivo,
+            origen: Some(path.clone()),
         });
 
-        // Mover archivo según resultado
-        let destino = if resultado == "Sin novedad" {
-            dir_procesados.join(archivo_pdf)
-        } else {
-            dir_revisar.join(archivo_pdf)
-        };
-
         if let Err(e) = fs::rename(&path, &destino) {
-            eprintln!("Error al mover archivo {}: {}", archivo_pdf, e);
+            registrar!(
+                "No se pudo mover {} (se reintentará al final): {}",
+                archivo_pdf,
+                e
+            );
+            pendientes.push((path.clone(), destino));
         }
     }
 
-    Ok(lista_datos)
+    // Reintentar una vez los archivos bloqueados; el usuario pudo haberlos cerrado
+    let mut archivos_bloqueados = Vec::new();
+    if !pendientes.is_empty() {
+        std::thread::sleep(Duration::from_secs(1));
+
+        for (origen, destino) in pendientes {
+            if let Err(e) = fs::rename(&origen, &destino) {
+                let nombre = origen
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                registrar!("Archivo bloqueado, no se pudo mover {}: {}", nombre, e);
+                archivos_bloqueados.push(nombre);
+            }
+        }
+    }
+
+    let resultado = ResultadoProcesamiento {
+        datos: lista_datos,
+        archivos_bloqueados,
+        archivos_protegidos,
+        archivos_fallidos,
+    };
+    // Solo extraer no toca la carpeta, y una corrida parcial no pisa el resumen completo
+    if opciones.mover && opciones.archivos.is_none() {
+        let ruta_resumen = ruta_archivos.join(ARCHIVO_RESUMEN);
+        if let Err(e) = fs::write(&ruta_resumen, resultado.resumen().texto()) {
+            registrar!("No se pudo guardar el resumen en {:?}: {}", ruta_resumen, e);
+        }
+    }
+    Ok(resultado)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_processor::CarpetaDePrueba;
+
+    fn fecha(anio: i32, mes: u32, dia: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(anio, mes, dia)
+    }
+
+    #[test]
+    fn convertir_fecha_pdf_sin_prefijo_ni_zona() {
+        assert_eq!(convertir_fecha_pdf("20230115103000"), fecha(2023, 1, 15));
+    }
+
+    #[test]
+    fn convertir_fecha_pdf_con_prefijo() {
+        assert_eq!(convertir_fecha_pdf("D:20230115103000"), fecha(2023, 1, 15));
+        assert_eq!(
+            convertir_fecha_pdf(" D:20230115103000 "),
+            fecha(2023, 1, 15)
+        );
+    }
+
+    #[test]
+    fn convertir_fecha_pdf_con_offset_de_buenos_aires() {
+        assert_eq!(
+            convertir_fecha_pdf("D:20230115233000-03'00'"),
+            fecha(2023, 1, 15)
+        );
+    }
+
+    #[test]
+    fn convertir_fecha_pdf_convierte_a_hora_de_buenos_aires() {
+        // 01:30 UTC del 15 son las 22:30 del 14 en Buenos Aires
+        assert_eq!(convertir_fecha_pdf("D:20230115013000Z"), fecha(2023, 1, 14));
+        assert_eq!(
+            convertir_fecha_pdf("D:20230115013000+00'00'"),
+            fecha(2023, 1, 14)
+        );
+        // 23:30 en UTC-5 son las 01:30 del día siguiente en Buenos Aires
+        assert_eq!(
+            convertir_fecha_pdf("D:20230115233000-05'00'"),
+            fecha(2023, 1, 16)
+        );
+        assert_eq!(
+            convertir_fecha_pdf("D:20230115013000+02"),
+            fecha(2023, 1, 14)
+        );
+    }
+
+    #[test]
+    fn convertir_fecha_pdf_zona_invalida_se_ignora() {
+        assert_eq!(
+            convertir_fecha_pdf("D:20230115013000+xx"),
+            fecha(2023, 1, 15)
+        );
+    }
+
+    #[test]
+    fn convertir_fecha_pdf_cadenas_cortas_o_invalidas() {
+        assert_eq!(convertir_fecha_pdf(""), None);
+        assert_eq!(convertir_fecha_pdf("D:"), None);
+        assert_eq!(convertir_fecha_pdf("D:20230115"), None);
+        assert_eq!(convertir_fecha_pdf("D:2023011510300"), None);
+        assert_eq!(convertir_fecha_pdf("D:20231315103000"), None);
+        assert_eq!(convertir_fecha_pdf("D:2023011510300é"), None);
+    }
+
+    #[test]
+    fn extraer_fecha_pdf_usa_la_primera_clave_disponible() {
+        let mut doc = Document::with_version("1.5");
+        let mut info_dict = lopdf::Dictionary::new();
+        info_dict.set(
+            "CreationDate",
+            lopdf::Object::string_literal("D:20230110090000"),
+        );
+        let info = doc.add_object(info_dict);
+        doc.trailer.set("Info", info);
+
+        // Sin ModDate se usa la fecha de creación como respaldo
+        let creacion = NaiveDate::from_ymd_opt(2023, 1, 10);
+        assert_eq!(
+            extraer_fecha_pdf(&doc, FuenteFecha::Modificacion.claves()),
+            creacion
+        );
+
+        doc.get_dictionary_mut(info)
+            .unwrap()
+            .set("ModDate", lopdf::Object::string_literal("D:20230220090000"));
+        assert_eq!(
+            extraer_fecha_pdf(&doc, FuenteFecha::Modificacion.claves()),
+            NaiveDate::from_ymd_opt(2023, 2, 20)
+        );
+        assert_eq!(
+            extraer_fecha_pdf(&doc, FuenteFecha::Creacion.claves()),
+            creacion
+        );
+        assert_eq!(extraer_fecha_pdf(&doc, &[b"Otra"]), None);
+    }
+
+    #[test]
+    fn decodificar_texto_pdf_windows_1252() {
+        assert_eq!(decodificar_texto_pdf("Dirección".as_bytes()), "Dirección");
+        assert_eq!(
+            decodificar_texto_pdf(b"\x93Direcci\xf3n\x94 \x96 Compa\xf1\xeda"),
+            "\u{201c}Dirección\u{201d} \u{2013} Compañía"
+        );
+    }
+
+    #[test]
+    fn extraer_patrimonial_formato_canonico() {
+        let texto = "Se informa el bien 1.23.4.5.678.9.0 del inventario";
+        assert_eq!(
+            extraer_patrimonial(texto),
+            Some("1.23.4.5.678.9.0".to_string())
+        );
+    }
+
+    #[test]
+    fn extraer_patrimonial_sin_codigo() {
+        assert_eq!(extraer_patrimonial("Sin novedad en el inventario"), None);
+        assert_eq!(extraer_patrimonial(""), None);
+    }
+
+    #[test]
+    fn extraer_patrimonial_con_mas_digitos() {
+        assert_eq!(
+            extraer_patrimonial("Bien 1.23.4.5.123456.12.3"),
+            Some("1.23.4.5.123456.12.3".to_string())
+        );
+        // No recortar el último segmento aunque el canónico coincida con el prefijo
+        assert_eq!(
+            extraer_patrimonial("Bien 1.23.4.5.12345.1.12"),
+            Some("1.23.4.5.12345.1.12".to_string())
+        );
+    }
+
+    #[test]
+    fn extraer_patrimonial_con_multiples_codigos() {
+        let texto = "Códigos 1.23.4.5.678.9.0 y 2.34.5.6.789.1.2";
+        assert_eq!(
+            extraer_patrimonial(texto),
+            Some("1.23.4.5.678.9.0".to_string())
+        );
+        assert_eq!(
+            extraer_patrimoniales_todos(texto),
+            vec![
+                "1.23.4.5.678.9.0".to_string(),
+                "2.34.5.6.789.1.2".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn clasificar_resultado_sin_novedad() {
+        let muestras = [
+            "Por la presente se informa que no existen novedades en el inventario",
+            "El relevamiento se realizó SIN NOVEDAD",
+            "Los bienes se encuentran sin cambios respecto del período anterior",
+            "No registra excedentes ni faltantes a notificar",
+            "Se deja constancia del inventario S/NOVEDAD",
+            "El sector presenta Sin Excedentes y Sin Faltantes",
+        ];
+        for muestra in muestras {
+            assert_eq!(
+                clasificar_resultado(muestra),
+                Resultado::SinNovedad,
+                "{}",
+                muestra
+            );
+        }
+    }
+
+    #[test]
+    fn clasificar_resultado_con_novedades() {
+        let muestras = [
+            "Se detectaron faltantes en el inventario del sector",
+            "Se adjunta el listado de bienes excedentes",
+        ];
+        for muestra in muestras {
+            assert_eq!(
+                clasificar_resultado(muestra),
+                Resultado::ConNovedades,
+                "{}",
+                muestra
+            );
+        }
+    }
+
+    #[test]
+    fn extraer_resultado_detallado_indica_el_patron() {
+        let (resultado, detalle) =
+            extraer_resultado_detallado("El relevamiento del sector se realizó SIN NOVEDAD alguna");
+        assert_eq!(resultado, Resultado::SinNovedad);
+        assert_eq!(
+            detalle.as_deref(),
+            Some("sinnovedad en \"…relevamientodelsectorserealizóSINNOVEDADalguna\"")
+        );
+
+        let (resultado, detalle) =
+            extraer_resultado_detallado("Se detectaron faltantes en el inventario del sector");
+        assert_eq!(resultado, Resultado::ConNovedades);
+        assert_eq!(detalle, None);
+    }
+
+    #[test]
+    fn clasificar_resultado_texto_insuficiente() {
+        assert_eq!(clasificar_resultado(""), Resultado::RevisionManual);
+        assert_eq!(clasificar_resultado("   \n  "), Resultado::RevisionManual);
+        assert_eq!(
+            clasificar_resultado("sin novedad"),
+            Resultado::RevisionManual
+        );
+    }
+
+    #[test]
+    fn extraer_resultado_devuelve_el_texto_del_excel() {
+        assert_eq!(
+            extraer_resultado("El inventario del período resultó sin novedad"),
+            "Sin novedad"
+        );
+        assert_eq!(
+            extraer_resultado("El inventario del período registra faltantes"),
+            "Con novedades (ver)"
+        );
+        assert_eq!(extraer_resultado(""), "Revisar manualmente");
+    }
+
+    #[test]
+    fn resultado_desde_texto() {
+        assert_eq!(
+            Resultado::desde_texto(" sin novedad "),
+            Resultado::SinNovedad
+        );
+        assert_eq!(
+            Resultado::desde_texto("Con novedades (ver)"),
+            Resultado::ConNovedades
+        );
+        assert_eq!(
+            Resultado::desde_texto("Revisar manualmente"),
+            Resultado::RevisionManual
+        );
+        assert_eq!(Resultado::desde_texto(""), Resultado::ConNovedades);
+    }
+
+    #[test]
+    fn devolver_a_origen_deshace_el_movimiento_aunque_se_haya_reclasificado() {
+        let base = CarpetaDePrueba::nueva("devolver");
+        fs::create_dir_all(base.join("sub")).unwrap();
+        fs::create_dir_all(base.join(CARPETA_REVISAR)).unwrap();
+        fs::write(base.join(CARPETA_REVISAR).join("NO-1.pdf"), b"%PDF").unwrap();
+
+        let mut movido = dato("NO-1", "DGIN", Resultado::ConNovedades);
+        movido.carpeta = CARPETA_REVISAR.to_string();
+        movido.origen = Some(base.join("sub").join("NO-1.pdf"));
+        reclasificar(&base, &mut movido, Resultado::SinNovedad).unwrap();
+        // Sin mover no hay nada que devolver
+        let sin_mover = dato("NO-2", "DGIN", Resultado::SinNovedad);
+
+        assert!(devolver_a_origen(&base, &[movido, sin_mover]).is_empty());
+        assert!(base.join("sub").join("NO-1.pdf").exists());
+        assert!(!base.join(CARPETA_PROCESADOS).join("NO-1.pdf").exists());
+    }
+
+    #[test]
+    fn reclasificar_mueve_el_pdf_a_la_carpeta_del_nuevo_resultado() {
+        let base = CarpetaDePrueba::nueva("reclasificar");
+        fs::create_dir_all(base.join(CARPETA_REVISAR)).unwrap();
+        fs::write(base.join(CARPETA_REVISAR).join("NO-1.pdf"), b"%PDF").unwrap();
+
+        let mut dato = DatosPdf {
+            ccoo: "NO-1".to_string(),
+            organismo: String::new(),
+            organismos_extra: Vec::new(),
+            patrimonial: String::new(),
+            fecha: None,
+            resultado: Resultado::ConNovedades,
+            carpeta: CARPETA_REVISAR.to_string(),
+            archivo: "NO-1.pdf".to_string(),
+            origen: None,
+        };
+        reclasificar(&base, &mut dato, Resultado::SinNovedad).unwrap();
+
+        assert_eq!(dato.resultado, Resultado::SinNovedad);
+        assert_eq!(dato.carpeta, CARPETA_PROCESADOS);
+        assert!(base.join(CARPETA_PROCESADOS).join("NO-1.pdf").exists());
+        assert!(!base.join(CARPETA_REVISAR).join("NO-1.pdf").exists());
+    }
+
+    #[test]
+    fn opciones_incluye_solo_los_archivos_elegidos() {
+        let todos = OpcionesProcesamiento::default();
+        assert!(todos.incluye(Path::new("x/NO-1.pdf")));
+
+        let elegidos = OpcionesProcesamiento {
+            archivos: Some(vec!["NO-1".to_string(), " no-2.pdf ".to_string()]),
+            ..Default::default()
+        };
+        assert!(elegidos.incluye(Path::new("x/NO-1.pdf")));
+        assert!(elegidos.incluye(Path::new("x/NO-2.pdf")));
+        assert!(!elegidos.incluye(Path::new("x/NO-3.pdf")));
+    }
+
+    #[test]
+    fn validar_patrimonial_normaliza_separadores() {
+        assert_eq!(
+            validar_patrimonial("1.23.4.5.678.9.0"),
+            Some("1.23.4.5.678.9.0".to_string())
+        );
+        assert_eq!(
+            validar_patrimonial(" 1. 23 ,4-5.12 345.1.12 "),
+            Some("1.23.4.5.12345.1.12".to_string())
+        );
+    }
+
+    #[test]
+    fn validar_patrimonial_rechaza_formatos_invalidos() {
+        assert_eq!(validar_patrimonial("1.234.4.5.678.9.0"), None);
+        assert_eq!(validar_patrimonial("1.23.4.5.67.9.0"), None);
+        assert_eq!(validar_patrimonial("1.23.4.5.678.9"), None);
+        assert_eq!(validar_patrimonial("1.23.a.5.678.9.0"), None);
+        assert_eq!(validar_patrimonial(""), None);
+    }
+
+    #[test]
+    fn extraer_patrimonial_validado_con_separadores_irregulares() {
+        assert_eq!(
+            extraer_patrimonial_validado("Bien 1, 23. 4.5.678 .9.0 del inventario"),
+            Some("1.23.4.5.678.9.0".to_string())
+        );
+        assert_eq!(extraer_patrimonial_validado("Expediente 2024-123-45"), None);
+    }
+
+    #[test]
+    fn resumen_cuenta_resultados_y_lista_los_de_revisar() {
+        let resultado = ResultadoProcesamiento {
+            datos: vec![
+                dato("NO-1", "", Resultado::SinNovedad),
+                dato("NO-2", "", Resultado::ConNovedades),
+                dato("NO-3", "", Resultado::RevisionManual),
+                dato("NO-4", "", Resultado::ConNovedades),
+            ],
+            archivos_bloqueados: Vec::new(),
+            archivos_protegidos: vec!["NO-5.pdf".to_string()],
+            archivos_fallidos: vec!["NO-6.pdf".to_string()],
+        };
+
+        let resumen = resultado.resumen();
+        assert_eq!(
+            resumen,
+            ResumenProcesamiento {
+                total: 6,
+                sin_novedad: 1,
+                con_novedades: 2,
+                ilegibles: 1,
+                fallidos: 2,
+                revisar: vec!["NO-2".to_string(), "NO-4".to_string()],
+            }
+        );
+        assert!(resumen.texto().contains("Con novedades: 2\n"));
+        assert!(resumen.texto().ends_with("Revisar:\n- NO-2\n- NO-4\n"));
+    }
+
+    #[test]
+    fn proporcion_basura_cuenta_reemplazos_y_uso_privado() {
+        assert_eq!(proporcion_basura(""), 0.0);
+        assert_eq!(proporcion_basura("Sin novedad"), 0.0);
+        assert_eq!(proporcion_basura("ab\u{FFFD}\u{E001}"), 0.5);
+    }
+
+    #[test]
+    fn elegir_menos_basura_prefiere_texto_limpio_y_no_vacio() {
+        let limpio = "Sin novedad".to_string();
+        let roto = "S\u{FFFD}n nov\u{FFFD}dad".to_string();
+        assert_eq!(elegir_menos_basura(roto.clone(), limpio.clone()), limpio);
+        assert_eq!(elegir_menos_basura(limpio.clone(), roto.clone()), limpio);
+        assert_eq!(elegir_menos_basura(String::new(), roto.clone()), roto);
+    }
 }
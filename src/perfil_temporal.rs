@@ -0,0 +1,337 @@
+//! Limpieza de los perfiles temporales de Chrome
+//!
+//! Cada corrida crea un perfil en la carpeta temporal del sistema y lo borra al
+//! cerrar el navegador. Si Windows todavía tiene archivos bloqueados y el borrado
+//! falla, la ruta se anota en un archivo para reintentarlo en el próximo arranque.
+//! Al arrancar también se borran los perfiles viejos que hayan quedado sin anotar,
+//! salvo que otra instancia o su Chrome todavía los estén usando.
+
+use crate::config::Config;
+use crate::registro::registrar;
+use anyhow::{Context, Result};
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+use walkdir::WalkDir;
+
+/// Prefijo de los perfiles de la descarga de comunicaciones
+pub(crate) const PREFIJO_PERFIL_DESCARGA: &str = "auto_sade_profile_";
+
+/// Prefijo de los perfiles de la búsqueda de comunicaciones sin organismo
+pub(crate) const PREFIJO_PERFIL_BUSQUEDA: &str = "busqueda_ccoo_profile_";
+
+/// Antigüedad a partir de la cual un perfil sin uso se considera abandonado; los
+/// más nuevos pueden estar recién creados por otra instancia que todavía no lo marcó
+const ANTIGUEDAD_HUERFANO: Duration = Duration::from_secs(60 * 60);
+
+/// Antigüedad a partir de la cual un `SingletonLock` cuyo proceso no se puede
+/// consultar se considera dejado por un Chrome que se cerró de golpe
+const ANTIGUEDAD_SINGLETON_LOCK: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Archivo (en la carpeta temporal) con los perfiles que no se pudieron borrar
+const ARCHIVO_HUERFANOS: &str = "auto_sade_perfiles_huerfanos.txt";
+
+/// Archivo dentro del perfil que la instancia dueña mantiene bloqueado mientras lo usa
+const ARCHIVO_EN_USO: &str = "auto_sade_en_uso.lock";
+
+/// Perfil temporal en uso por esta instancia: mientras exista, el bloqueo de
+/// `ARCHIVO_EN_USO` evita que la limpieza de otra instancia lo borre
+pub(crate) struct PerfilEnUso {
+    ruta: PathBuf,
+    bloqueo: File,
+}
+
+impl PerfilEnUso {
+    /// Marca como en uso un perfil recién creado
+    pub(crate) fn marcar(ruta: PathBuf) -> Result<Self> {
+        let bloqueo = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(ruta.join(ARCHIVO_EN_USO))
+            .and_then(|archivo| archivo.try_lock_exclusive().map(|()| archivo))
+            .with_context(|| format!("No se pudo marcar en uso el perfil {:?}", ruta))?;
+        Ok(PerfilEnUso { ruta, bloqueo })
+    }
+
+    pub(crate) fn ruta(&self) -> &Path {
+        &self.ruta
+    }
+
+    /// Deja el perfil marcado hasta que termine el proceso, para el navegador que
+    /// queda abierto a pedido del usuario
+    pub(crate) fn conservar(self) {
+        std::mem::forget(self.bloqueo);
+    }
+}
+
+/// Indica si otra instancia o un Chrome abierto están usando el perfil
+fn perfil_en_uso(perfil: &Path) -> bool {
+    let bloqueado_por_instancia = File::open(perfil.join(ARCHIVO_EN_USO)).is_ok_and(|archivo| {
+        let libre = archivo.try_lock_exclusive().is_ok();
+        if libre {
+            let _ = archivo.unlock();
+        }
+        !libre
+    });
+    // Chrome en Windows tiene `lockfile` abierto sin compartir mientras corre
+    let lockfile = perfil.join("lockfile");
+    let chrome_windows =
+        lockfile.exists() && OpenOptions::new().write(true).open(&lockfile).is_err();
+    bloqueado_por_instancia || chrome_windows || singleton_lock_activo(perfil)
+}
+
+/// En Linux y macOS Chrome deja el enlace `SingletonLock` (`<equipo>-<pid>`)
+/// mientras corre, pero también si se cierra de golpe: solo cuenta si el proceso
+/// sigue vivo. Sin `/proc` para consultarlo se confía en la antigüedad del enlace.
+fn singleton_lock_activo(perfil: &Path) -> bool {
+    let enlace = perfil.join("SingletonLock");
+    let Ok(destino) = fs::read_link(&enlace) else {
+        return false;
+    };
+    let pid = destino
+        .to_string_lossy()
+        .rsplit_once('-')
+        .and_then(|(_, pid)| pid.parse::<u32>().ok());
+    let proc = Path::new("/proc");
+    match pid {
+        Some(pid) if proc.is_dir() => proc.join(pid.to_string()).exists(),
+        _ => fs::symlink_metadata(&enlace)
+            .and_then(|m| m.modified())
+            .is_ok_and(|creado| creado.elapsed().unwrap_or_default() < ANTIGUEDAD_SINGLETON_LOCK),
+    }
+}
+
+/// Ruta para un perfil nuevo: prefijo, usuario, instancia (si hay) y un sello en
+/// nanosegundos, para que dos instancias en paralelo nunca compartan perfil
+fn ruta_perfil(prefijo: &str, config: &Config, usuario_id: &str) -> Result<PathBuf> {
+    let usuario: String = config
+        .nombre_usuario(usuario_id)
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+    let instancia = config
+        .instancia_id
+        .map(|n| format!("i{}_", n))
+        .unwrap_or_default();
+    let sello = SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_nanos();
+    Ok(std::env::temp_dir().join(format!("{}{}_{}{}", prefijo, usuario, instancia, sello)))
+}
+
+/// Crea un perfil nuevo con traducción y gestor de contraseñas desactivados y
+/// descargas sin confirmación en `ruta_descargas`
+pub(crate) fn crear_perfil(
+    prefijo: &str,
+    ruta_descargas: &Path,
+    config: &Config,
+    usuario_id: &str,
+) -> Result<PathBuf> {
+    let perfil = ruta_perfil(prefijo, config, usuario_id)?;
+    let default_dir = perfil.join("Default");
+    fs::create_dir_all(&default_dir).context("No se pudo crear directorio del perfil")?;
+    fs::write(
+        default_dir.join("Preferences"),
+        preferencias_chrome(ruta_descargas).to_string(),
+    )
+    .context("No se pudo escribir archivo de preferencias")?;
+    Ok(perfil)
+}
+
+/// Contenido del archivo `Preferences` del perfil
+fn preferencias_chrome(ruta_descargas: &Path) -> serde_json::Value {
+    serde_json::json!({
+        "translate": { "enabled": false },
+        "profile": {
+            "password_manager_enabled": false,
+            "default_content_setting_values": { "automatic_downloads": 1 }
+        },
+        "download": {
+            "default_directory": ruta_descargas.display().to_string(),
+            "prompt_for_download": false
+        },
+        "credentials_enable_service": false
+    })
+}
+
+fn ruta_archivo_huerfanos() -> PathBuf {
+    std::env::temp_dir().join(ARCHIVO_HUERFANOS)
+}
+
+/// Anota un perfil que no se pudo borrar para limpiarlo en el próximo arranque
+fn anotar_huerfano(perfil: &Path) {
+    let resultado = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(ruta_archivo_huerfanos())
+        .and_then(|mut archivo| writeln!(archivo, "{}", perfil.display()));
+    if let Err(e) = resultado {
+        registrar!("No se pudo anotar el perfil huérfano {:?}: {}", perfil, e);
+    }
+}
+
+/// Libera el perfil temporal y lo borra con los reintentos y la espera de la
+/// configuración. Si no se logra, lo anota para `limpiar_perfiles_huerfanos`.
+pub(crate) async fn eliminar_perfil(en_uso: PerfilEnUso, config: &Config) {
+    let PerfilEnUso { ruta, bloqueo } = en_uso;
+    // Windows no borra un archivo abierto: se suelta el bloqueo antes
+    drop(bloqueo);
+    let perfil = ruta.as_path();
+    let intentos = config.reintentos_limpieza_perfil.max(1);
+    for intento in 1..=intentos {
+        match fs::remove_dir_all(perfil) {
+            Ok(()) => return,
+            Err(e) if intento == intentos => {
+                registrar!(
+                    "Advertencia: No se pudo limpiar el perfil temporal tras {} intentos: {}",
+                    intentos,
+                    e
+                );
+                anotar_huerfano(perfil);
+            }
+            Err(_) => sleep(config.espera_limpieza_perfil).await,
+        }
+    }
+}
+
+/// Tamaño total en bytes de los archivos de una carpeta
+fn tamano_carpeta(carpeta: &Path) -> u64 {
+    WalkDir::new(carpeta)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Borra una carpeta y devuelve cuántos bytes liberó, o `None` si no se pudo
+fn borrar_perfil(perfil: &Path) -> Option<u64> {
+    let tamano = tamano_carpeta(perfil);
+    fs::remove_dir_all(perfil).ok().map(|()| tamano)
+}
+
+/// Perfiles de la carpeta temporal con más de `ANTIGUEDAD_HUERFANO` de antigüedad
+/// que ninguna instancia ni ningún Chrome están usando
+fn perfiles_abandonados() -> Vec<PathBuf> {
+    let Ok(entradas) = fs::read_dir(std::env::temp_dir()) else {
+        return Vec::new();
+    };
+    let limite = SystemTime::now() - ANTIGUEDAD_HUERFANO;
+    entradas
+        .flatten()
+        .filter(|e| {
+            let nombre = e.file_name();
+            let nombre = nombre.to_string_lossy();
+            nombre.starts_with(PREFIJO_PERFIL_DESCARGA)
+                || nombre.starts_with(PREFIJO_PERFIL_BUSQUEDA)
+        })
+        .filter(|e| {
+            e.metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|modificado| modificado < limite)
+        })
+        .map(|e| e.path())
+        .filter(|p| p.is_dir() && !perfil_en_uso(p))
+        .collect()
+}
+
+/// Borra los perfiles que quedaron anotados en corridas anteriores y los de la
+/// carpeta temporal con más de una hora que nadie usa, ignorando los que no se
+/// puedan borrar.
+/// Los anotados que siguen sin poder borrarse quedan para la próxima vez.
+pub fn limpiar_perfiles_huerfanos() {
+    let mut borrados = 0;
+    let mut liberados: u64 = 0;
+
+    let ruta = ruta_archivo_huerfanos();
+    let contenido = fs::read_to_string(&ruta).unwrap_or_default();
+    let mut pendientes = Vec::new();
+    for linea in contenido.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let perfil = Path::new(linea);
+        if !perfil.exists() {
+            continue;
+        }
+        if perfil_en_uso(perfil) {
+            pendientes.push(linea);
+            continue;
+        }
+        match borrar_perfil(perfil) {
+            Some(bytes) => {
+                borrados += 1;
+                liberados += bytes;
+            }
+            None => pendientes.push(linea),
+        }
+    }
+
+    for perfil in perfiles_abandonados() {
+        if let Some(bytes) = borrar_perfil(&perfil) {
+            borrados += 1;
+            liberados += bytes;
+        }
+    }
+
+    let resultado = if !pendientes.is_empty() {
+        fs::write(&ruta, pendientes.join("\n") + "\n")
+    } else if ruta.exists() {
+        fs::remove_file(&ruta)
+    } else {
+        Ok(())
+    };
+    if let Err(e) = resultado {
+        registrar!("No se pudo actualizar {:?}: {}", ruta, e);
+    }
+    if borrados > 0 {
+        registrar!(
+            "Perfiles temporales huérfanos eliminados: {} ({:.1} MB liberados)",
+            borrados,
+            liberados as f64 / (1024.0 * 1024.0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_processor::CarpetaDePrueba;
+
+    #[test]
+    fn perfil_en_uso_mientras_lo_marca_una_instancia_o_chrome() {
+        let perfil = CarpetaDePrueba::nueva("en_uso");
+        assert!(!perfil_en_uso(&perfil));
+
+        let en_uso = PerfilEnUso::marcar(perfil.to_path_buf()).unwrap();
+        assert!(perfil_en_uso(&perfil));
+        drop(en_uso);
+        assert!(!perfil_en_uso(&perfil));
+    }
+
+    #[test]
+    fn preferencias_chrome_escapa_la_ruta_de_descargas() {
+        let ruta = r#"C:\Users\Ana "AS"\Downloads"#;
+        let texto = preferencias_chrome(Path::new(ruta)).to_string();
+        let leidas: serde_json::Value = serde_json::from_str(&texto).unwrap();
+        assert_eq!(leidas["download"]["default_directory"], ruta);
+        assert_eq!(leidas["translate"]["enabled"], false);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn singleton_lock_solo_cuenta_si_el_proceso_vive() {
+        let perfil = CarpetaDePrueba::nueva("singleton");
+        let enlace = perfil.join("SingletonLock");
+
+        std::os::unix::fs::symlink(format!("equipo-{}", std::process::id()), &enlace).unwrap();
+        assert!(perfil_en_uso(&perfil));
+
+        // Un Chrome que se cerró de golpe deja el enlace con un pid que ya no existe
+        fs::remove_file(&enlace).unwrap();
+        std::os::unix::fs::symlink(format!("equipo-{}", u32::MAX), &enlace).unwrap();
+        assert!(!perfil_en_uso(&perfil));
+    }
+}
@@ -0,0 +1,65 @@
+//! Registro persistente de cada corrida en archivos de texto
+//!
+//! Los mensajes se agregan a `<ruta_archivos>/logs/auto-sade_<fecha>.log`,
+//! un archivo por día. Mientras no se llame a `inicializar` no se escribe nada.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Carpeta donde se guardan los archivos de log
+static DIRECTORIO_LOGS: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Escribe un mensaje en stderr y en el archivo de log del día
+macro_rules! registrar {
+    ($($arg:tt)*) => {{
+        let mensaje = format!($($arg)*);
+        eprintln!("{}", mensaje);
+        $crate::registro::escribir(&mensaje);
+    }};
+}
+pub(crate) use registrar;
+
+/// Define la carpeta base de los logs (se usa su subcarpeta `logs`)
+pub fn inicializar(ruta_base: &Path) {
+    let directorio = ruta_base.join("logs");
+    if let Err(e) = std::fs::create_dir_all(&directorio) {
+        eprintln!(
+            "No se pudo crear la carpeta de logs {:?}: {}",
+            directorio, e
+        );
+        return;
+    }
+    if let Ok(mut dir) = DIRECTORIO_LOGS.lock() {
+        *dir = Some(directorio);
+    }
+}
+
+/// Agrega una línea con fecha y hora al archivo de log del día
+pub fn escribir(mensaje: &str) {
+    let directorio = match DIRECTORIO_LOGS.lock().ok().and_then(|d| d.clone()) {
+        Some(d) => d,
+        None => return,
+    };
+
+    let ahora = chrono::Local::now();
+    let ruta = directorio.join(format!("auto-sade_{}.log", ahora.format("%Y-%m-%d")));
+
+    let resultado = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&ruta)
+        .and_then(|mut archivo| {
+            writeln!(
+                archivo,
+                "[{}] {}",
+                ahora.format("%Y-%m-%d %H:%M:%S"),
+                mensaje
+            )
+        });
+
+    if let Err(e) = resultado {
+        eprintln!("No se pudo escribir en el log {:?}: {}", ruta, e);
+    }
+}
@@ -0,0 +1,235 @@
+//! Localización de elementos de la página de SADE
+//!
+//! Los elementos se buscan por su texto o por estar visibles y habilitados, en lugar
+//! de por su posición en la lista, que se desplaza cuando SADE agrega un botón.
+//! La posición histórica queda como respaldo y su uso se registra en el log.
+//!
+//! Los selectores CSS se leen de `selectores.json` junto al ejecutable (o del archivo
+//! indicado en `SADE_SELECTORES`), para ajustarlos sin recompilar cuando SADE
+//! actualiza ZK. Las claves que falten toman el valor embebido.
+
+use crate::config::obtener_directorio_exe;
+use crate::registro::registrar;
+use anyhow::{Context, Result};
+use chromiumoxide::element::Element;
+use chromiumoxide::Page;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Archivo de selectores que se busca junto al ejecutable
+const ARCHIVO_SELECTORES: &str = "selectores.json";
+
+/// Selectores CSS de los elementos de SADE que usa la automatización
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub(crate) struct Selectores {
+    /// Campos de usuario y contraseña del login
+    pub campo_login: String,
+    /// Botón "Ingresar" del login
+    pub boton_login: String,
+    /// Pestañas de la aplicación ("Bandeja CO", ...)
+    pub pestana: String,
+    /// Texto "[ 1 - 100 / 250 ]" del paginador
+    pub info_paginador: String,
+    /// Botones sin borde de la bandeja ("Ver 100", detalle)
+    pub boton_sin_caja: String,
+    /// Botón de página siguiente
+    pub pagina_siguiente: String,
+    /// Botón de página anterior
+    pub pagina_anterior: String,
+    /// Página siguiente del paginador de adjuntos, dentro de la ventana de detalle
+    pub pagina_siguiente_adjuntos: String,
+    /// Lupa que abre el detalle de una comunicación
+    pub icono_detalle: String,
+    /// Ícono de descarga de un adjunto
+    pub icono_descarga: String,
+    /// Botones con borde ("Volver")
+    pub boton: String,
+    /// Ícono de cerrar sesión del encabezado
+    pub cerrar_sesion: String,
+    /// Campo del número de comunicación en la búsqueda
+    pub campo_busqueda: String,
+    /// Botones de la pantalla de búsqueda ("Buscar")
+    pub boton_busqueda: String,
+    /// Botón de detalle del resultado de la búsqueda
+    pub detalle_busqueda: String,
+}
+
+impl Default for Selectores {
+    fn default() -> Self {
+        Self {
+            campo_login: ".form-control.z-textbox".into(),
+            boton_login: ".btn.btn-default.z-button".into(),
+            pestana: ".z-tab-text".into(),
+            info_paginador: ".z-paging-info".into(),
+            boton_sin_caja: ".boton-sin-caja.z-button".into(),
+            pagina_siguiente: ".z-paging-button.z-paging-next".into(),
+            pagina_anterior: ".z-paging-button.z-paging-prev".into(),
+            pagina_siguiente_adjuntos: ".z-window .z-paging-button.z-paging-next".into(),
+            icono_detalle: ".z-icon-search.z-span".into(),
+            icono_descarga: ".z-icon-download".into(),
+            boton: ".btn.z-button".into(),
+            cerrar_sesion: ".z-icon-sign-out.texto-header-unificado.z-span".into(),
+            campo_busqueda: ".z-textbox".into(),
+            boton_busqueda: ".z-button".into(),
+            detalle_busqueda: ".boton-sin-caja.z-button:has(.z-icon-search)".into(),
+        }
+    }
+}
+
+impl Selectores {
+    /// Lee los selectores de un archivo JSON; las claves ausentes quedan con el default
+    fn desde_archivo(ruta: &Path) -> Result<Self> {
+        let contenido = std::fs::read_to_string(ruta)
+            .with_context(|| format!("No se pudo leer {}", ruta.display()))?;
+        serde_json::from_str(&contenido)
+            .with_context(|| format!("{} no es un JSON de selectores válido", ruta.display()))
+    }
+}
+
+/// Ruta del archivo de selectores: `SADE_SELECTORES` o `selectores.json` junto al ejecutable
+fn ruta_selectores() -> PathBuf {
+    std::env::var("SADE_SELECTORES")
+        .ok()
+        .filter(|r| !r.trim().is_empty())
+        .map(|r| PathBuf::from(r.trim()))
+        .unwrap_or_else(|| obtener_directorio_exe().join(ARCHIVO_SELECTORES))
+}
+
+/// Selectores en uso, cargados la primera vez que se piden. Si el archivo no existe
+/// se usan los embebidos; si tiene errores, se registra y también se usan los embebidos
+pub(crate) fn actuales() -> &'static Selectores {
+    static SELECTORES: OnceLock<Selectores> = OnceLock::new();
+    SELECTORES.get_or_init(|| {
+        let ruta = ruta_selectores();
+        if !ruta.exists() {
+            return Selectores::default();
+        }
+        match Selectores::desde_archivo(&ruta) {
+            Ok(selectores) => {
+                registrar!("Selectores cargados de {}", ruta.display());
+                selectores
+            }
+            Err(e) => {
+                registrar!("{:#}; se usan los selectores embebidos", e);
+                Selectores::default()
+            }
+        }
+    })
+}
+
+/// Texto normalizado para comparar: minúsculas y espacios simples
+fn normalizar(texto: &str) -> String {
+    texto
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Indica si el elemento se ve en pantalla y no está deshabilitado
+async fn es_visible(elemento: &Element) -> bool {
+    elemento
+        .call_js_fn(
+            "function() { return this.offsetParent !== null && !this.hasAttribute('disabled'); }",
+            false,
+        )
+        .await
+        .ok()
+        .and_then(|r| r.result.value)
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+/// Primer elemento del selector cuyo texto visible coincide con alguno de `textos`
+/// (sin distinguir mayúsculas ni espacios)
+pub(crate) async fn buscar_por_texto(
+    page: &Page,
+    selector: &str,
+    textos: &[&str],
+) -> Result<Option<Element>> {
+    let buscados: Vec<String> = textos.iter().map(|t| normalizar(t)).collect();
+    for elemento in page.find_elements(selector).await? {
+        let texto = elemento
+            .inner_text()
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        if buscados.contains(&normalizar(&texto)) {
+            return Ok(Some(elemento));
+        }
+    }
+    Ok(None)
+}
+
+/// Primer elemento del selector que está visible y habilitado
+pub(crate) async fn primer_visible(page: &Page, selector: &str) -> Result<Option<Element>> {
+    for elemento in page.find_elements(selector).await? {
+        if es_visible(&elemento).await {
+            return Ok(Some(elemento));
+        }
+    }
+    Ok(None)
+}
+
+/// Elemento en la posición histórica `indice` del selector, registrando que se
+/// recurrió al respaldo porque no se encontró `descripcion`
+pub(crate) async fn respaldo_por_indice(
+    page: &Page,
+    selector: &str,
+    indice: usize,
+    descripcion: &str,
+) -> Result<Option<Element>> {
+    let elemento = page.find_elements(selector).await?.into_iter().nth(indice);
+    if elemento.is_some() {
+        registrar!(
+            "No se encontró {}; se usa la posición {} de {}",
+            descripcion,
+            indice,
+            selector
+        );
+    }
+    Ok(elemento)
+}
+
+/// Busca el elemento por texto y, si no aparece, usa la posición histórica `indice`
+pub(crate) async fn localizar_por_texto(
+    page: &Page,
+    selector: &str,
+    textos: &[&str],
+    indice: usize,
+) -> Result<Option<Element>> {
+    if let Some(elemento) = buscar_por_texto(page, selector, textos).await? {
+        return Ok(Some(elemento));
+    }
+    let descripcion = format!("\"{}\"", textos.join("\" / \""));
+    respaldo_por_indice(page, selector, indice, &descripcion).await
+}
+
+/// Busca el primer elemento visible y habilitado y, si no hay, usa la posición
+/// histórica `indice`
+pub(crate) async fn localizar_visible(
+    page: &Page,
+    selector: &str,
+    indice: usize,
+) -> Result<Option<Element>> {
+    if let Some(elemento) = primer_visible(page, selector).await? {
+        return Ok(Some(elemento));
+    }
+    respaldo_por_indice(page, selector, indice, "un elemento visible").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claves_ausentes_toman_el_default() {
+        let selectores: Selectores =
+            serde_json::from_str(r#"{ "icono_descarga": ".z-icon-bajar" }"#).unwrap();
+        assert_eq!(selectores.icono_descarga, ".z-icon-bajar");
+        assert_eq!(selectores.campo_login, Selectores::default().campo_login);
+    }
+}
@@ -1,41 +1,151 @@
-use crate::config::Config;
+use crate::config::{Config, Credenciales, BYTES_POR_MB};
+use crate::esperas;
+use crate::perfil_temporal::{crear_perfil, eliminar_perfil, PerfilEnUso, PREFIJO_PERFIL_DESCARGA};
+use crate::registro::registrar;
+use crate::selectores::{
+    self, localizar_por_texto, localizar_visible, primer_visible, respaldo_por_indice,
+};
 use anyhow::{Context, Result};
 use chromiumoxide::browser::{Browser, BrowserConfig};
+use chromiumoxide::cdp::browser_protocol::browser::{
+    DownloadProgressState, EventDownloadProgress, EventDownloadWillBegin,
+    SetDownloadBehaviorBehavior, SetDownloadBehaviorParams,
+};
+use chromiumoxide::detection::{default_executable, DetectionOptions};
+use chromiumoxide::Page;
+use futures::future::join_all;
 use futures::StreamExt;
-use std::path::PathBuf;
-use std::time::Duration;
+use regex::Regex;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
-/// Configura un perfil temporal con preferencias para desactivar traducción
-fn setup_custom_profile() -> Result<PathBuf> {
-    let mut temp_dir = std::env::temp_dir();
-    let unique_id = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)?
-        .as_nanos();
-    temp_dir.push(format!("auto_sade_profile_{}", unique_id));
-
-    let default_dir = temp_dir.join("Default");
-    std::fs::create_dir_all(&default_dir).context("No se pudo crear directorio del perfil")?;
-
-    let prefs_path = default_dir.join("Preferences");
-    let prefs_content = r#"{
-        "translate": { "enabled": false },
-        "profile": { 
-            "password_manager_enabled": false,
-            "default_content_setting_values": { "automatic_downloads": 1 }
-        },
-        "credentials_enable_service": false
-    }"#;
+/// Rutas en que el instalador de Chrome deja el ejecutable en Windows
+fn rutas_chrome_windows() -> Vec<PathBuf> {
+    ["ProgramFiles", "ProgramFiles(x86)", "LocalAppData"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(|base| {
+            PathBuf::from(base)
+                .join("Google")
+                .join("Chrome")
+                .join("Application")
+                .join("chrome.exe")
+        })
+        .collect()
+}
 
-    std::fs::write(prefs_path, prefs_content)
-        .context("No se pudo escribir archivo de preferencias")?;
+/// Busca el ejecutable de Chrome: `SADE_CHROME_PATH`, las rutas típicas de Windows
+/// o la detección de chromiumoxide. Si no aparece, el error dice cómo resolverlo.
+pub fn verificar_chrome() -> Result<PathBuf> {
+    if let Some(ruta) = std::env::var_os("SADE_CHROME_PATH").filter(|r| !r.is_empty()) {
+        let ruta = PathBuf::from(ruta);
+        if !ruta.is_file() {
+            anyhow::bail!(
+                "SADE_CHROME_PATH apunta a {}, que no existe. Corrija la ruta a chrome.exe",
+                ruta.display()
+            );
+        }
+        return Ok(ruta);
+    }
 
-    Ok(temp_dir)
+    rutas_chrome_windows()
+        .into_iter()
+        .find(|r| r.is_file())
+        .or_else(|| default_executable(DetectionOptions::default()).ok())
+        .context(
+            "No se encontró Google Chrome. Instale Google Chrome o defina SADE_CHROME_PATH \
+             con la ruta a chrome.exe",
+        )
 }
 
-/// Espera hasta que no haya archivos .crdownload en la carpeta de descargas
+/// Autoriza las descargas vía CDP y registra las que Chrome cancela o bloquea
+pub(crate) struct MonitorDescargas {
+    bloqueadas: Arc<Mutex<Vec<String>>>,
+    tarea: JoinHandle<()>,
+}
+
+impl MonitorDescargas {
+    /// Permite todas las descargas en `ruta_descargas` (equivale a "Conservar" en los
+    /// avisos de descarga bloqueada) y empieza a escuchar los eventos de progreso
+    pub(crate) async fn iniciar(browser: &Browser, ruta_descargas: &Path) -> Result<Self> {
+        let parametros = SetDownloadBehaviorParams::builder()
+            .behavior(SetDownloadBehaviorBehavior::Allow)
+            .download_path(ruta_descargas.to_string_lossy())
+            .events_enabled(true)
+            .build()
+            .map_err(|e| anyhow::anyhow!("Error al configurar descargas: {}", e))?;
+        browser
+            .execute(parametros)
+            .await
+            .context("No se pudieron autorizar las descargas")?;
+
+        let mut comienzos = browser.event_listener::<EventDownloadWillBegin>().await?;
+        let mut progresos = browser.event_listener::<EventDownloadProgress>().await?;
+        let bloqueadas = Arc::new(Mutex::new(Vec::new()));
+        let registro = Arc::clone(&bloqueadas);
+
+        let tarea = tokio::spawn(async move {
+            // Nombre sugerido de cada descarga, para informar cuál se perdió
+            let mut nombres: HashMap<String, String> = HashMap::new();
+            loop {
+                tokio::select! {
+                    Some(evento) = comienzos.next() => {
+                        nombres.insert(evento.guid.clone(), evento.suggested_filename.clone());
+                    }
+                    Some(evento) = progresos.next() => {
+                        if evento.state == DownloadProgressState::Canceled {
+                            let nombre = nombres
+                                .remove(&evento.guid)
+                                .unwrap_or_else(|| evento.guid.clone());
+                            registrar!("Chrome canceló o bloqueó la descarga de {}", nombre);
+                            if let Ok(mut b) = registro.lock() {
+                                b.push(nombre);
+                            }
+                        } else if evento.state == DownloadProgressState::Completed {
+                            nombres.remove(&evento.guid);
+                        }
+                    }
+                    else => break,
+                }
+            }
+        });
+
+        Ok(Self { bloqueadas, tarea })
+    }
+
+    /// Descargas canceladas o bloqueadas hasta el momento
+    pub(crate) fn bloqueadas(&self) -> Vec<String> {
+        self.bloqueadas
+            .lock()
+            .map(|b| b.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for MonitorDescargas {
+    fn drop(&mut self) {
+        self.tarea.abort();
+    }
+}
+
+/// Espera hasta que aparezcan `esperados` PDFs completos que no estaban en `previos`
+/// y no queden archivos .crdownload durante un par de segundos. Contar los PDFs
+/// evita dar por terminada una ráfaga cuando una descarga termina justo antes de
+/// que empiece la siguiente.
 /// Retorna true si las descargas terminaron, false si se agotó el tiempo
-async fn esperar_descargas_completas(ruta_descargas: &PathBuf, timeout_secs: u64) -> bool {
+pub(crate) async fn esperar_descargas_completas(
+    ruta_descargas: &Path,
+    previos: &HashSet<String>,
+    esperados: usize,
+    timeout_secs: u64,
+) -> bool {
     let inicio = std::time::Instant::now();
     let timeout = Duration::from_secs(timeout_secs);
     let tiempo_estabilidad = Duration::from_secs(2); // Debe estar estable 2 segundos sin .crdownload
@@ -54,12 +164,13 @@ async fn esperar_descargas_completas(ruta_descargas: &PathBuf, timeout_secs: u64
         } else {
             false
         };
+        let completos = pdfs_descargados(ruta_descargas).difference(previos).count();
 
-        if hay_pendientes {
-            // Si hay pendientes, reseteamos el contador de estabilidad
+        if hay_pendientes || completos < esperados {
+            // Si faltan descargas, reseteamos el contador de estabilidad
             inicio_estabilidad = None;
         } else {
-            // Si no hay pendientes, iniciamos o chequeamos el contador de estabilidad
+            // Si están todas, iniciamos o chequeamos el contador de estabilidad
             match inicio_estabilidad {
                 None => {
                     inicio_estabilidad = Some(std::time::Instant::now());
@@ -80,36 +191,771 @@ async fn esperar_descargas_completas(ruta_descargas: &PathBuf, timeout_secs: u64
     }
 }
 
+/// URL de la aplicación de Comunicaciones Oficiales de SADE
+const URL_SADE: &str = "http://euc.gcba.gob.ar/ccoo-web/";
+
+/// Nombre de los adjuntos descargados: `NO-YYYY-NNNN-GCABA-XXX.pdf`, con el sufijo
+/// ` (n)` que agrega Chrome cuando el archivo ya existe
+const PATRON_ADJUNTO: &str = r"^NO-\d{4}-\d+-GCABA-[A-Za-z0-9]+( \(\d+\))?\.pdf$";
+
+/// Comunicaciones por página que se eligen en la bandeja (opción "100 elementos")
+const ELEMENTOS_POR_PAGINA: u32 = 100;
+
+/// Texto del paginador de la bandeja: `[ 1 - 100 / 523 ]`
+const PATRON_PAGINACION: &str = r"(\d+)\s*-\s*(\d+)\s*/\s*(\d+)";
+
+/// Divide la lista ordenada de comunicaciones en hasta `partes` bloques consecutivos
+fn dividir_lista(numeros: &[u32], partes: usize) -> Vec<&[u32]> {
+    if numeros.is_empty() {
+        return Vec::new();
+    }
+    let tamano = numeros.len().div_ceil(partes.max(1));
+    numeros.chunks(tamano).collect()
+}
+
+/// Máximo de comunicaciones de una corrida: evita reservar memoria sin límite si se
+/// tipea un rango como `1-4000000000`
+const MAXIMO_COMUNICACIONES: u32 = 10_000;
+
+/// Interpreta una lista de comunicaciones pegada por el usuario (por ejemplo de un
+/// mail): números separados por espacios, comas, punto y coma o saltos de línea,
+/// y rangos `inicio-final`. Devuelve los números ordenados y sin repetir.
+pub fn parsear_lista_comunicaciones(texto: &str) -> Result<Vec<u32>> {
+    let mut numeros = Vec::new();
+    for parte in texto
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|p| !p.is_empty())
+    {
+        let numero = |s: &str| {
+            s.trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n >= 1)
+                .with_context(|| format!("'{}' no es un número de comunicación válido", parte))
+        };
+        match parte.split_once('-') {
+            Some((desde, hasta)) => {
+                let (desde, hasta) = (numero(desde)?, numero(hasta)?);
+                if desde > hasta {
+                    anyhow::bail!("El rango '{}' está invertido", parte);
+                }
+                if (hasta - desde) as usize + numeros.len() >= MAXIMO_COMUNICACIONES as usize {
+                    anyhow::bail!(
+                        "El rango '{}' supera el máximo de {} comunicaciones por corrida",
+                        parte,
+                        MAXIMO_COMUNICACIONES
+                    );
+                }
+                numeros.extend(desde..=hasta);
+            }
+            None => numeros.push(numero(parte)?),
+        }
+    }
+    if numeros.len() > MAXIMO_COMUNICACIONES as usize {
+        anyhow::bail!(
+            "La lista supera el máximo de {} comunicaciones por corrida",
+            MAXIMO_COMUNICACIONES
+        );
+    }
+    numeros.sort_unstable();
+    numeros.dedup();
+    Ok(numeros)
+}
+
+/// Comunicaciones del rango `inicio`-`final_`, validando que empiece en 1 o más,
+/// que no esté invertido y que no supere `MAXIMO_COMUNICACIONES`
+pub fn rango_comunicaciones(inicio: u32, final_: u32) -> Result<Vec<u32>> {
+    if inicio == 0 {
+        anyhow::bail!("Las comunicaciones se numeran desde 1: la inicial no puede ser 0");
+    }
+    if final_ < inicio {
+        anyhow::bail!(
+            "La comunicación final ({}) es menor que la inicial ({})",
+            final_,
+            inicio
+        );
+    }
+    if final_ - inicio >= MAXIMO_COMUNICACIONES {
+        anyhow::bail!(
+            "El rango {}-{} supera el máximo de {} comunicaciones por corrida",
+            inicio,
+            final_,
+            MAXIMO_COMUNICACIONES
+        );
+    }
+    Ok((inicio..=final_).collect())
+}
+
+/// Página de la bandeja (contando desde 0) y posición dentro de ella de la
+/// comunicación `numero` (contando desde 1)
+fn ubicacion_en_bandeja(numero: u32, por_pagina: u32) -> (u32, usize) {
+    let posicion = numero.saturating_sub(1);
+    let por_pagina = por_pagina.max(1);
+    (posicion / por_pagina, (posicion % por_pagina) as usize)
+}
+
+/// Nombres de los archivos de la carpeta de descargas que cumplen `filtro`
+fn archivos_descargados(ruta_descargas: &Path, filtro: impl Fn(&str) -> bool) -> HashSet<String> {
+    std::fs::read_dir(ruta_descargas)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|e| e.file_name().to_str().map(str::to_string))
+                .filter(|nombre| filtro(nombre))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Nombres de los adjuntos presentes en la carpeta de descargas
+fn adjuntos_descargados(ruta_descargas: &Path, patron: &Regex) -> HashSet<String> {
+    archivos_descargados(ruta_descargas, |nombre| patron.is_match(nombre))
+}
+
+/// Nombres de los PDFs completos (ya sin `.crdownload`) de la carpeta de descargas
+pub fn pdfs_descargados(ruta_descargas: &Path) -> HashSet<String> {
+    archivos_descargados(ruta_descargas, |nombre| {
+        nombre.to_lowercase().ends_with(".pdf")
+    })
+}
+
+/// Espera a que aparezcan las pestañas de la aplicación tras el login, en lugar
+/// de un tiempo fijo, y usa lo que tardaron para calibrar las esperas siguientes
+pub(crate) async fn esperar_login(page: &Page) {
+    let tope = esperas::escalar(Duration::from_secs(3)) * 3;
+    let tardanza = esperas::esperar_elemento(page, &selectores::actuales().pestana, tope).await;
+    esperas::calibrar(tardanza.unwrap_or(tope), esperas::REFERENCIA_LOGIN);
+    // Margen para que ZK termine de armar la página
+    esperas::esperar(Duration::from_millis(500)).await;
+}
+
+/// Completa el formulario de login de SADE y lo envía
+async fn ingresar_credenciales(page: &Page, usuario: &str, password: &str) -> Result<()> {
+    let inputs = page
+        .find_elements(&selectores::actuales().campo_login)
+        .await?;
+    if inputs.len() < 2 {
+        anyhow::bail!("No se encontraron los campos de login");
+    }
+    inputs[0].click().await?.type_str(usuario).await?;
+    inputs[1].click().await?.type_str(password).await?;
+
+    // Click en botón de login
+    let login_btn = page
+        .find_element(&selectores::actuales().boton_login)
+        .await?;
+    login_btn.click().await?;
+    Ok(())
+}
+
+/// Indica si SADE volvió a mostrar el formulario de login (sesión expirada):
+/// están los campos de usuario y contraseña y ya no están las pestañas de la aplicación
+async fn sesion_expirada(page: &Page) -> bool {
+    let campos = page
+        .find_elements(&selectores::actuales().campo_login)
+        .await
+        .map(|e| e.len())
+        .unwrap_or(0);
+    let pestanas = page
+        .find_elements(&selectores::actuales().pestana)
+        .await
+        .map(|e| e.len())
+        .unwrap_or(0);
+    campos >= 2 && pestanas == 0
+}
+
+/// Lee el paginador de la bandeja: (primera comunicación mostrada, última, total).
+/// Retorna None si no hay un paginador legible.
+async fn leer_paginacion(page: &Page) -> Option<(u32, u32, u32)> {
+    let regex = Regex::new(PATRON_PAGINACION).ok()?;
+    // Otros paneles ZK pueden tener paginadores ocultos con otros totales
+    let info = localizar_visible(page, &selectores::actuales().info_paginador, 0)
+        .await
+        .ok()??;
+    let texto = info.inner_text().await.ok()??;
+    let caps = regex.captures(&texto)?;
+    Some((
+        caps[1].parse().ok()?,
+        caps[2].parse().ok()?,
+        caps[3].parse().ok()?,
+    ))
+}
+
+/// Comunicaciones por página según el paginador de la bandeja.
+/// Retorna None si no se puede leer o si todo entra en una sola página.
+async fn elementos_por_pagina(page: &Page) -> Option<u32> {
+    let (desde, hasta, total) = leer_paginacion(page).await?;
+    (hasta < total && desde <= hasta).then(|| hasta - desde + 1)
+}
+
+/// Página que muestra la bandeja (contando desde 0), según la primera comunicación
+/// del paginador
+async fn pagina_actual(page: &Page, por_pagina: u32) -> Option<u32> {
+    let (desde, _, _) = leer_paginacion(page).await?;
+    Some(ubicacion_en_bandeja(desde, por_pagina).0)
+}
+
+/// Abre la Bandeja CO, la configura para mostrar `ELEMENTOS_POR_PAGINA` comunicaciones
+/// por página y devuelve las que efectivamente muestra, para no asumir que el click
+/// en "100 elementos" surtió efecto
+async fn abrir_bandeja(page: &Page) -> Result<u32> {
+    if let Some(tab) =
+        localizar_por_texto(page, &selectores::actuales().pestana, &["Bandeja CO"], 3).await?
+    {
+        tab.click().await?;
+    }
+
+    esperas::esperar(Duration::from_secs(2)).await;
+
+    // Seleccionar ver 100 elementos
+    if let Some(boton) = localizar_por_texto(
+        page,
+        &selectores::actuales().boton_sin_caja,
+        &["100", "Ver 100"],
+        27,
+    )
+    .await?
+    {
+        boton.click().await?;
+    }
+
+    esperas::esperar(Duration::from_secs(4)).await;
+
+    match elementos_por_pagina(page).await {
+        Some(n) if n != ELEMENTOS_POR_PAGINA => {
+            registrar!(
+                "La bandeja muestra {} comunicaciones por página en lugar de {}; se usa {}",
+                n,
+                ELEMENTOS_POR_PAGINA,
+                n
+            );
+            Ok(n)
+        }
+        _ => Ok(ELEMENTOS_POR_PAGINA),
+    }
+}
+
+/// Avanza a la página siguiente de la bandeja
+async fn pagina_siguiente(page: &Page, espera: Duration) -> Result<()> {
+    if let Some(siguiente) =
+        localizar_visible(page, &selectores::actuales().pagina_siguiente, 5).await?
+    {
+        siguiente.click().await?;
+        sleep(espera).await;
+    }
+    Ok(())
+}
+
+/// Vuelve a la página anterior de la bandeja
+async fn pagina_anterior(page: &Page, espera: Duration) -> Result<()> {
+    if let Some(anterior) = primer_visible(page, &selectores::actuales().pagina_anterior).await? {
+        anterior.click().await?;
+        sleep(espera).await;
+    }
+    Ok(())
+}
+
+/// Clicks que se repiten cuando el paginador muestra que la página no cambió
+const REINTENTOS_PAGINACION: u32 = 3;
+
+/// Lleva la bandeja de la página `actual` a `objetivo` (contando desde 0) y verifica
+/// con el paginador que cada click surtió efecto, repitiéndolo si la página no
+/// cambió y retrocediendo si se pasó. Sin paginador legible se confía en el click.
+/// Devuelve la página en que quedó.
+async fn ir_a_pagina(
+    page: &Page,
+    mut actual: u32,
+    objetivo: u32,
+    por_pagina: u32,
+    espera: Duration,
+) -> Result<u32> {
+    let mut reintentos = 0;
+    while actual != objetivo {
+        let avanzar = actual < objetivo;
+        if avanzar {
+            pagina_siguiente(page, espera).await?;
+        } else {
+            pagina_anterior(page, espera).await?;
+        }
+
+        match pagina_actual(page, por_pagina).await {
+            Some(leida) if leida == actual => {
+                reintentos += 1;
+                if reintentos > REINTENTOS_PAGINACION {
+                    anyhow::bail!(
+                        "La bandeja no pasó de la página {} a la {}",
+                        actual + 1,
+                        objetivo + 1
+                    );
+                }
+                registrar!(
+                    "La bandeja sigue en la página {}, reintentando ({}/{})",
+                    actual + 1,
+                    reintentos,
+                    REINTENTOS_PAGINACION
+                );
+                sleep(espera).await;
+            }
+            Some(leida) => {
+                reintentos = 0;
+                actual = leida;
+            }
+            None if avanzar => actual += 1,
+            None => actual -= 1,
+        }
+    }
+    Ok(actual)
+}
+
+/// Descarga los adjuntos de la comunicación en la posición `indice` de la página actual.
+/// Devuelve `true` solo si aparecieron adjuntos nuevos en la carpeta de descargas.
+/// El semáforo `descargas` evita que dos pestañas descarguen a la vez, para que la
+/// espera de archivos `.crdownload` y los archivos nuevos correspondan a una sola
+/// comunicación.
+async fn descargar_comunicacion(
+    page: &Page,
+    num_comunicacion: u32,
+    indice: usize,
+    ruta_descargas: &Path,
+    descargas: &Semaphore,
+    patron_adjunto: &Regex,
+) -> Result<bool> {
+    // Hacer clic en la comunicación
+    let search_icons = page
+        .find_elements(&selectores::actuales().icono_detalle)
+        .await?;
+    if search_icons.len() > indice {
+        if let Err(e) = search_icons[indice].click().await {
+            registrar!(
+                "Error al hacer clic en comunicación {}: {}",
+                num_comunicacion,
+                e
+            );
+            return Ok(false);
+        }
+    }
+
+    esperas::esperar(Duration::from_secs(1)).await;
+
+    // Descargar archivos adjuntos
+    let permiso = descargas.acquire().await?;
+    let antes = adjuntos_descargados(ruta_descargas, patron_adjunto);
+    let pdfs_previos = pdfs_descargados(ruta_descargas);
+    let mut esperados = 0;
+    loop {
+        esperas::esperar(Duration::from_secs(1)).await;
+        let download_icons = page
+            .find_elements(&selectores::actuales().icono_descarga)
+            .await?;
+
+        if download_icons.is_empty() {
+            break;
+        }
+
+        // Descargar solo el primer archivo (índice 1), si existe
+        if download_icons.len() > 1 {
+            match download_icons[1].click().await {
+                Ok(_) => esperados += 1,
+                Err(e) => registrar!("Error descargando el primer archivo: {}", e),
+            }
+            // Espera mínima para que el navegador procese
+            sleep(Duration::from_millis(300)).await;
+        }
+
+        // Espera inicial para asegurar que Chrome cree los archivos .crdownload
+        esperas::esperar(Duration::from_secs(1)).await;
+
+        // Esperar a que las descargas terminen (verificando archivos .crdownload)
+        // Timeout reducido a 10s por pedido del usuario
+        let timeout_descarga = 1;
+        if !esperar_descargas_completas(ruta_descargas, &pdfs_previos, esperados, timeout_descarga)
+            .await
+        {
+            registrar!("Advertencia: Algunas descargas pueden no haber terminado");
+        }
+
+        // Verificar si hay más páginas de adjuntos (paginador de la ventana de detalle)
+        let siguiente =
+            match primer_visible(page, &selectores::actuales().pagina_siguiente_adjuntos).await? {
+                Some(boton) => Some(boton),
+                None => {
+                    respaldo_por_indice(
+                        page,
+                        &selectores::actuales().pagina_siguiente,
+                        1,
+                        "el paginador de adjuntos",
+                    )
+                    .await?
+                }
+            };
+        match siguiente {
+            Some(boton) => {
+                if boton.click().await.is_err() {
+                    break;
+                }
+                esperas::esperar(Duration::from_secs(1)).await;
+            }
+            None => break,
+        }
+    }
+    let contar_nuevos = || {
+        adjuntos_descargados(ruta_descargas, patron_adjunto)
+            .difference(&antes)
+            .count()
+    };
+    let mut nuevos = contar_nuevos();
+    if nuevos == 0 {
+        // Dar un margen a descargas lentas antes de darla por fallida
+        esperar_descargas_completas(ruta_descargas, &pdfs_previos, esperados.max(1), 10).await;
+        nuevos = contar_nuevos();
+    }
+    drop(permiso);
+
+    // Volver a la lista
+    if let Some(volver) =
+        localizar_por_texto(page, &selectores::actuales().boton, &["Volver"], 0).await?
+    {
+        volver.click().await?;
+    }
+
+    esperas::esperar(Duration::from_secs(1)).await;
+
+    if nuevos == 0 {
+        registrar!(
+            "No se encontraron archivos descargados para la comunicación {}",
+            num_comunicacion
+        );
+    }
+    Ok(nuevos > 0)
+}
+
+/// Abre la bandeja y avanza hasta la página que contiene la comunicación `numero`.
+/// Devuelve las comunicaciones por página de la bandeja.
+async fn posicionar_en_pagina(page: &Page, numero: u32, on_status: &dyn Fn(&str)) -> Result<u32> {
+    let por_pagina = abrir_bandeja(page).await?;
+
+    // Página de la comunicación, verificando la posición con el paginador
+    let (objetivo, _) = ubicacion_en_bandeja(numero, por_pagina);
+    let actual = pagina_actual(page, por_pagina).await.unwrap_or(0);
+    if actual != objetivo {
+        on_status(&format!("Avanzando a página {}...", objetivo + 1));
+        let espera = esperas::escalar(Duration::from_secs(2));
+        ir_a_pagina(page, actual, objetivo, por_pagina, espera).await?;
+    }
+    Ok(por_pagina)
+}
+
+/// Vuelve a iniciar sesión tras una expiración y se reposiciona en la página
+/// de la comunicación `numero`. Devuelve las comunicaciones por página de la bandeja.
+async fn restablecer_sesion(
+    page: &Page,
+    credenciales: &Credenciales,
+    numero: u32,
+    on_status: &dyn Fn(&str),
+) -> Result<u32> {
+    on_status("La sesión de SADE expiró, iniciando sesión nuevamente...");
+    registrar!("Sesión expirada antes de la comunicación {}", numero);
+    ingresar_credenciales(page, &credenciales.usuario, &credenciales.password).await?;
+    esperar_login(page).await;
+    posicionar_en_pagina(page, numero, on_status).await
+}
+
+/// Cada cuánto se revisa si se reanudó una descarga en pausa
+const INTERVALO_PAUSA: Duration = Duration::from_millis(500);
+
+/// Mientras `pausado` esté activo espera sin cerrar el navegador, antes de pasar
+/// a la comunicación `numero`. Un pedido de cancelación corta la pausa.
+async fn esperar_reanudacion(
+    pausado: &AtomicBool,
+    cancelar: &AtomicBool,
+    numero: u32,
+    on_status: &dyn Fn(&str),
+) {
+    if !pausado.load(Ordering::Relaxed) {
+        return;
+    }
+    registrar!("Descarga en pausa antes de la comunicación {}", numero);
+    on_status(&format!(
+        "En pausa, se continúa con la comunicación {} al reanudar",
+        numero
+    ));
+    while pausado.load(Ordering::Relaxed) && !cancelar.load(Ordering::Relaxed) {
+        sleep(INTERVALO_PAUSA).await;
+    }
+    if !cancelar.load(Ordering::Relaxed) {
+        registrar!("Descarga reanudada en la comunicación {}", numero);
+        on_status(&format!("Reanudando en la comunicación {}...", numero));
+    }
+}
+
+/// Descarga el bloque de comunicaciones `numeros` (ordenado) en una pestaña ya
+/// logueada: abre la bandeja, se posiciona en la página de la primera y avanza
+/// de página a medida que lo piden las siguientes
+#[allow(clippy::too_many_arguments)]
+async fn descargar_bloque(
+    page: &Page,
+    credenciales: &Credenciales,
+    numeros: &[u32],
+    ruta_descargas: &Path,
+    descargas: &Semaphore,
+    patron_adjunto: &Regex,
+    cancelar: &AtomicBool,
+    pausado: &AtomicBool,
+    espacio_minimo: u64,
+    sin_espacio: &AtomicBool,
+    limite: Option<Instant>,
+    tiempo_excedido: &AtomicBool,
+    on_inicio: &dyn Fn(u32),
+    on_status: &dyn Fn(&str),
+    on_terminada: &dyn Fn(u32, bool),
+) -> Result<()> {
+    let Some(&primera) = numeros.first() else {
+        return Ok(());
+    };
+    on_status("Navegando a Bandeja CO...");
+    let mut por_pagina = posicionar_en_pagina(page, primera, on_status).await?;
+    let (mut pagina_en_curso, _) = ubicacion_en_bandeja(primera, por_pagina);
+
+    for &num_comunicacion in numeros {
+        esperar_reanudacion(pausado, cancelar, num_comunicacion, on_status).await;
+        if cancelar.load(Ordering::Relaxed)
+            || sin_espacio.load(Ordering::Relaxed)
+            || tiempo_excedido.load(Ordering::Relaxed)
+        {
+            break;
+        }
+        if limite.is_some_and(|l| Instant::now() >= l) {
+            registrar!(
+                "Tiempo máximo excedido antes de la comunicación {}",
+                num_comunicacion
+            );
+            tiempo_excedido.store(true, Ordering::Relaxed);
+            break;
+        }
+        // En lotes largos el disco se puede llenar a mitad de camino
+        if let Err(e) = verificar_espacio(ruta_descargas, espacio_minimo) {
+            registrar!("{}", e);
+            on_status(&e.to_string());
+            sin_espacio.store(true, Ordering::Relaxed);
+            break;
+        }
+        on_inicio(num_comunicacion);
+
+        // Avanzar hasta la página de la comunicación (puede saltear páginas enteras)
+        let (objetivo, mut indice_actual) = ubicacion_en_bandeja(num_comunicacion, por_pagina);
+        if pagina_en_curso != objetivo {
+            pagina_en_curso = ir_a_pagina(
+                page,
+                pagina_en_curso,
+                objetivo,
+                por_pagina,
+                esperas::escalar(Duration::from_secs(1)),
+            )
+            .await?;
+        }
+
+        esperas::esperar(Duration::from_secs(1)).await;
+
+        // Chequear que la sesión siga activa antes de cada comunicación
+        if sesion_expirada(page).await {
+            por_pagina =
+                restablecer_sesion(page, credenciales, num_comunicacion, on_status).await?;
+            (pagina_en_curso, indice_actual) = ubicacion_en_bandeja(num_comunicacion, por_pagina);
+        }
+
+        let mut descargada = descargar_comunicacion(
+            page,
+            num_comunicacion,
+            indice_actual,
+            ruta_descargas,
+            descargas,
+            patron_adjunto,
+        )
+        .await?;
+
+        // Si la sesión expiró durante la descarga, reintentar una vez tras re-loguear
+        if !descargada && sesion_expirada(page).await {
+            por_pagina =
+                restablecer_sesion(page, credenciales, num_comunicacion, on_status).await?;
+            (pagina_en_curso, indice_actual) = ubicacion_en_bandeja(num_comunicacion, por_pagina);
+            descargada = descargar_comunicacion(
+                page,
+                num_comunicacion,
+                indice_actual,
+                ruta_descargas,
+                descargas,
+                patron_adjunto,
+            )
+            .await?;
+        }
+        on_terminada(num_comunicacion, descargada);
+    }
+
+    Ok(())
+}
+
 /// Resultado de la descarga de comunicaciones
 #[derive(Debug, Default)]
 pub struct ResultadoDescarga {
     pub comunicaciones_procesadas: u32,
     pub total_comunicaciones: u32,
+    /// Comunicaciones para las que no apareció ningún archivo descargado
+    pub fallidas: Vec<u32>,
+    /// La descarga se detuvo antes de terminar por pedido del usuario
+    pub cancelada: bool,
+    /// Archivos cuya descarga Chrome canceló o bloqueó
+    pub descargas_bloqueadas: Vec<String>,
+    /// La descarga se detuvo porque la carpeta de descargas se quedó sin espacio
+    pub sin_espacio: bool,
 }
 
-/// Descarga comunicaciones desde SADE
+/// Mensaje de error si el disco de `ruta` tiene menos de `minimo` bytes libres.
+/// Si el espacio no se puede consultar se registra y se sigue.
+fn verificar_espacio(ruta: &Path, minimo: u64) -> Result<()> {
+    if minimo == 0 {
+        return Ok(());
+    }
+    match fs2::available_space(ruta) {
+        Ok(libre) if libre < minimo => anyhow::bail!(
+            "Espacio insuficiente en {}: quedan {} MB libres y se necesitan al menos {} MB \
+             (SADE_ESPACIO_MINIMO_MB)",
+            ruta.display(),
+            libre / BYTES_POR_MB,
+            minimo / BYTES_POR_MB
+        ),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            registrar!("No se pudo consultar el espacio libre en {:?}: {}", ruta, e);
+            Ok(())
+        }
+    }
+}
+
+/// Descarga el rango de comunicaciones `inicio..=final_` desde SADE
 /// Equivalente a `descargar_comunicaciones` en Python
+#[allow(clippy::too_many_arguments)]
 pub async fn descargar_comunicaciones(
     inicio: u32,
     final_: u32,
     usuario_id: &str,
     config: &Config,
+    cancelar: &AtomicBool,
+    pausado: &AtomicBool,
+    timeout_global: Option<Duration>,
     on_status: impl Fn(&str),
+    on_progreso: impl Fn(u32, u32),
 ) -> Result<ResultadoDescarga> {
+    let numeros = rango_comunicaciones(inicio, final_)?;
+    descargar_lista(
+        &numeros,
+        usuario_id,
+        config,
+        cancelar,
+        pausado,
+        timeout_global,
+        on_status,
+        on_progreso,
+    )
+    .await
+}
+
+/// Si el proxy pide usuario y contraseña, hace que la pestaña responda al pedido
+/// de autenticación con los configurados. Se llama antes de navegar.
+pub(crate) async fn autenticar_proxy(page: &Page, config: &Config) -> Result<()> {
+    let Some((usuario, password)) = config.proxy.as_ref().and_then(|p| p.credenciales.clone())
+    else {
+        return Ok(());
+    };
+    page.authenticate(chromiumoxide::auth::Credentials {
+        username: usuario,
+        password,
+    })
+    .await
+    .context("No se pudo configurar la autenticación del proxy")
+}
+
+/// Cierra el navegador, o lo deja abierto si `mantener_abierto` para revisar a
+/// mano el estado final de SADE. Devuelve `true` si se cerró, es decir si ya se
+/// puede borrar el perfil temporal.
+pub(crate) fn cerrar_navegador(
+    browser: Browser,
+    handle: JoinHandle<()>,
+    mantener_abierto: bool,
+) -> bool {
+    if mantener_abierto {
+        // Soltar el Browser mataría a Chrome (se lanza con kill_on_drop), así que
+        // se lo olvida; el handler sigue hasta que el usuario cierre la ventana
+        std::mem::forget(browser);
+        return false;
+    }
+    drop(browser);
+    handle.abort();
+    true
+}
+
+/// Descarga desde SADE una lista arbitraria de comunicaciones (se ordena y se
+/// quitan las repetidas antes de recorrerla).
+/// Con `timeout_global`, si la corrida excede ese tiempo desde el inicio se corta
+/// (también durante el arranque del navegador y el login), se cierra el navegador
+/// aunque la configuración pida mantenerlo abierto, se borra el perfil y se
+/// devuelve un error; el tiempo en pausa (mientras `pausado` esté activo) también cuenta.
+#[allow(clippy::too_many_arguments)]
+pub async fn descargar_lista(
+    numeros: &[u32],
+    usuario_id: &str,
+    config: &Config,
+    cancelar: &AtomicBool,
+    pausado: &AtomicBool,
+    timeout_global: Option<Duration>,
+    on_status: impl Fn(&str),
+    on_progreso: impl Fn(u32, u32),
+) -> Result<ResultadoDescarga> {
+    let limite = timeout_global.map(|t| Instant::now() + t);
+    let mut numeros = numeros.to_vec();
+    numeros.sort_unstable();
+    numeros.dedup();
+    if numeros.contains(&0) {
+        anyhow::bail!("Las comunicaciones se numeran desde 1");
+    }
+    if numeros.is_empty() {
+        anyhow::bail!("No hay comunicaciones para descargar");
+    }
+
     let credenciales = config
         .get_credenciales(usuario_id)
         .context("Credenciales de usuario no encontradas")?;
 
-    // Obtener carpeta de descargas
-    let ruta_descargas = dirs::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    // Antes de crear el perfil, para no dejarlo huérfano si falta Chrome
+    let chrome = verificar_chrome()?;
+
+    // Carpeta de descargas propia del usuario
+    if let (alternativa, true) = config.ruta_descargas_efectiva() {
+        on_status(&format!(
+            "No se puede escribir en la carpeta de descargas {}, se usa {}",
+            config.ruta_descargas.display(),
+            alternativa.display()
+        ));
+    }
+    let ruta_descargas = config.ruta_descargas_usuario(usuario_id)?;
+    verificar_espacio(&ruta_descargas, config.espacio_minimo_descarga)?;
 
     // Configurar perfil personalizado para preferencias
-    let user_data_dir = setup_custom_profile()?;
+    let perfil = PerfilEnUso::marcar(crear_perfil(
+        PREFIJO_PERFIL_DESCARGA,
+        &ruta_descargas,
+        config,
+        usuario_id,
+    )?)?;
+    let user_data_dir = perfil.ruta().to_path_buf();
 
-    // Configurar navegador con opciones para permitir descargas inseguras
+    // Configurar navegador con opciones para permitir descargas inseguras. El puerto
+    // de depuración queda en 0 (lo elige Chrome), así que otra instancia no choca
     let browser_config = BrowserConfig::builder()
+        .chrome_executable(chrome)
         .user_data_dir(&user_data_dir)
         .with_head() // Mostrar navegador (no headless)
+        .args(config.argumentos_ventana())
+        .args(config.argumentos_proxy())
         // Suprimir popups y diálogos
         .arg("--no-first-run")
         .arg("--no-default-browser-check")
@@ -138,240 +984,295 @@ pub async fn descargar_comunicaciones(
         .build()
         .map_err(|e| anyhow::anyhow!("Error al configurar navegador: {}", e))?;
 
-    let (browser, mut handler) = Browser::launch(browser_config)
-        .await
-        .context("Error al iniciar navegador")?;
-
-    // Manejar eventos del navegador en segundo plano
-    let handle = tokio::spawn(async move { while let Some(_event) = handler.next().await {} });
-
-    let page = browser
-        .new_page("about:blank")
-        .await
-        .context("Error al crear página")?;
-
-    // Navegar a SADE
-    on_status("Navegando a SADE...");
-    page.goto("http://euc.gcba.gob.ar/ccoo-web/")
-        .await
-        .context("Error al navegar a SADE")?;
-
-    sleep(Duration::from_secs(2)).await;
+    // Contadores compartidos con la sesión, para informar lo hecho si se corta
+    let total_comunicaciones = numeros.len() as u32;
+    let terminadas = Cell::new(0u32);
+    let fallidas = RefCell::new(Vec::new());
+    let sin_espacio = AtomicBool::new(false);
+    let tiempo_excedido = AtomicBool::new(false);
 
-    // Login
-    on_status("Iniciando sesión...");
+    let sesion = async {
+        let (browser, mut handler) = Browser::launch(browser_config)
+            .await
+            .context("Error al iniciar navegador")?;
 
-    // Buscar campos de texto
-    let mut inputs = page.find_elements(".form-control.z-textbox").await?;
+        // Manejar eventos del navegador en segundo plano
+        let handle = tokio::spawn(async move { while let Some(_event) = handler.next().await {} });
 
-    // Si no hay campos de login, probablemente hay una sesión activa - hacer logout
-    if inputs.len() < 2 {
-        on_status("Sesión existente detectada, cerrando sesión...");
+        // Autorizar descargas sin avisos y registrar las que Chrome bloquee
+        let monitor = MonitorDescargas::iniciar(&browser, &ruta_descargas).await?;
 
-        // Buscar y hacer clic en el botón de logout
-        let logout_btn = page
-            .find_elements(".z-icon-sign-out.texto-header-unificado.z-span")
-            .await?;
-        if !logout_btn.is_empty() {
-            logout_btn[0].click().await?;
+        let page = browser
+            .new_page("about:blank")
+            .await
+            .context("Error al crear página")?;
+        autenticar_proxy(&page, config).await?;
 
-            // Esperar a que se complete el logout
-            sleep(Duration::from_secs(2)).await;
+        // Navegar a SADE, midiendo cuánto tarda para ajustar las esperas
+        on_status("Navegando a SADE...");
+        esperas::reiniciar();
+        let inicio_carga = Instant::now();
+        page.goto(URL_SADE)
+            .await
+            .context("Error al navegar a SADE")?;
+        esperas::calibrar(inicio_carga.elapsed(), esperas::REFERENCIA_CARGA);
 
-            // Navegar de nuevo a la página para tener un estado limpio
-            on_status("Navegando a SADE nuevamente...");
-            page.goto("http://euc.gcba.gob.ar/ccoo-web/")
-                .await
-                .context("Error al navegar a SADE después del logout")?;
+        esperas::esperar(Duration::from_secs(2)).await;
 
-            sleep(Duration::from_secs(2)).await;
+        // Login
+        on_status("Iniciando sesión...");
 
-            // Volver a buscar los campos de login
-            inputs = page.find_elements(".form-control.z-textbox").await?;
-        }
-    }
-
-    // Ahora hacer login
-    if inputs.len() >= 2 {
-        on_status("Ingresando credenciales...");
-        inputs[0]
-            .click()
-            .await?
-            .type_str(&credenciales.usuario)
-            .await?;
-        inputs[1]
-            .click()
-            .await?
-            .type_str(&credenciales.password)
+        // Buscar campos de texto
+        let mut inputs = page
+            .find_elements(&selectores::actuales().campo_login)
             .await?;
 
-        // Click en botón de login
-        let login_btn = page.find_element(".btn.btn-default.z-button").await?;
-        login_btn.click().await?;
-    } else {
-        anyhow::bail!("No se encontraron los campos de login después de intentar logout");
-    }
+        // Si no hay campos de login, probablemente hay una sesión activa - hacer logout
+        if inputs.len() < 2 {
+            on_status("Sesión existente detectada, cerrando sesión...");
 
-    sleep(Duration::from_secs(3)).await;
+            // Buscar y hacer clic en el botón de logout
+            let logout_btn = page
+                .find_elements(&selectores::actuales().cerrar_sesion)
+                .await?;
+            if !logout_btn.is_empty() {
+                logout_btn[0].click().await?;
 
-    // Navegar a Bandeja CO
-    on_status("Navegando a Bandeja CO...");
-    let tabs = page.find_elements(".z-tab-text").await?;
-    if tabs.len() > 3 {
-        tabs[3].click().await?;
-    }
-
-    sleep(Duration::from_secs(2)).await;
-
-    // Seleccionar ver 100 elementos
-    let botones = page.find_elements(".boton-sin-caja.z-button").await?;
-    if botones.len() > 27 {
-        botones[27].click().await?;
-    }
+                // Esperar a que se complete el logout
+                esperas::esperar(Duration::from_secs(2)).await;
 
-    sleep(Duration::from_secs(4)).await;
+                // Navegar de nuevo a la página para tener un estado limpio
+                on_status("Navegando a SADE nuevamente...");
+                page.goto(URL_SADE)
+                    .await
+                    .context("Error al navegar a SADE después del logout")?;
 
-    // Calcular páginas a avanzar
-    let paginas_completas = (inicio - 1) / 100;
+                esperas::esperar(Duration::from_secs(2)).await;
 
-    if paginas_completas > 0 {
-        on_status(&format!("Avanzando a página {}...", paginas_completas + 1));
-        for _ in 0..paginas_completas {
-            let next_btns = page.find_elements(".z-paging-button.z-paging-next").await?;
-            if next_btns.len() > 5 {
-                next_btns[5].click().await?;
-                sleep(Duration::from_secs(2)).await;
+                // Volver a buscar los campos de login
+                inputs = page
+                    .find_elements(&selectores::actuales().campo_login)
+                    .await?;
             }
         }
-    }
-
-    // Procesar comunicaciones
-    let mut comunicaciones_procesadas = 0u32;
-    let total_comunicaciones = final_ - inicio + 1;
 
-    for num_comunicacion in inicio..=final_ {
-        on_status(&format!(
-            "Descargando comunicación {} ({} de {})",
-            num_comunicacion,
-            comunicaciones_procesadas + 1,
-            total_comunicaciones
-        ));
-
-        let indice_actual = ((num_comunicacion - 1) % 100) as usize;
-
-        // Si llegamos al índice 0 y no es la primera comunicación, avanzar página
-        if indice_actual == 0 && num_comunicacion != inicio {
-            let next_btns = page.find_elements(".z-paging-button.z-paging-next").await?;
-            if next_btns.len() > 5 {
-                next_btns[5].click().await?;
-                sleep(Duration::from_secs(1)).await;
-            }
+        // Ahora hacer login
+        if inputs.len() >= 2 {
+            on_status("Ingresando credenciales...");
+            ingresar_credenciales(&page, &credenciales.usuario, &credenciales.password).await?;
+        } else {
+            anyhow::bail!("No se encontraron los campos de login después de intentar logout");
         }
 
-        sleep(Duration::from_secs(1)).await;
+        esperar_login(&page).await;
 
-        // Hacer clic en la comunicación
-        let search_icons = page.find_elements(".z-icon-search.z-span").await?;
-        if search_icons.len() > indice_actual {
-            if let Err(e) = search_icons[indice_actual].click().await {
-                eprintln!(
-                    "Error al hacer clic en comunicación {}: {}",
-                    num_comunicacion, e
-                );
-                continue;
-            }
+        // Repartir la lista entre las pestañas; las adicionales comparten la sesión
+        let bloques = dividir_lista(&numeros, config.pestanas_descarga);
+        let mut paginas = vec![page];
+        for _ in 1..bloques.len() {
+            on_status(&format!("Abriendo pestaña {}...", paginas.len() + 1));
+            let pestana = browser
+                .new_page("about:blank")
+                .await
+                .context("Error al abrir pestaña adicional")?;
+            autenticar_proxy(&pestana, config).await?;
+            pestana
+                .goto(URL_SADE)
+                .await
+                .context("Error al navegar a SADE en la pestaña adicional")?;
+            paginas.push(pestana);
         }
+        esperas::esperar(Duration::from_secs(2)).await;
 
-        sleep(Duration::from_secs(1)).await;
+        // Procesar comunicaciones
+        let descargas = Semaphore::new(1);
+        let patron_adjunto =
+            Regex::new(PATRON_ADJUNTO).context("Error al compilar regex de adjuntos")?;
+        on_progreso(0, total_comunicaciones);
 
-        // Descargar archivos adjuntos
-        loop {
-            sleep(Duration::from_secs(1)).await;
-            let download_icons = page.find_elements(".z-icon-download").await?;
-
-            if download_icons.is_empty() {
-                break;
+        let on_status = &on_status;
+        let on_progreso = &on_progreso;
+        let tareas = paginas.iter().zip(&bloques).map(|(pagina, &bloque)| {
+            let terminadas = &terminadas;
+            let fallidas = &fallidas;
+            let descargas = &descargas;
+            let patron_adjunto = &patron_adjunto;
+            let ruta_descargas = &ruta_descargas;
+            let sin_espacio = &sin_espacio;
+            let tiempo_excedido = &tiempo_excedido;
+            async move {
+                descargar_bloque(
+                    pagina,
+                    credenciales,
+                    bloque,
+                    ruta_descargas,
+                    descargas,
+                    patron_adjunto,
+                    cancelar,
+                    pausado,
+                    config.espacio_minimo_descarga,
+                    sin_espacio,
+                    limite,
+                    tiempo_excedido,
+                    &|num| {
+                        on_status(&format!(
+                            "Descargando comunicación {} ({} de {})",
+                            num,
+                            terminadas.get() + 1,
+                            total_comunicaciones
+                        ))
+                    },
+                    on_status,
+                    &|num, descargada| {
+                        if !descargada {
+                            fallidas.borrow_mut().push(num);
+                        }
+                        terminadas.set(terminadas.get() + 1);
+                        on_progreso(terminadas.get(), total_comunicaciones);
+                    },
+                )
+                .await
             }
+        });
+        for resultado in join_all(tareas).await {
+            resultado?;
+        }
+        Ok::<_, anyhow::Error>((browser, handle, monitor))
+    };
 
-            // Descargar solo el primer archivo (índice 1), si existe
-            if download_icons.len() > 1 {
-                if let Err(e) = download_icons[1].click().await {
-                    eprintln!("Error descargando el primer archivo: {}", e);
-                }
-                // Espera mínima para que el navegador procese
-                sleep(Duration::from_millis(300)).await;
+    // El límite corta todo: el arranque, el login o una espera colgada dentro de una
+    // comunicación. Al soltar la sesión se suelta el navegador, que cierra Chrome.
+    let navegador = match limite {
+        Some(l) => match tokio::time::timeout_at(l.into(), sesion).await {
+            Ok(resultado) => Some(resultado?),
+            Err(_) => {
+                registrar!("Tiempo máximo excedido, se corta la descarga en curso");
+                tiempo_excedido.store(true, Ordering::Relaxed);
+                None
             }
+        },
+        None => Some(sesion.await?),
+    };
 
-            // Espera inicial de 3s para asegurar que Chrome cree los archivos .crdownload
-            sleep(Duration::from_secs(1)).await;
+    let mut fallidas = fallidas.into_inner();
+    fallidas.sort_unstable();
+    let comunicaciones_procesadas = terminadas.get() - fallidas.len() as u32;
+    let cancelada = cancelar.load(Ordering::Relaxed);
+    let sin_espacio = sin_espacio.into_inner();
+    let tiempo_excedido = tiempo_excedido.into_inner();
 
-            // Esperar a que las descargas terminen (verificando archivos .crdownload)
-            // Timeout reducido a 10s por pedido del usuario
-            let timeout_descarga = 1;
-            if !esperar_descargas_completas(&ruta_descargas, timeout_descarga).await {
-                eprintln!("Advertencia: Algunas descargas pueden no haber terminado");
-            }
+    if cancelada || sin_espacio || tiempo_excedido {
+        on_status("Descarga cancelada, cerrando navegador...");
+    } else {
+        // Al terminar la última comunicación esperamos 5 segundos extra antes de cerrar
+        on_status("Esperando 5s extra por seguridad al ser la última comunicación...");
+        sleep(Duration::from_secs(5)).await;
+    }
 
-            // Verificar si hay más páginas de adjuntos
-            let next_btns = page.find_elements(".z-paging-button.z-paging-next").await?;
-            if next_btns.len() > 1 {
-                if next_btns[1].click().await.is_err() {
-                    break;
-                }
-                sleep(Duration::from_secs(1)).await;
-            } else {
-                break;
-            }
-        }
+    let mut descargas_bloqueadas = Vec::new();
+    let cerrado = match navegador {
+        Some((browser, handle, monitor)) => {
+            // Espera final breve antes de cerrar
+            on_status("Finalizando...");
+            sleep(Duration::from_secs(3)).await;
 
-        // Volver a la lista
-        let volver_btns = page.find_elements(".btn.z-button").await?;
-        if !volver_btns.is_empty() {
-            volver_btns[0].click().await?;
+            descargas_bloqueadas = monitor.bloqueadas();
+            drop(monitor);
+            // Una corrida cortada por tiempo no deja el navegador abierto
+            let mantener_abierto = config.mantener_navegador_abierto && !tiempo_excedido;
+            cerrar_navegador(browser, handle, mantener_abierto)
         }
+        // La sesión se soltó al vencer el límite y con ella se cerró el navegador
+        None => true,
+    };
 
-        sleep(Duration::from_secs(1)).await;
+    if cerrado {
+        // Dar tiempo al SO para liberar los archivos (Windows suele ser lento liberando locks)
+        sleep(Duration::from_secs(2)).await;
 
-        // Si es la última comunicación, esperamos 5 segundos extra antes de cerrar
-        if num_comunicacion == final_ {
-            on_status("Esperando 5s extra por seguridad al ser la última comunicación...");
-            sleep(Duration::from_secs(5)).await;
-        }
-
-        comunicaciones_procesadas += 1;
+        // Limpiar perfil temporal con reintentos
+        eliminar_perfil(perfil, config).await;
+    } else {
+        perfil.conservar();
+        on_status("El navegador queda abierto para revisar SADE; ciérrelo a mano al terminar");
     }
 
-    // Espera final breve antes de cerrar
-    on_status("Finalizando...");
-    sleep(Duration::from_secs(3)).await;
-
-    // Cerrar navegador
-    drop(browser);
-    handle.abort();
-
-    // Dar tiempo al SO para liberar los archivos (Windows suele ser lento liberando locks)
-    sleep(Duration::from_secs(2)).await;
-
-    // Limpiar perfil temporal con reintentos
-    let mut clean_retries = 5;
-    while clean_retries > 0 {
-        if let Err(e) = std::fs::remove_dir_all(&user_data_dir) {
-            if clean_retries == 1 {
-                eprintln!(
-                    "Advertencia: No se pudo limpiar el perfil temporal tras varios intentos: {}",
-                    e
-                );
-            } else {
-                sleep(Duration::from_secs(1)).await;
-            }
-        } else {
-            break;
-        }
-        clean_retries -= 1;
+    if tiempo_excedido {
+        anyhow::bail!(
+            "Tiempo máximo excedido: {} de {} comunicaciones descargadas",
+            comunicaciones_procesadas,
+            total_comunicaciones
+        );
     }
 
     Ok(ResultadoDescarga {
         comunicaciones_procesadas,
         total_comunicaciones,
+        fallidas,
+        cancelada,
+        descargas_bloqueadas,
+        sin_espacio,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsear_lista_con_numeros_y_rangos() {
+        assert_eq!(
+            parsear_lista_comunicaciones("15, 12\n20-22; 12").unwrap(),
+            vec![12, 15, 20, 21, 22]
+        );
+        assert!(parsear_lista_comunicaciones("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parsear_lista_rechaza_valores_invalidos() {
+        assert!(parsear_lista_comunicaciones("12, abc").is_err());
+        assert!(parsear_lista_comunicaciones("0").is_err());
+        assert!(parsear_lista_comunicaciones("25-20").is_err());
+        assert!(parsear_lista_comunicaciones("1-4000000000").is_err());
+        assert!(parsear_lista_comunicaciones("5, 1-10000").is_err());
+        assert_eq!(
+            parsear_lista_comunicaciones("1-10000").unwrap().len(),
+            MAXIMO_COMUNICACIONES as usize
+        );
+    }
+
+    #[test]
+    fn rango_valida_los_limites() {
+        assert_eq!(rango_comunicaciones(3, 5).unwrap(), vec![3, 4, 5]);
+        assert_eq!(rango_comunicaciones(1, 1).unwrap(), vec![1]);
+        assert!(rango_comunicaciones(0, 5).is_err());
+        assert!(rango_comunicaciones(5, 3).is_err());
+        assert!(rango_comunicaciones(1, u32::MAX).is_err());
+        assert_eq!(
+            rango_comunicaciones(u32::MAX - MAXIMO_COMUNICACIONES + 1, u32::MAX)
+                .unwrap()
+                .len(),
+            MAXIMO_COMUNICACIONES as usize
+        );
+    }
+
+    #[test]
+    fn ubicacion_en_bandeja_sin_desbordes() {
+        assert_eq!(ubicacion_en_bandeja(1, 100), (0, 0));
+        assert_eq!(ubicacion_en_bandeja(100, 100), (0, 99));
+        assert_eq!(ubicacion_en_bandeja(101, 100), (1, 0));
+        assert_eq!(ubicacion_en_bandeja(0, 100), (0, 0));
+        assert_eq!(ubicacion_en_bandeja(7, 0), (6, 0));
+    }
+
+    #[test]
+    fn dividir_lista_en_bloques_consecutivos() {
+        let numeros = [1, 2, 5, 8, 9];
+        assert_eq!(
+            dividir_lista(&numeros, 2),
+            vec![&[1, 2, 5][..], &[8, 9][..]]
+        );
+        assert_eq!(dividir_lista(&numeros, 10).len(), 5);
+        assert!(dividir_lista(&[], 3).is_empty());
+    }
+}